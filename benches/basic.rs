@@ -3,6 +3,7 @@ use gpu_compute::{
     an_external_function, do_virtual_call,
     execute_util::{ExecuteParameters, ExecuteUtil},
     execute_util_compute::{ComputeExecuteUtil, ComputeParameters},
+    executor::PluggableConstants,
     vulkan_util::VulkanData,
 };
 use nalgebra::Vector2;
@@ -43,7 +44,7 @@ fn criterion_benchmark(c: &mut Criterion) {
     });
 
     g.bench_function("run_shader", |b| {
-        let shader = none_sbuffer_loop::load(vulkan.device.clone()).unwrap();
+        let shader = vulkan.load_shader(none_sbuffer_loop::load);
         let mut execute = ExecuteUtil::<u32>::setup_storage_buffer(
             &mut vulkan,
             Vector2::new(1, 1),
@@ -64,12 +65,12 @@ fn criterion_benchmark(c: &mut Criterion) {
     });
 
     g.bench_function("run_compute_shader", |b| {
-        let shader = compute_none_sbuffer_loop::load(vulkan.device.clone()).unwrap();
+        let shader = vulkan.load_shader(compute_none_sbuffer_loop::load);
         let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
             &mut vulkan,
             Vector2::new(64, 1),
             &shader,
-            compute_none_sbuffer_loop::SpecializationConstants {
+            PluggableConstants {
                 TEXTURE_SIZE_X: 1,
                 TEXTURE_SIZE_Y: 1,
             },
@@ -1,10 +1,16 @@
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use gpu_compute::{
-    execute_util::{ExecuteParameters, ExecuteUtil, OutputKind, QuadMethod},
+    bench_matrix::{BenchMatrix, BenchShader, InputKind},
+    cpu::ReduceOp,
+    execute_util::{DataGenerator, ExecuteParameters, OutputKind, QuadMethod},
     execute_util_compute::{ComputeExecuteUtil, ComputeParameters, OutputModification},
-    vulkan_util::VulkanData,
+    executor::PluggableConstants,
+    results::{throughput, ResultSet},
+    shader_registry::loader,
+    vulkan_util::{SubgroupSupport, VulkanData},
 };
 use nalgebra::Vector2;
+use vulkano::{format::Format, shader::ShaderStages};
 
 fn criterion_benchmark(c: &mut Criterion) {
     let mut vulkan = VulkanData::init();
@@ -17,468 +23,367 @@ fn criterion_benchmark(c: &mut Criterion) {
 
 
     println!("{:X?}", profiling_sizes);
-    for y in profiling_sizes.clone() {
-        let data_size = Vector2::new(vulkan.gpu_thread_count(), y / vulkan.gpu_thread_count());
-
-        for method in QuadMethod::all(&vulkan).iter().copied() {
-            for framebuffer_y in [1, 2, 32, 64] {
-                let suffix = format!(
-                    "{method:?}_{framebuffer_x}x{framebuffer_y}",
-                    framebuffer_x = data_size.x / framebuffer_y
-                );
-
-                g.bench_with_input(
-                    BenchmarkId::new(format!("buffer_to_rendertarget_{suffix}"), y),
-                    &y,
-                    |b, _| {
-                        let shader =
-                            attach_discard_sbuffer_loop::load(vulkan.device.clone()).unwrap();
-                        let mut execute = ExecuteUtil::<u32>::setup_storage_buffer(
-                            &mut vulkan,
-                            data_size,
-                            &shader,
-                            attach_discard_sbuffer_loop::SpecializationConstants {
-                                TEXTURE_SIZE_X: (data_size.x / framebuffer_y) as _,
-                                TEXTURE_SIZE_Y: framebuffer_y as _,
-                            },
-                            ExecuteParameters {
-                                output: OutputKind::Attachment,
-                                quad_method: method,
-                                framebuffer_y,
-                                ..Default::default()
-                            },
-                            |a, b| a + b,
-                        );
-
-                        b.iter(|| {
-                            execute.run(&mut vulkan, true);
-                        });
-                    },
-                );
-
-                if data_size.y <= 32768 {
-                    g.bench_with_input(
-                        BenchmarkId::new(format!("sampler2d_to_rendertarget_{suffix}"), y),
-                        &y,
-                        |b, _| {
-                            let shader =
-                                attach_none_sampled_loop::load(vulkan.device.clone()).unwrap();
-                            let mut execute = ExecuteUtil::<u32>::setup_2d_sampler(
-                                &mut vulkan,
-                                data_size,
-                                &shader,
-                                attach_none_sampled_loop::SpecializationConstants {
-                                    TEXTURE_SIZE_X: (data_size.x / framebuffer_y) as _,
-                                    TEXTURE_SIZE_Y: framebuffer_y as _,
-                                },
-                                ExecuteParameters {
-                                    output: OutputKind::Attachment,
-                                    quad_method: method,
-                                    framebuffer_y,
-                                    ..Default::default()
-                                },
-                                |a, b| a + b,
-                            );
-
-                            b.iter(|| {
-                                execute.run(&mut vulkan, true);
-                            });
-                        },
-                    );
-                }
-
-                g.bench_with_input(
-                    BenchmarkId::new(format!("buffer_to_buffer_{suffix}"), y),
-                    &y,
-                    |b, _| {
-                        let shader = buffer_none_sbuffer_loop::load(vulkan.device.clone()).unwrap();
-                        let mut execute = ExecuteUtil::<u32>::setup_storage_buffer(
-                            &mut vulkan,
-                            data_size,
-                            &shader,
-                            buffer_none_sbuffer_loop::SpecializationConstants {
-                                TEXTURE_SIZE_X: (data_size.x / framebuffer_y) as _,
-                                TEXTURE_SIZE_Y: framebuffer_y as _,
-                            },
-                            ExecuteParameters {
-                                output: OutputKind::Buffer,
-                                quad_method: method,
-                                framebuffer_y,
-                                ..Default::default()
-                            },
-                            |a, b| a + b,
-                        );
-
-                        b.iter(|| {
-                            execute.run(&mut vulkan, true);
-                        });
-                    },
-                );
-                g.bench_with_input(
-                    BenchmarkId::new(format!("buffer_to_buffer_cpu_visible_memory_{suffix}"), y),
-                    &y,
-                    |b, _| {
-                        let shader = buffer_none_sbuffer_loop::load(vulkan.device.clone()).unwrap();
-                        let mut execute = ExecuteUtil::<u32>::setup_storage_buffer(
-                            &mut vulkan,
-                            data_size,
-                            &shader,
-                            buffer_none_sbuffer_loop::SpecializationConstants {
-                                TEXTURE_SIZE_X: (data_size.x / framebuffer_y) as _,
-                                TEXTURE_SIZE_Y: framebuffer_y as _,
-                            },
-                            ExecuteParameters {
-                                output: OutputKind::Buffer,
-                                quad_method: method,
-                                framebuffer_y,
-                                ..Default::default()
-                            },
-                            |a, b| a + b,
-                        );
-
-                        b.iter(|| {
-                            execute.run(&mut vulkan, false);
-                        });
-                    },
-                );
 
-                if data_size.y % 4 == 0 {
-                    g.bench_with_input(
-                        BenchmarkId::new(format!("vector_buffer_to_buffer_{suffix}"), y),
-                        &y,
-                        |b, _| {
-                            let shader =
-                                vector_buffer_none_sbuffer_loop::load(vulkan.device.clone())
-                                    .unwrap();
-                            let mut execute = ExecuteUtil::<u32>::setup_storage_buffer(
-                                &mut vulkan,
-                                data_size,
-                                &shader,
-                                vector_buffer_none_sbuffer_loop::SpecializationConstants {
-                                    TEXTURE_SIZE_X: (data_size.x / framebuffer_y) as _,
-                                    TEXTURE_SIZE_Y: framebuffer_y as _,
-                                },
-                                ExecuteParameters {
-                                    output: OutputKind::Buffer,
-                                    quad_method: method,
-                                    framebuffer_y,
-                                    vectorization_factor: 4,
-                                    ..Default::default()
-                                },
-                                |a, b| a + b,
-                            );
-
-                            b.iter(|| {
-                                execute.run(&mut vulkan, true);
-                            });
-                        },
-                    );
-                }
-            }
-        }
-
-        g.bench_with_input(
-            BenchmarkId::new("compute_buffer_to_buffer", y),
-            &y,
-            |b, _| {
-                let shader = compute_none_sbuffer_loop::load(vulkan.device.clone()).unwrap();
-                let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
-                    &mut vulkan,
-                    data_size,
-                    &shader,
-                    compute_none_sbuffer_loop::SpecializationConstants {
-                        TEXTURE_SIZE_X: data_size.x as _,
-                        TEXTURE_SIZE_Y: 1,
-                    },
-                    ComputeParameters::default(),
-                    |a, b| a + b,
-                );
-
-                b.iter(|| {
-                    execute.run(&mut vulkan, true);
-                });
-            },
-        );
-        g.bench_with_input(
-            BenchmarkId::new("compute_buffer_to_buffer_cpu_visible_memory", y),
-            &y,
-            |b, _| {
-                let shader = compute_none_sbuffer_loop::load(vulkan.device.clone()).unwrap();
-                let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
-                    &mut vulkan,
-                    data_size,
-                    &shader,
-                    compute_none_sbuffer_loop::SpecializationConstants {
-                        TEXTURE_SIZE_X: data_size.x as _,
-                        TEXTURE_SIZE_Y: 1,
-                    },
-                    ComputeParameters::default(),
-                    |a, b| a + b,
-                );
-
-                b.iter(|| {
-                    execute.run(&mut vulkan, false);
-                });
-            },
-        );
-        g.bench_with_input(
-            BenchmarkId::new("compute_buffer_to_buffer_subgroup_cpu_visible_memory", y),
-            &y,
-            |b, _| {
-                let shader = compute_none_groupbuffer_loop::load(vulkan.device.clone()).unwrap();
-                let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
-                    &mut vulkan,
-                    data_size,
-                    &shader,
-                    compute_none_groupbuffer_loop::SpecializationConstants {
-                        TEXTURE_SIZE_X: data_size.x as _,
-                        TEXTURE_SIZE_Y: 1,
-                    },
-                    ComputeParameters {
-                        output: OutputModification::OnePerSubgroup,
-                        ..Default::default()
-                    },
-                    |a, b| a + b,
-                );
-
-                b.iter(|| {
-                    execute.run(&mut vulkan, false);
-                });
+    let graphics = |output, vectorization_factor| ExecuteParameters {
+        output,
+        vectorization_factor,
+        ..Default::default()
+    };
+    let packed = |format, attachments| OutputKind::PackedAttachments {
+        format,
+        attachments,
+    };
+    let atomic = ComputeParameters {
+        output: OutputModification::SingleValue,
+        clear_buffer: true,
+        ..Default::default()
+    };
+    let subgroup = ComputeParameters {
+        output: OutputModification::OnePerSubgroup,
+        ..Default::default()
+    };
+    // Reduces each workgroup in shared memory instead of each subgroup
+    let shared_fallback = |vulkan: &mut VulkanData, _: &str| {
+        Some(
+            BenchShader::compute(
+                "compute_buffer_to_buffer_shared",
+                vulkan.load_shader(loader::<u32>(
+                    ReduceOp::Sum,
+                    "compute_buffer_to_buffer_shared",
+                )),
+                ComputeParameters {
+                    output: OutputModification::OnePerWorkgroup,
+                    ..Default::default()
+                },
+            )
+            .separate_read_buffer(&[false]),
+        )
+    };
+    let vectorized = ComputeParameters {
+        vectorization_factor: 4,
+        ..Default::default()
+    };
+    let tree = ComputeParameters {
+        output: OutputModification::OnePerWorkgroup,
+        ..Default::default()
+    };
+
+    let matrix = BenchMatrix::new("gpu_sum", &vulkan)
+        .sizes(profiling_sizes.clone())
+        .data(DataGenerator::from_env())
+        .quad_methods(QuadMethod::all(&vulkan).iter().copied())
+        .framebuffer_ys([1, 2, 32, 64])
+        .outputs([OutputKind::Attachment, OutputKind::Buffer])
+        .vectorization_factors([1, 4])
+        // The shared memory trees also run at the block size of
+        // kernelSumReduce in src/cuda/cuda_sum.cu
+        .workgroup_sizes([64, 256])
+        .constraint(|case| {
+            case.workgroup_size == ComputeParameters::default().workgroup_size
+                || case.shader.name.starts_with("compute_buffer_to_buffer_tree")
+        })
+        .shader(BenchShader::graphics(
+            "buffer_to_rendertarget",
+            vulkan.load_shader(loader::<u32>(ReduceOp::Sum, "buffer_to_rendertarget")),
+            graphics(OutputKind::Attachment, 1),
+        ))
+        .shader(BenchShader::graphics(
+            "buffer_to_rg_rendertarget",
+            vulkan.load_shader(attach_none_sbuffer_rg_loop::load),
+            graphics(packed(Format::R32G32_UINT, 1), 1),
+        ))
+        .shader(BenchShader::graphics(
+            "buffer_to_rgba_rendertarget",
+            vulkan.load_shader(attach_none_sbuffer_rgba_loop::load),
+            graphics(packed(Format::R32G32B32A32_UINT, 1), 1),
+        ))
+        .shader(BenchShader::graphics(
+            "buffer_to_4_rendertargets",
+            vulkan.load_shader(attach_none_sbuffer_mrt4_loop::load),
+            graphics(packed(Format::R32_UINT, 4), 1),
+        ))
+        .shader(BenchShader::graphics(
+            "buffer_to_8_rgba_rendertargets",
+            vulkan.load_shader(attach_none_sbuffer_rgba_mrt8_loop::load),
+            graphics(packed(Format::R32G32B32A32_UINT, 8), 1),
+        ))
+        .shader(
+            BenchShader::graphics(
+                "sampler2d_to_rendertarget",
+                vulkan.load_shader(attach_none_sampled_loop::load),
+                graphics(OutputKind::Attachment, 1),
+            )
+            .input(InputKind::Sampler2D)
+            .constraint(|case| case.data_size.y <= 32768),
+        )
+        .shader(
+            BenchShader::graphics(
+                "storage_image2d_to_rendertarget",
+                vulkan.load_shader(attach_none_simage2d_loop::load),
+                graphics(OutputKind::Attachment, 1),
+            )
+            .input(InputKind::StorageImage2D),
+        )
+        .shader(
+            BenchShader::graphics(
+                "storage_image1d_to_buffer",
+                vulkan.load_shader(buffer_none_simage1d_loop::load),
+                graphics(OutputKind::Buffer, 1),
+            )
+            .input(InputKind::StorageImage1D),
+        )
+        .shader(
+            BenchShader::graphics(
+                "storage_image2d_to_buffer",
+                vulkan.load_shader(buffer_none_simage2d_loop::load),
+                graphics(OutputKind::Buffer, 1),
+            )
+            .input(InputKind::StorageImage2D),
+        )
+        .shader(
+            BenchShader::graphics(
+                "uniform_texel_buffer_to_buffer",
+                vulkan.load_shader(buffer_none_utbuffer_loop::load),
+                graphics(OutputKind::Buffer, 1),
+            )
+            .input(InputKind::UniformTexelBuffer),
+        )
+        .shader(
+            BenchShader::graphics(
+                "storage_texel_buffer_to_buffer",
+                vulkan.load_shader(buffer_none_stbuffer_loop::load),
+                graphics(OutputKind::Buffer, 1),
+            )
+            .input(InputKind::StorageTexelBuffer),
+        )
+        .shader(
+            BenchShader::graphics(
+                "uniform_buffer_to_buffer",
+                vulkan.load_shader(buffer_none_ubuffer_loop::load),
+                graphics(OutputKind::Buffer, 1),
+            )
+            .input(InputKind::UniformBuffer),
+        )
+        .shader(
+            BenchShader::graphics(
+                "buffer_to_buffer",
+                vulkan.load_shader(loader::<u32>(ReduceOp::Sum, "buffer_to_buffer")),
+                graphics(OutputKind::Buffer, 1),
+            )
+            .separate_read_buffer(&[true, false]),
+        )
+        .shader(BenchShader::graphics(
+            "vector_buffer_to_buffer",
+            vulkan.load_shader(loader::<u32>(ReduceOp::Sum, "vector_buffer_to_buffer")),
+            graphics(OutputKind::Buffer, 4),
+        ))
+        .shader(
+            BenchShader::compute(
+                "compute_buffer_to_buffer",
+                vulkan.load_shader(loader::<u32>(ReduceOp::Sum, "compute_buffer_to_buffer")),
+                ComputeParameters::default(),
+            )
+            .separate_read_buffer(&[true, false]),
+        )
+        .shader(
+            BenchShader::compute(
+                "compute_storage_image1d_to_buffer",
+                vulkan.load_shader(compute_none_simage1d_loop::load),
+                ComputeParameters::default(),
+            )
+            .input(InputKind::StorageImage1D),
+        )
+        .shader(
+            BenchShader::compute(
+                "compute_storage_image2d_to_buffer",
+                vulkan.load_shader(compute_none_simage2d_loop::load),
+                ComputeParameters::default(),
+            )
+            .input(InputKind::StorageImage2D),
+        )
+        .shader(
+            BenchShader::compute(
+                "compute_uniform_texel_buffer_to_buffer",
+                vulkan.load_shader(compute_none_utbuffer_loop::load),
+                ComputeParameters::default(),
+            )
+            .input(InputKind::UniformTexelBuffer),
+        )
+        .shader(
+            BenchShader::compute(
+                "compute_storage_texel_buffer_to_buffer",
+                vulkan.load_shader(compute_none_stbuffer_loop::load),
+                ComputeParameters::default(),
+            )
+            .input(InputKind::StorageTexelBuffer),
+        )
+        .shader(
+            BenchShader::compute(
+                "compute_uniform_buffer_to_buffer",
+                vulkan.load_shader(compute_none_ubuffer_loop::load),
+                ComputeParameters::default(),
+            )
+            .input(InputKind::UniformBuffer),
+        )
+        .subgroup_shader(
+            &mut vulkan,
+            "compute_buffer_to_buffer_subgroup",
+            ShaderStages::COMPUTE,
+            SubgroupSupport::writer_operations(),
+            |vulkan, name| {
+                BenchShader::compute(
+                    name,
+                    vulkan.load_shader(loader::<u32>(ReduceOp::Sum, "compute_buffer_to_buffer_subgroup")),
+                    subgroup.clone(),
+                )
+                .separate_read_buffer(&[false])
             },
-        );
-        g.bench_with_input(
-            BenchmarkId::new(
-                "compute_buffer_to_buffer_subgroup_decimate_cpu_visible_memory",
-                y,
-            ),
-            &y,
-            |b, _| {
-                let shader =
-                    compute_none_groupbuffer_decimate_loop::load(vulkan.device.clone()).unwrap();
-                let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
-                    &mut vulkan,
-                    data_size,
-                    &shader,
-                    compute_none_groupbuffer_decimate_loop::SpecializationConstants {
-                        TEXTURE_SIZE_X: data_size.x as _,
-                        TEXTURE_SIZE_Y: 1,
-                    },
-                    ComputeParameters {
-                        output: OutputModification::OnePerSubgroup,
-                        ..Default::default()
-                    },
-                    |a, b| a + b,
-                );
-
-                b.iter(|| {
-                    execute.run(&mut vulkan, false);
-                });
+            shared_fallback,
+        )
+        .subgroup_shader(
+            &mut vulkan,
+            "compute_buffer_to_buffer_subgroup_decimate",
+            ShaderStages::COMPUTE,
+            SubgroupSupport::writer_operations(),
+            |vulkan, name| {
+                BenchShader::compute(
+                    name,
+                    vulkan.load_shader(compute_none_groupbuffer_decimate_loop::load),
+                    subgroup,
+                )
+                .separate_read_buffer(&[false])
             },
-        );
-        g.bench_with_input(
-            BenchmarkId::new(
-                "compute_buffer_to_buffer_atomic_subgroup_cpu_visible_memory",
-                y,
-            ),
-            &y,
-            |b, _| {
-                let shader =
-                    compute_none_subgroup_abuffer_loop::load(vulkan.device.clone()).unwrap();
-                let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
-                    &mut vulkan,
-                    data_size,
-                    &shader,
-                    compute_none_subgroup_abuffer_loop::SpecializationConstants {
-                        TEXTURE_SIZE_X: data_size.x as _,
-                        TEXTURE_SIZE_Y: 1,
-                    },
-                    ComputeParameters {
-                        output: OutputModification::SingleValue,
-                        clear_buffer: true,
-                        ..Default::default()
-                    },
-                    |a, b| a + b,
-                );
-
-                b.iter(|| {
-                    execute.run(&mut vulkan, false);
-                });
+            shared_fallback,
+        )
+        .subgroup_shader(
+            &mut vulkan,
+            "compute_buffer_to_buffer_atomic_subgroup",
+            ShaderStages::COMPUTE,
+            SubgroupSupport::writer_operations(),
+            |vulkan, name| {
+                BenchShader::compute(
+                    name,
+                    vulkan.load_shader(compute_none_subgroup_abuffer_loop::load),
+                    atomic.clone(),
+                )
+                .separate_read_buffer(&[false])
             },
-        );
-        g.bench_with_input(
-            BenchmarkId::new("compute_none_subgroup_casbuffer_loop", y),
-            &y,
-            |b, _| {
-                let shader =
-                    compute_none_subgroup_casbuffer_loop::load(vulkan.device.clone()).unwrap();
-                let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
-                    &mut vulkan,
-                    data_size,
-                    &shader,
-                    compute_none_subgroup_casbuffer_loop::SpecializationConstants {
-                        TEXTURE_SIZE_X: data_size.x as _,
-                        TEXTURE_SIZE_Y: 1,
-                    },
-                    ComputeParameters {
-                        output: OutputModification::SingleValue,
-                        clear_buffer: true,
-                        ..Default::default()
-                    },
-                    |a, b| a + b,
-                );
-
-                b.iter(|| {
-                    execute.run(&mut vulkan, false);
-                });
+            |_, _| None,
+        )
+        .subgroup_shader(
+            &mut vulkan,
+            "compute_none_subgroup_casbuffer_loop",
+            ShaderStages::COMPUTE,
+            SubgroupSupport::writer_operations(),
+            |vulkan, name| {
+                BenchShader::compute(
+                    name,
+                    vulkan.load_shader(compute_none_subgroup_casbuffer_loop::load),
+                    atomic.clone(),
+                )
+                .separate_read_buffer(&[false])
             },
-        );
-        g.bench_with_input(
-            BenchmarkId::new(
-                "compute_buffer_to_buffer_atomic_subgroup_cpu_visible_memory_65536_threads",
-                y,
-            ),
-            &y,
-            |b, _| {
-                let shader =
-                    compute_none_subgroup_abuffer_loop::load(vulkan.device.clone()).unwrap();
-                let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
-                    &mut vulkan,
-                    data_size,
-                    &shader,
-                    compute_none_subgroup_abuffer_loop::SpecializationConstants {
-                        TEXTURE_SIZE_X: data_size.x as _,
-                        TEXTURE_SIZE_Y: 1,
-                    },
+            |_, _| None,
+        )
+        .subgroup_shader(
+            &mut vulkan,
+            "compute_buffer_to_buffer_atomic_subgroup",
+            ShaderStages::COMPUTE,
+            SubgroupSupport::writer_operations(),
+            |vulkan, name| {
+                BenchShader::compute(
+                    name,
+                    vulkan.load_shader(compute_none_subgroup_abuffer_loop::load),
                     ComputeParameters {
-                        output: OutputModification::SingleValue,
-                        clear_buffer: true,
                         override_thread_count: Some(65536),
-                        ..Default::default()
+                        ..atomic.clone()
                     },
-                    |a, b| a + b,
-                );
-
-                b.iter(|| {
-                    execute.run(&mut vulkan, false);
-                });
+                )
+                .separate_read_buffer(&[false])
             },
-        );
-
-        g.bench_with_input(
-            BenchmarkId::new("compute_buffer_to_buffer_atomic_add_cpu_visible_memory", y),
-            &y,
-            |b, _| {
-                let shader =
-                    compute_none_atomic_add_buffer_loop::load(vulkan.device.clone()).unwrap();
-                let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
-                    &mut vulkan,
-                    data_size,
-                    &shader,
-                    compute_none_atomic_add_buffer_loop::SpecializationConstants {
-                        TEXTURE_SIZE_X: data_size.x as _,
-                        TEXTURE_SIZE_Y: 1,
-                    },
-                    ComputeParameters {
-                        output: OutputModification::SingleValue,
-                        clear_buffer: true,
-                        ..ComputeParameters::default()
-                    },
-                    |a, b| a + b,
-                );
-
-                b.iter(|| {
-                    execute.run(&mut vulkan, false);
-                });
+            |_, _| None,
+        )
+        .shader(
+            BenchShader::compute(
+                "compute_buffer_to_buffer_atomic_add",
+                vulkan.load_shader(loader::<u32>(ReduceOp::Sum, "compute_buffer_to_buffer_atomic_add")),
+                atomic.clone(),
+            )
+            .separate_read_buffer(&[false]),
+        )
+        .shader(
+            BenchShader::compute(
+                "compute_buffer_to_buffer_tree_interleaved",
+                vulkan.load_shader(loader::<u32>(ReduceOp::Sum, "compute_buffer_to_buffer_tree_interleaved")),
+                tree.clone(),
+            )
+            .separate_read_buffer(&[false]),
+        )
+        .shader(
+            BenchShader::compute(
+                "compute_buffer_to_buffer_tree_sequential",
+                vulkan.load_shader(loader::<u32>(ReduceOp::Sum, "compute_buffer_to_buffer_tree_sequential")),
+                tree.clone(),
+            )
+            .separate_read_buffer(&[false]),
+        )
+        .subgroup_shader(
+            &mut vulkan,
+            "compute_buffer_to_buffer_tree_interleaved_subgroup",
+            ShaderStages::COMPUTE,
+            SubgroupSupport::writer_operations(),
+            |vulkan, name| {
+                BenchShader::compute(
+                    name,
+                    vulkan.load_shader(compute_none_sharedtree_interleaved_subgroup_loop::load),
+                    tree.clone(),
+                )
+                .separate_read_buffer(&[false])
             },
-        );
-        g.bench_with_input(
-            BenchmarkId::new(
-                "compute_buffer_to_buffer_atomic_add_cpu_visible_memory_65536_threads",
-                y,
-            ),
-            &y,
-            |b, _| {
-                let shader =
-                    compute_none_atomic_add_buffer_loop::load(vulkan.device.clone()).unwrap();
-                let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
-                    &mut vulkan,
-                    data_size,
-                    &shader,
-                    compute_none_atomic_add_buffer_loop::SpecializationConstants {
-                        TEXTURE_SIZE_X: data_size.x as _,
-                        TEXTURE_SIZE_Y: 1,
-                    },
-                    ComputeParameters {
-                        output: OutputModification::SingleValue,
-                        clear_buffer: true,
-                        override_thread_count: Some(65536),
-                        ..ComputeParameters::default()
-                    },
-                    |a, b| a + b,
-                );
-
-                b.iter(|| {
-                    execute.run(&mut vulkan, false);
-                });
+            |_, _| None,
+        )
+        .subgroup_shader(
+            &mut vulkan,
+            "compute_buffer_to_buffer_tree_sequential_subgroup",
+            ShaderStages::COMPUTE,
+            SubgroupSupport::writer_operations(),
+            |vulkan, name| {
+                BenchShader::compute(
+                    name,
+                    vulkan.load_shader(compute_none_sharedtree_sequential_subgroup_loop::load),
+                    tree,
+                )
+                .separate_read_buffer(&[false])
             },
+            |_, _| None,
+        )
+        .shader(
+            BenchShader::compute(
+                "compute_buffer_to_buffer_atomic_add",
+                vulkan.load_shader(loader::<u32>(ReduceOp::Sum, "compute_buffer_to_buffer_atomic_add")),
+                ComputeParameters {
+                    override_thread_count: Some(65536),
+                    ..atomic
+                },
+            )
+            .separate_read_buffer(&[false]),
+        )
+        .shader(
+            BenchShader::compute(
+                "vector_compute_buffer_to_buffer",
+                vulkan.load_shader(loader::<u32>(ReduceOp::Sum, "vector_compute_buffer_to_buffer")),
+                vectorized,
+            )
+            .separate_read_buffer(&[true, false])
+            .memory_suffix("_visible_memory"),
         );
 
-
-        if data_size.y % 4 == 0 {
-            g.bench_with_input(
-                BenchmarkId::new("vector_compute_buffer_to_buffer", y),
-                &y,
-                |b, _| {
-                    let shader =
-                        vector_compute_none_sbuffer_loop::load(vulkan.device.clone()).unwrap();
-                    let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
-                        &mut vulkan,
-                        data_size,
-                        &shader,
-                        vector_compute_none_sbuffer_loop::SpecializationConstants {
-                            TEXTURE_SIZE_X: data_size.x as _,
-                            TEXTURE_SIZE_Y: 1,
-                        },
-                        ComputeParameters {
-                            vectorization_factor: 4,
-                            ..ComputeParameters::default()
-                        },
-                        |a, b| a + b,
-                    );
-
-                    b.iter(|| {
-                        execute.run(&mut vulkan, true);
-                    });
-                },
-            );
-            g.bench_with_input(
-                BenchmarkId::new("vector_compute_buffer_to_buffer_visible_memory", y),
-                &y,
-                |b, _| {
-                    let shader =
-                        vector_compute_none_sbuffer_loop::load(vulkan.device.clone()).unwrap();
-                    let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
-                        &mut vulkan,
-                        data_size,
-                        &shader,
-                        vector_compute_none_sbuffer_loop::SpecializationConstants {
-                            TEXTURE_SIZE_X: data_size.x as _,
-                            TEXTURE_SIZE_Y: 1,
-                        },
-                        ComputeParameters {
-                            vectorization_factor: 4,
-                            ..ComputeParameters::default()
-                        },
-                        |a, b| a + b,
-                    );
-
-                    b.iter(|| {
-                        execute.run(&mut vulkan, true);
-                    });
-                },
-            );
-        }
+    let mut results = ResultSet::new(&mut vulkan);
+    if std::env::var_os("CALIBRATE_BANDWIDTH").is_some() {
+        results.calibrate(&mut vulkan);
     }
+    matrix.run::<u32, _>(&mut g, &mut vulkan, &mut results, |a, b| a + b);
+    results.save("target/results", "gpu_sum").unwrap();
 
     {
         // This has AWFUL performance
@@ -488,16 +393,17 @@ fn criterion_benchmark(c: &mut Criterion) {
             y * 2 / vulkan.gpu_thread_count(),
         );
 
+        g.throughput(throughput::<u32>(data_size.x * data_size.y));
         g.bench_with_input(
             BenchmarkId::new("compute_buffer_to_buffer_atomic_cpu_visible_memory", y),
             &y,
             |b, _| {
-                let shader = compute_none_abuffer_loop::load(vulkan.device.clone()).unwrap();
+                let shader = vulkan.load_shader(compute_none_abuffer_loop::load);
                 let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
                     &mut vulkan,
                     data_size,
                     &shader,
-                    compute_none_abuffer_loop::SpecializationConstants {
+                    PluggableConstants {
                         TEXTURE_SIZE_X: data_size.x as _,
                         TEXTURE_SIZE_Y: 1,
                     },
@@ -522,28 +428,39 @@ fn criterion_benchmark(c: &mut Criterion) {
 criterion_group!(benches, criterion_benchmark);
 criterion_main!(benches);
 
-mod attach_discard_sbuffer_loop {
+mod attach_none_sbuffer_rg_loop {
     vulkano_shaders::shader! {
         ty: "fragment",
-        path: "shaders/instances/gpu_sum/attach_none_sbuffer_loop.glsl",
+        path: "shaders/instances/gpu_sum/attach_none_sbuffer_packed_loop.glsl",
         include: ["shaders/pluggable"],
+        define: [("OUTPUT_COMPONENTS", "2")],
     }
 }
 
-mod buffer_none_sbuffer_loop {
+mod attach_none_sbuffer_rgba_loop {
     vulkano_shaders::shader! {
         ty: "fragment",
-        path: "shaders/instances/gpu_sum/buffer_none_sbuffer_loop.glsl",
+        path: "shaders/instances/gpu_sum/attach_none_sbuffer_packed_loop.glsl",
         include: ["shaders/pluggable"],
+        define: [("OUTPUT_COMPONENTS", "4")],
     }
 }
 
-mod compute_none_sbuffer_loop {
+mod attach_none_sbuffer_mrt4_loop {
     vulkano_shaders::shader! {
-        ty: "compute",
-        path: "shaders/instances/gpu_sum/buffer_none_sbuffer_loop.glsl",
+        ty: "fragment",
+        path: "shaders/instances/gpu_sum/attach_none_sbuffer_packed_loop.glsl",
         include: ["shaders/pluggable"],
-        define: [("COMPUTE_SHADER", "1")],
+        define: [("OUTPUT_ATTACHMENTS", "4")],
+    }
+}
+
+mod attach_none_sbuffer_rgba_mrt8_loop {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "shaders/instances/gpu_sum/attach_none_sbuffer_packed_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("OUTPUT_COMPONENTS", "4"), ("OUTPUT_ATTACHMENTS", "8")],
     }
 }
 mod compute_none_abuffer_loop {
@@ -564,56 +481,138 @@ mod compute_none_subgroup_casbuffer_loop {
         spirv_version: "1.3",
     }
 }
-mod compute_none_atomic_add_buffer_loop {
+mod compute_none_subgroup_abuffer_loop {
     vulkano_shaders::shader! {
         ty: "compute",
-        path: "shaders/instances/gpu_sum/buffer_none_atomic_add_buffer_loop.glsl",
+        path: "shaders/instances/gpu_sum/buffer_none_subgroup_abuffer_loop.glsl",
         include: ["shaders/pluggable"],
         define: [("COMPUTE_SHADER", "1")],
+        spirv_version: "1.3",
     }
 }
-mod compute_none_subgroup_abuffer_loop {
+
+
+mod attach_none_sampled_loop {
     vulkano_shaders::shader! {
-        ty: "compute",
-        path: "shaders/instances/gpu_sum/buffer_none_subgroup_abuffer_loop.glsl",
+        ty: "fragment",
+        path: "shaders/instances/gpu_sum/attach_none_sampled2D_loop.glsl",
+        include: ["shaders/pluggable"],
+    }
+}
+
+mod attach_none_simage2d_loop {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "shaders/instances/gpu_sum/attach_none_simage2D_loop.glsl",
+        include: ["shaders/pluggable"],
+    }
+}
+
+mod buffer_none_simage1d_loop {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "shaders/instances/gpu_sum/buffer_none_simage1D_loop.glsl",
         include: ["shaders/pluggable"],
-        define: [("COMPUTE_SHADER", "1")],
-        spirv_version: "1.3",
     }
 }
 
-mod vector_buffer_none_sbuffer_loop {
+mod buffer_none_simage2d_loop {
     vulkano_shaders::shader! {
         ty: "fragment",
-        path: "shaders/instances/gpu_sum/vectorized/buffer_none_sbuffer_loop.glsl",
+        path: "shaders/instances/gpu_sum/buffer_none_simage2D_loop.glsl",
         include: ["shaders/pluggable"],
     }
 }
 
-mod vector_compute_none_sbuffer_loop {
+mod compute_none_simage1d_loop {
     vulkano_shaders::shader! {
         ty: "compute",
-        path: "shaders/instances/gpu_sum/vectorized/buffer_none_sbuffer_loop.glsl",
+        path: "shaders/instances/gpu_sum/buffer_none_simage1D_loop.glsl",
         include: ["shaders/pluggable"],
         define: [("COMPUTE_SHADER", "1")],
     }
 }
 
+mod compute_none_simage2d_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_sum/buffer_none_simage2D_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1")],
+    }
+}
 
-mod attach_none_sampled_loop {
+mod buffer_none_utbuffer_loop {
     vulkano_shaders::shader! {
         ty: "fragment",
-        path: "shaders/instances/gpu_sum/attach_none_sampled2D_loop.glsl",
+        path: "shaders/instances/gpu_sum/buffer_none_utbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+    }
+}
+
+mod buffer_none_stbuffer_loop {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "shaders/instances/gpu_sum/buffer_none_stbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+    }
+}
+
+mod buffer_none_ubuffer_loop {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "shaders/instances/gpu_sum/buffer_none_ubuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+    }
+}
+
+mod compute_none_utbuffer_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_sum/buffer_none_utbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1")],
+    }
+}
+
+mod compute_none_stbuffer_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_sum/buffer_none_stbuffer_loop.glsl",
         include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1")],
     }
 }
 
-mod compute_none_groupbuffer_loop {
+mod compute_none_ubuffer_loop {
     vulkano_shaders::shader! {
         ty: "compute",
-        path: "shaders/instances/gpu_sum/buffer_none_groupbuffer_loop.glsl",
+        path: "shaders/instances/gpu_sum/buffer_none_ubuffer_loop.glsl",
         include: ["shaders/pluggable"],
         define: [("COMPUTE_SHADER", "1")],
+    }
+}
+
+mod compute_none_sharedtree_interleaved_subgroup_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_sum/buffer_none_sharedtree_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [
+            ("COMPUTE_SHADER", "1"),
+            ("TREE_INTERLEAVED", "1"),
+            ("TREE_SUBGROUP_FINAL", "1")
+        ],
+        spirv_version: "1.3",
+    }
+}
+
+mod compute_none_sharedtree_sequential_subgroup_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_sum/buffer_none_sharedtree_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1"), ("TREE_SUBGROUP_FINAL", "1")],
         spirv_version: "1.3",
     }
 }
@@ -1,13 +1,15 @@
-use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use criterion::{criterion_group, criterion_main, Criterion};
 use gpu_compute::{
-    execute_util::{BlendMethod, ExecuteParameters, ExecuteUtil, OutputKind, QuadMethod},
-    execute_util_compute::{ComputeExecuteUtil, ComputeParameters},
-    vulkan_util::VulkanData,
+    bench_matrix::{BenchMatrix, BenchShader},
+    cpu::ReduceOp,
+    execute_util::{BlendMethod, DataGenerator, ExecuteParameters, OutputKind, QuadMethod},
+    execute_util_compute::{ComputeParameters, OutputModification},
+    results::ResultSet,
+    shader_registry::loader,
+    vulkan_util::{SubgroupSupport, VulkanData},
 };
 use itertools::Itertools;
-use nalgebra::Vector2;
-use num::Float;
-use vulkano::format::{ClearValue, Format};
+use vulkano::{format::Format, shader::ShaderStages};
 
 fn criterion_benchmark(c: &mut Criterion) {
     let mut vulkan = VulkanData::init();
@@ -37,193 +39,128 @@ fn criterion_benchmark(c: &mut Criterion) {
             .filter(|v| v % 4 == 0)
             .collect_vec()
     );
-    for y in profiling_sizes.clone() {
-        let data_size = Vector2::new(vulkan.gpu_thread_count(), y / vulkan.gpu_thread_count());
-
-        g.bench_with_input(
-            BenchmarkId::new("graphics_buffer_to_buffer", y),
-            &y,
-            |b, _| {
-                let shader = none_sbuffer_loop::load(vulkan.device.clone()).unwrap();
-                let mut execute = ExecuteUtil::<f32>::setup_storage_buffer(
-                    &mut vulkan,
-                    data_size,
-                    &shader,
-                    none_sbuffer_loop::SpecializationConstants {
-                        TEXTURE_SIZE_X: data_size.x as _,
-                        TEXTURE_SIZE_Y: 1,
-                    },
-                    ExecuteParameters {
-                        output: OutputKind::Buffer,
-                        quad_method: QuadMethod::large_triangle,
-                        ..Default::default()
-                    },
-                    |a, b| a.min(b),
-                );
-
-                b.iter(|| {
-                    execute.run(&mut vulkan, true);
-                });
-            },
-        );
-        g.bench_with_input(
-            BenchmarkId::new("graphics_buffer_to_rendertarget", y),
-            &y,
-            |b, _| {
-                let shader = attach_none_sbuffer_loop::load(vulkan.device.clone()).unwrap();
-                let mut execute = ExecuteUtil::<f32>::setup_storage_buffer(
-                    &mut vulkan,
-                    data_size,
-                    &shader,
-                    attach_none_sbuffer_loop::SpecializationConstants {
-                        TEXTURE_SIZE_X: data_size.x as _,
-                        TEXTURE_SIZE_Y: 1,
-                    },
-                    ExecuteParameters {
-                        output: OutputKind::RenderAttachment(Format::R32_SFLOAT),
-                        quad_method: QuadMethod::large_triangle,
-                        clear_value: ClearValue::Float([f32::infinity(); 4]),
-                        ..Default::default()
-                    },
-                    |a, b| a.min(b),
-                );
-
-                b.iter(|| {
-                    execute.run(&mut vulkan, true);
-                });
-            },
-        );
-        g.bench_with_input(
-            BenchmarkId::new("graphics_buffer_to_rendertarget_blend", y),
-            &y,
-            |b, _| {
-                let shader = attach_none_sbuffer_many::load(vulkan.device.clone()).unwrap();
-                let mut execute = ExecuteUtil::<f32>::setup_storage_buffer(
-                    &mut vulkan,
-                    data_size,
-                    &shader,
-                    attach_none_sbuffer_many::SpecializationConstants {
-                        TEXTURE_SIZE_X: data_size.x as _,
-                        TEXTURE_SIZE_Y: 1,
-                    },
-                    ExecuteParameters {
-                        output: OutputKind::RenderAttachment(Format::R32_SFLOAT),
-                        quad_method: QuadMethod::large_triangle,
-                        clear_value: ClearValue::Float([f32::infinity(); 4]),
-                        blend: Some(BlendMethod::Min),
-                        use_instances_and_blend: true,
-                        ..Default::default()
-                    },
-                    |a, b| a.min(b),
-                );
-
-                b.iter(|| {
-                    execute.run(&mut vulkan, true);
-                });
+    let graphics = |output| ExecuteParameters {
+        output,
+        quad_method: QuadMethod::large_triangle,
+        ..Default::default()
+    };
+    let tree = ComputeParameters {
+        output: OutputModification::OnePerWorkgroup,
+        ..Default::default()
+    };
+
+    let matrix = BenchMatrix::new("gpu_min_f32", &vulkan)
+        .sizes(profiling_sizes.clone())
+        .data(DataGenerator::from_env())
+        .quad_methods([QuadMethod::large_triangle])
+        .outputs([
+            OutputKind::RenderAttachment(Format::R32_SFLOAT),
+            OutputKind::Buffer,
+        ])
+        .vectorization_factors([1, 4])
+        .shader(BenchShader::graphics(
+            "graphics_buffer_to_buffer",
+            vulkan.load_shader(loader::<f32>(ReduceOp::Min, "graphics_buffer_to_buffer")),
+            graphics(OutputKind::Buffer),
+        ))
+        .shader(BenchShader::graphics(
+            "graphics_buffer_to_rendertarget",
+            vulkan.load_shader(loader::<f32>(
+                ReduceOp::Min,
+                "graphics_buffer_to_rendertarget",
+            )),
+            graphics(OutputKind::RenderAttachment(Format::R32_SFLOAT)),
+        ))
+        .shader(BenchShader::graphics(
+            "graphics_buffer_to_rendertarget_blend",
+            vulkan.load_shader(blend_none_sbuffer_many::load),
+            ExecuteParameters {
+                blend: Some(BlendMethod::Min),
+                use_instances_and_blend: true,
+                ..graphics(OutputKind::BlendAttachment)
             },
-        );
-
-        g.bench_with_input(
-            BenchmarkId::new("compute_buffer_to_buffer", y),
-            &y,
-            |b, _| {
-                let shader = compute_none_sbuffer_loop::load(vulkan.device.clone()).unwrap();
-                let mut execute = ComputeExecuteUtil::<f32>::setup_storage_buffer(
-                    &mut vulkan,
-                    data_size,
-                    &shader,
-                    compute_none_sbuffer_loop::SpecializationConstants {
-                        TEXTURE_SIZE_X: data_size.x as _,
-                        TEXTURE_SIZE_Y: 1,
-                    },
-                    ComputeParameters::default(),
-                    |a, b| a.min(b),
-                );
-
-                b.iter(|| {
-                    execute.run(&mut vulkan, true);
-                });
+        ))
+        .shader(
+            BenchShader::compute(
+                "compute_buffer_to_buffer",
+                vulkan.load_shader(loader::<f32>(ReduceOp::Min, "compute_buffer_to_buffer")),
+                ComputeParameters::default(),
+            )
+            .separate_read_buffer(&[true, false]),
+        )
+        .shader(
+            BenchShader::compute(
+                "vector_compute_buffer_to_buffer",
+                vulkan.load_shader(loader::<f32>(
+                    ReduceOp::Min,
+                    "vector_compute_buffer_to_buffer",
+                )),
+                ComputeParameters {
+                    vectorization_factor: 4,
+                    ..ComputeParameters::default()
+                },
+            )
+            .separate_read_buffer(&[true, false]),
+        )
+        .shader(
+            BenchShader::compute(
+                "compute_buffer_to_buffer_tree_interleaved",
+                vulkan.load_shader(loader::<f32>(
+                    ReduceOp::Min,
+                    "compute_buffer_to_buffer_tree_interleaved",
+                )),
+                tree.clone(),
+            )
+            .separate_read_buffer(&[false]),
+        )
+        .shader(
+            BenchShader::compute(
+                "compute_buffer_to_buffer_tree_sequential",
+                vulkan.load_shader(loader::<f32>(
+                    ReduceOp::Min,
+                    "compute_buffer_to_buffer_tree_sequential",
+                )),
+                tree.clone(),
+            )
+            .separate_read_buffer(&[false]),
+        )
+        .subgroup_shader(
+            &mut vulkan,
+            "compute_buffer_to_buffer_tree_interleaved_subgroup",
+            ShaderStages::COMPUTE,
+            SubgroupSupport::writer_operations(),
+            |vulkan, name| {
+                BenchShader::compute(
+                    name,
+                    vulkan.load_shader(compute_none_sharedtree_interleaved_subgroup_loop::load),
+                    tree.clone(),
+                )
+                .separate_read_buffer(&[false])
             },
-        );
-        g.bench_with_input(
-            BenchmarkId::new("compute_buffer_to_buffer_cpu_visible_memory", y),
-            &y,
-            |b, _| {
-                let shader = compute_none_sbuffer_loop::load(vulkan.device.clone()).unwrap();
-                let mut execute = ComputeExecuteUtil::<f32>::setup_storage_buffer(
-                    &mut vulkan,
-                    data_size,
-                    &shader,
-                    compute_none_sbuffer_loop::SpecializationConstants {
-                        TEXTURE_SIZE_X: data_size.x as _,
-                        TEXTURE_SIZE_Y: 1,
-                    },
-                    ComputeParameters::default(),
-                    |a, b| a.min(b),
-                );
-
-                b.iter(|| {
-                    execute.run(&mut vulkan, false);
-                });
+            |_, _| None,
+        )
+        .subgroup_shader(
+            &mut vulkan,
+            "compute_buffer_to_buffer_tree_sequential_subgroup",
+            ShaderStages::COMPUTE,
+            SubgroupSupport::writer_operations(),
+            |vulkan, name| {
+                BenchShader::compute(
+                    name,
+                    vulkan.load_shader(compute_none_sharedtree_sequential_subgroup_loop::load),
+                    tree,
+                )
+                .separate_read_buffer(&[false])
             },
+            |_, _| None,
         );
 
-
-        if data_size.y % 4 == 0 {
-            g.bench_with_input(
-                BenchmarkId::new("vector_compute_buffer_to_buffer", y),
-                &y,
-                |b, _| {
-                    let shader =
-                        vector_compute_none_sbuffer_loop::load(vulkan.device.clone()).unwrap();
-                    let mut execute = ComputeExecuteUtil::<f32>::setup_storage_buffer(
-                        &mut vulkan,
-                        data_size,
-                        &shader,
-                        vector_compute_none_sbuffer_loop::SpecializationConstants {
-                            TEXTURE_SIZE_X: data_size.x as _,
-                            TEXTURE_SIZE_Y: 1,
-                        },
-                        ComputeParameters {
-                            vectorization_factor: 4,
-                            ..ComputeParameters::default()
-                        },
-                        |a, b| a.min(b),
-                    );
-
-                    b.iter(|| {
-                        execute.run(&mut vulkan, true);
-                    });
-                },
-            );
-            g.bench_with_input(
-                BenchmarkId::new("vector_compute_buffer_to_buffer_visible_memory", y),
-                &y,
-                |b, _| {
-                    let shader =
-                        vector_compute_none_sbuffer_loop::load(vulkan.device.clone()).unwrap();
-                    let mut execute = ComputeExecuteUtil::<f32>::setup_storage_buffer(
-                        &mut vulkan,
-                        data_size,
-                        &shader,
-                        vector_compute_none_sbuffer_loop::SpecializationConstants {
-                            TEXTURE_SIZE_X: data_size.x as _,
-                            TEXTURE_SIZE_Y: 1,
-                        },
-                        ComputeParameters {
-                            vectorization_factor: 4,
-                            ..ComputeParameters::default()
-                        },
-                        |a, b| a.min(b),
-                    );
-
-                    b.iter(|| {
-                        execute.run(&mut vulkan, true);
-                    });
-                },
-            );
-        }
+    let mut results = ResultSet::new(&mut vulkan);
+    if std::env::var_os("CALIBRATE_BANDWIDTH").is_some() {
+        results.calibrate(&mut vulkan);
     }
+    matrix.run::<f32, _>(&mut g, &mut vulkan, &mut results, |a, b| a.min(b));
+    results.save("target/results", "gpu_min_f32").unwrap();
 
     drop(g);
 }
@@ -231,44 +168,39 @@ fn criterion_benchmark(c: &mut Criterion) {
 criterion_group!(benches, criterion_benchmark);
 criterion_main!(benches);
 
-mod none_sbuffer_loop {
-    vulkano_shaders::shader! {
-        ty: "fragment",
-        path: "shaders/instances/gpu_min/buffer_none_sbuffer_loop.glsl",
-        include: ["shaders/pluggable"],
-        define: [("DATA_TYPE", "float")],
-    }
-}
-mod attach_none_sbuffer_loop {
-    vulkano_shaders::shader! {
-        ty: "fragment",
-        path: "shaders/instances/gpu_min/attach_none_sbuffer_loop.glsl",
-        include: ["shaders/pluggable"],
-        define: [("DATA_TYPE", "float")],
-    }
-}
-mod attach_none_sbuffer_many {
+mod blend_none_sbuffer_many {
     vulkano_shaders::shader! {
         ty: "fragment",
-        path: "shaders/instances/gpu_min/attach_none_sbuffer_many.glsl",
+        path: "shaders/instances/blend/attach_none_sbuffer_many.glsl",
         include: ["shaders/pluggable"],
-        define: [("DATA_TYPE", "float")],
+        define: [("DATA_TYPE", "float"), ("OUTPUT_VECTOR_TYPE", "vec4")],
     }
 }
 
-mod compute_none_sbuffer_loop {
+mod compute_none_sharedtree_interleaved_subgroup_loop {
     vulkano_shaders::shader! {
         ty: "compute",
-        path: "shaders/instances/gpu_min/buffer_none_sbuffer_loop.glsl",
+        path: "shaders/instances/gpu_min/buffer_none_sharedtree_loop.glsl",
         include: ["shaders/pluggable"],
-        define: [("COMPUTE_SHADER", "1"), ("DATA_TYPE", "float")],
+        define: [
+            ("COMPUTE_SHADER", "1"),
+            ("DATA_TYPE", "float"),
+            ("TREE_INTERLEAVED", "1"),
+            ("TREE_SUBGROUP_FINAL", "1")
+        ],
+        spirv_version: "1.3",
     }
 }
-mod vector_compute_none_sbuffer_loop {
+mod compute_none_sharedtree_sequential_subgroup_loop {
     vulkano_shaders::shader! {
         ty: "compute",
-        path: "shaders/instances/gpu_min/vectorized/buffer_none_sbuffer_loop.glsl",
+        path: "shaders/instances/gpu_min/buffer_none_sharedtree_loop.glsl",
         include: ["shaders/pluggable"],
-        define: [("COMPUTE_SHADER", "1"), ("DATA_TYPE", "vec4")],
+        define: [
+            ("COMPUTE_SHADER", "1"),
+            ("DATA_TYPE", "float"),
+            ("TREE_SUBGROUP_FINAL", "1")
+        ],
+        spirv_version: "1.3",
     }
 }
@@ -1,36 +1,49 @@
-#![feature(portable_simd)]
-
-use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
-use gpu_compute::vulkan_util::VulkanData;
-use itertools::Itertools;
-use rayon::prelude::*;
-use std::{
-    hint::black_box,
-    ops::{Add, AddAssign},
-    simd::{u32x16, SimdUint},
+use criterion::{
+    criterion_group, criterion_main, measurement::WallTime, BenchmarkGroup, BenchmarkId, Criterion,
 };
-
-fn accumulate<T>(i: &[T]) -> T
-where
-    T: Copy + Default + Add + AddAssign,
+use gpu_compute::{
+    cpu::{CpuStrategy, ReduceOp},
+    results::{throughput, Recorder, ResultSet, RunParameters},
+    vulkan_util::VulkanData,
+};
+use itertools::Itertools;
+use std::hint::black_box;
+
+/// Benchmarks `reduce` on `data` with criterion, then records it into
+/// `results` unless criterion's filter skipped it
+fn bench_reduce<T, F>(
+    g: &mut BenchmarkGroup<WallTime>,
+    results: &mut ResultSet,
+    group: &str,
+    id: &str,
+    size: u32,
+    data: &[T],
+    reduce: F,
+) where
+    F: Fn(&[T]) -> T,
 {
-    let mut acc = Default::default();
-
-    for i in i {
-        acc += *i;
+    let mut ran = false;
+    g.bench_with_input(BenchmarkId::new(id, size), &size, |b, _| {
+        ran = true;
+        b.iter(|| reduce(black_box(data)));
+    });
+    if !ran {
+        return;
     }
 
-    acc
-}
-fn accumulate_parallel<T>(i: &[T]) -> T
-where
-    T: Copy + Default + Add<Output = T> + Send + Sync,
-{
-    i.par_iter().copied().reduce(Default::default, |a, b| a + b)
+    let mut recorder = Recorder::default();
+    recorder.measure_cpu_runs(|| reduce(black_box(data)));
+    results.record(
+        group,
+        &format!("{id}/{size}"),
+        RunParameters::cpu::<T>(id, size),
+        recorder,
+    );
 }
 
 fn criterion_benchmark(c: &mut Criterion) {
-    let vulkan = VulkanData::init();
+    let mut vulkan = VulkanData::init();
+    let mut results = ResultSet::new(&mut vulkan);
 
     let mut g = c.benchmark_group("cpu_sum");
 
@@ -42,51 +55,19 @@ fn criterion_benchmark(c: &mut Criterion) {
     println!("{:?}", sizes);
 
     for size in sizes.clone() {
-        g.bench_with_input(BenchmarkId::new("u32", size), &size, |b, size| {
-            let data = (1u32..*size).into_iter().collect_vec();
-
-            b.iter(|| accumulate(black_box(&data)));
-        });
-        g.bench_with_input(BenchmarkId::new("u32_parallel", size), &size, |b, size| {
-            let data = (1u32..*size).into_iter().collect_vec();
-
-            b.iter(|| accumulate_parallel(black_box(&data)));
-        });
-
-        g.bench_with_input(BenchmarkId::new("u32_vector", size), &size, |b, size| {
-            let data = (1u32..*size).into_iter().collect_vec();
-
-            b.iter(|| {
-                let mut accumulator = u32x16::from_array([0; 16]);
-
-                for it in black_box(data.chunks_exact(16)) {
-                    let it = u32x16::from_array(it.try_into().unwrap());
-                    accumulator += it;
-                }
-
-                accumulator.reduce_sum()
+        g.throughput(throughput::<u32>(size));
+        let data = (1u32..size).collect_vec();
+
+        for (id, strategy) in [
+            ("u32", CpuStrategy::Scalar),
+            ("u32_parallel", CpuStrategy::Parallel),
+            ("u32_vector", CpuStrategy::Simd),
+            ("u32_parallel_vector", CpuStrategy::ParallelSimd),
+        ] {
+            bench_reduce(&mut g, &mut results, "cpu_sum", id, size, &data, |data| {
+                strategy.reduce(data, ReduceOp::Sum)
             });
-        });
-
-        g.bench_with_input(
-            BenchmarkId::new("u32_parallel_vector", size),
-            &size,
-            |b, size| {
-                let data = (1u32..*size).into_iter().collect_vec();
-                let data: Vec<u32x16> = data
-                    .chunks_exact(16)
-                    .map(|chunk| u32x16::from_array(chunk.try_into().unwrap()))
-                    .collect_vec();
-
-                b.iter(|| {
-                    black_box(&data)
-                        .par_iter()
-                        .copied()
-                        .reduce(|| u32x16::from_array([0; 16]), |a, b| a + b)
-                        .reduce_sum()
-                });
-            },
-        );
+        }
     }
 
     drop(g);
@@ -94,17 +75,23 @@ fn criterion_benchmark(c: &mut Criterion) {
     let mut g = c.benchmark_group("cpu_min");
 
     for size in sizes.clone() {
-        g.bench_with_input(BenchmarkId::new("f32", size), &size, |b, size| {
-            let data = (1u32..*size).into_iter().map(|it| it as f32).collect_vec();
-
-            b.iter(|| {
-                black_box(&data)
-                    .iter()
-                    .copied()
-                    .reduce(|a, b| if a < b { a } else { b })
-            });
-        });
+        g.throughput(throughput::<f32>(size));
+        let data = (1u32..size).map(|it| it as f32).collect_vec();
+
+        bench_reduce(
+            &mut g,
+            &mut results,
+            "cpu_min",
+            "f32",
+            size,
+            &data,
+            |data| CpuStrategy::Scalar.reduce(data, ReduceOp::Min),
+        );
     }
+
+    drop(g);
+
+    results.save("target/results", "cpu").unwrap();
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -3,7 +3,7 @@ use criterion::{criterion_group, criterion_main};
 #[cfg(feature = "cuda")]
 mod imp {
     use criterion::{measurement::WallTime, BenchmarkGroup, BenchmarkId, Criterion};
-    use gpu_compute::{execute_util::generate_data, vulkan_util::VulkanData};
+    use gpu_compute::{execute_util::DataGenerator, results::throughput, vulkan_util::VulkanData};
     use itertools::Itertools;
     use std::time::Duration;
 
@@ -14,13 +14,15 @@ mod imp {
 
     fn do_cuda_bench(
         g: &mut BenchmarkGroup<WallTime>,
+        data: DataGenerator,
         data_size: u32,
         kernel_size: u32,
         name: &str,
         algo: Algo,
     ) {
+        g.throughput(throughput::<u32>(data_size));
         g.bench_with_input(BenchmarkId::new(name, data_size), &data_size, |b, _| {
-            let data = generate_data::<u32>(data_size).collect_vec();
+            let data = data.generate::<u32>(data_size).collect_vec();
             unsafe { gpu_compute::cuda_accumulate_u32_set_data(data.as_ptr(), data.len()) };
 
             match algo {
@@ -51,12 +53,15 @@ mod imp {
         let sizes = vulkan.profiling_sizes().clone();
         println!("{:X?}", sizes);
 
+        let data = DataGenerator::from_env();
+        println!("Data: {data}");
+
         {
             let mut g = c.benchmark_group("call_times");
             g.measurement_time(Duration::from_secs(30));
             g.sample_size(1000);
 
-            do_cuda_bench(&mut g, 1, 1, "cuda", Algo::Simple);
+            do_cuda_bench(&mut g, data, 1, 1, "cuda", Algo::Simple);
         }
 
         {
@@ -65,8 +70,15 @@ mod imp {
             g.sample_size(10);
 
             for data_size in sizes.clone() {
-                do_cuda_bench(&mut g, data_size, 1024, "cuda", Algo::Simple);
-                do_cuda_bench(&mut g, data_size, 1024, "cuda_subgroup", Algo::Subgroup);
+                do_cuda_bench(&mut g, data, data_size, 1024, "cuda", Algo::Simple);
+                do_cuda_bench(
+                    &mut g,
+                    data,
+                    data_size,
+                    1024,
+                    "cuda_subgroup",
+                    Algo::Subgroup,
+                );
             }
         }
     }
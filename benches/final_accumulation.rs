@@ -1,27 +1,12 @@
-#![feature(portable_simd)]
-
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use gpu_compute::{
+    cpu::{CpuStrategy, ReduceOp},
+    results::throughput,
+};
 use itertools::Itertools;
 use rand::Rng;
 use rand_pcg::Pcg64;
-use std::{
-    hint::black_box,
-    ops::{Add, AddAssign},
-    simd::{u32x16, SimdUint},
-};
-
-fn accumulate<T>(i: &[T]) -> T
-where
-    T: Copy + Default + Add + AddAssign,
-{
-    let mut acc = Default::default();
-
-    for i in i {
-        acc += *i;
-    }
-
-    acc
-}
+use std::hint::black_box;
 
 fn criterion_benchmark(c: &mut Criterion) {
     let mut g = c.benchmark_group("final_accumulation");
@@ -36,6 +21,7 @@ fn criterion_benchmark(c: &mut Criterion) {
                 .take(DATA_SIZES / (size as usize))
                 .collect_vec(),
         );
+        g.throughput(throughput::<u32>(size));
 
         g.bench_with_input(BenchmarkId::new("pcg_cost", size), &size, |b, _size| {
             let mut rng = Pcg64::new(42, 0);
@@ -51,7 +37,7 @@ fn criterion_benchmark(c: &mut Criterion) {
 
             b.iter(|| {
                 let data = data.get(rng.gen_range(0..data.len())).unwrap();
-                accumulate(black_box(data))
+                CpuStrategy::Scalar.reduce(black_box(data), ReduceOp::Sum)
             });
         });
 
@@ -60,14 +46,7 @@ fn criterion_benchmark(c: &mut Criterion) {
 
             b.iter(|| {
                 let data = data.get(rng.gen_range(0..data.len())).unwrap();
-                let mut accumulator = u32x16::from_array([0; 16]);
-
-                for it in black_box(data.chunks_exact(16)) {
-                    let it = u32x16::from_array(it.try_into().unwrap());
-                    accumulator += it;
-                }
-
-                accumulator.reduce_sum()
+                CpuStrategy::Simd.reduce(black_box(data), ReduceOp::Sum)
             });
         });
     }
@@ -4,100 +4,61 @@ use criterion::criterion_main;
 
 #[cfg(feature = "opencl")]
 pub mod all {
-    use bytemuck::Pod;
     use criterion::{
-        black_box, criterion_group, criterion_main, measurement::WallTime, BenchmarkGroup,
-        BenchmarkId, Criterion,
+        criterion_group, criterion_main, measurement::WallTime, BenchmarkGroup, BenchmarkId,
+        Criterion,
+    };
+    use gpu_compute::{
+        cpu::CpuReducible, execute_util::DataGenerator, execute_util_opencl::OpenClExecuteUtil,
+        executor::Executor, results::throughput, vulkan_util::VulkanData,
     };
-    use gpu_compute::{execute_util::generate_data, vulkan_util::VulkanData};
     use itertools::Itertools;
-    use num::{NumCast, Zero};
-    use ocl::{r#async::BufferSink, Buffer, MemFlags, OclPrm, ProQue, WriteGuard};
-    use ocl_futures::future::Future;
-    use std::{fmt::Debug, time::Duration};
-    use vulkano::buffer::BufferContents;
+    use nalgebra::Vector2;
+    use num::NumCast;
+    use ocl::ProQue;
+    use std::time::Duration;
 
-    pub fn do_cl_bench<Type, Acc>(
+    pub fn do_bench<Type, E, Acc>(
         g: &mut BenchmarkGroup<WallTime>,
+        vulkan: &mut VulkanData,
+        data: DataGenerator,
         data_size: u32,
         kernel_size: u32,
-        src: &str,
+        program: &E::Program,
+        parameters: E::Parameters,
         name: &str,
         accumulate: Acc,
     ) where
-        Type: Copy + NumCast + Pod + BufferContents + PartialEq + Debug + OclPrm + Zero,
-        Acc: Fn(Type, Type) -> Type,
+        Type: NumCast + CpuReducible,
+        E: Executor<Type>,
+        E::Parameters: Clone,
+        Acc: 'static + Clone + Fn(Type, Type) -> Type,
     {
+        g.throughput(throughput::<Type>(data_size));
         g.bench_with_input(BenchmarkId::new(name, data_size), &data_size, |b, _| {
-            let cl_program = ProQue::builder()
-                .src(src)
-                .dims(kernel_size)
-                .build()
-                .unwrap();
-
-            let source_buffer = Buffer::<Type>::builder()
-                .queue(cl_program.queue().clone())
-                .flags(
-                    MemFlags::empty()
-                        .read_only()
-                        .host_write_only()
-                        .alloc_host_ptr(),
-                )
-                .len(data_size)
-                .build()
-                .unwrap();
-
-            {
-                let source_sink = unsafe {
-                    BufferSink::from_buffer(
-                        source_buffer.clone(),
-                        Some(cl_program.queue().clone()),
-                        0,
-                        data_size as _,
-                    )
-                }
-                .unwrap();
-                let writer = source_sink.write();
-                let mut writer = writer.wait().unwrap();
-                writer.copy_from_slice(&generate_data(data_size).collect_vec());
-
-                let source_sink: BufferSink<Type> = WriteGuard::release(writer).into();
-                source_sink.flush().enq().unwrap().wait().unwrap();
-            }
+            let mut execute = E::setup(
+                vulkan,
+                program,
+                data.generate(data_size).collect_vec(),
+                Vector2::new(kernel_size, data_size / kernel_size),
+                parameters.clone(),
+                accumulate.clone(),
+            );
 
-            let output_buffer = cl_program
-                .buffer_builder::<Type>()
-                .flags(MemFlags::empty().host_read_only().write_only())
-                .build()
-                .unwrap();
-
-            let kernel = cl_program
-                .kernel_builder("sum")
-                .arg(kernel_size as u64)
-                .arg((data_size / kernel_size) as u64)
-                .arg(&source_buffer)
-                .arg(&output_buffer)
-                .build()
-                .unwrap();
-
-            let expected: Type = generate_data(data_size).reduce(&accumulate).unwrap();
-            let mut result = vec![Type::zero(); kernel_size as _];
-
-            b.iter(|| {
-                unsafe { kernel.enq() }.unwrap();
-                output_buffer.read(&mut result).enq().unwrap();
-                let result_sum = black_box(result.iter().copied().reduce(&accumulate)).unwrap();
-                // assert_eq!(result_sum, expected);
-            });
+            b.iter(|| execute.run(vulkan, true));
         });
     }
 
     fn criterion_benchmark(c: &mut Criterion) {
-        let vulkan = VulkanData::init();
+        let mut vulkan = VulkanData::init();
 
         let sizes = vulkan.profiling_sizes().clone();
+        let thread_count = vulkan.gpu_thread_count();
         println!("{:X?}", sizes);
 
+        let data = DataGenerator::from_env();
+        println!("Data: {data}");
+
         {
             let mut g = c.benchmark_group("call_times");
             g.measurement_time(Duration::from_secs(30));
@@ -118,11 +79,14 @@ pub mod all {
                 });
             }
 
-            do_cl_bench::<u32, _>(
+            do_bench::<u32, OpenClExecuteUtil<u32>, _>(
                 &mut g,
+                &mut vulkan,
+                data,
                 1,
                 1,
                 include_str!("../shaders/opencl/sum_column_major.cl"),
+                (),
                 "opencl",
                 |a, b| a + b,
             );
@@ -134,19 +98,25 @@ pub mod all {
             g.sample_size(10);
 
             for data_size in sizes.clone() {
-                do_cl_bench::<u32, _>(
+                do_bench::<u32, OpenClExecuteUtil<u32>, _>(
                     &mut g,
+                    &mut vulkan,
+                    data,
                     data_size,
-                    vulkan.gpu_thread_count(),
+                    thread_count,
                     include_str!("../shaders/opencl/sum_column_major.cl"),
+                    (),
                     "opencl_column_major",
                     |a, b| a + b,
                 );
-                do_cl_bench::<u32, _>(
+                do_bench::<u32, OpenClExecuteUtil<u32>, _>(
                     &mut g,
+                    &mut vulkan,
+                    data,
                     data_size,
-                    vulkan.gpu_thread_count(),
+                    thread_count,
                     include_str!("../shaders/opencl/sum_row_major.cl"),
+                    (),
                     "opencl_row_major",
                     |a, b| a + b,
                 );
@@ -158,19 +128,25 @@ pub mod all {
             g.sample_size(10);
 
             for data_size in sizes.clone() {
-                do_cl_bench::<f32, _>(
+                do_bench::<f32, OpenClExecuteUtil<f32>, _>(
                     &mut g,
+                    &mut vulkan,
+                    data,
                     data_size,
-                    vulkan.gpu_thread_count(),
+                    thread_count,
                     include_str!("../shaders/opencl/min_column_major_f32.cl"),
+                    (),
                     "opencl_column_major",
                     |a, b| a.min(b),
                 );
-                do_cl_bench::<f32, _>(
+                do_bench::<f32, OpenClExecuteUtil<f32>, _>(
                     &mut g,
+                    &mut vulkan,
+                    data,
                     data_size,
-                    vulkan.gpu_thread_count(),
+                    thread_count,
                     include_str!("../shaders/opencl/min_row_major_f32.cl"),
+                    (),
                     "opencl_row_major",
                     |a, b| a.min(b),
                 );
@@ -185,8 +161,7 @@ pub mod all {
 pub mod all {
     use criterion::{criterion_group, Criterion};
 
-    fn criterion_benchmark(_: &mut Criterion) {
-    }
+    fn criterion_benchmark(_: &mut Criterion) {}
     criterion_group!(benches, criterion_benchmark);
 }
 
@@ -2,12 +2,16 @@
 
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use gpu_compute::{
+    bench_matrix::{BenchMatrix, BenchShader},
     execute_util::{ExecuteParameters, ExecuteUtil, OutputKind, QuadMethod},
     execute_util_compute::{ComputeExecuteUtil, ComputeParameters, OutputModification},
-    vulkan_util::VulkanData,
+    executor::PluggableConstants,
+    results::{throughput, ResultSet},
+    vulkan_util::{SubgroupSupport, VulkanData},
 };
 use nalgebra::Vector2;
 use std::collections::HashSet;
+use vulkano::shader::ShaderStages;
 
 fn criterion_benchmark(c: &mut Criterion) {
     let mut vulkan = VulkanData::init();
@@ -34,6 +38,7 @@ fn criterion_benchmark(c: &mut Criterion) {
                 if !dedup.insert((group_size, data_size)) {
                     continue;
                 }
+                g.throughput(throughput::<u32>(data_size));
 
                 g.bench_with_input(
                     BenchmarkId::new(format!("group-{}", group_size), data_size),
@@ -41,7 +46,7 @@ fn criterion_benchmark(c: &mut Criterion) {
                     |b, data_size| {
                         let framebuffer_y = 4;
 
-                        let shader = buffer_none_sbuffer_loop::load(vulkan.device.clone()).unwrap();
+                        let shader = vulkan.load_shader(buffer_none_sbuffer_loop::load);
                         let mut execute = ExecuteUtil::<u32>::setup_storage_buffer(
                             &mut vulkan,
                             Vector2::new(group_size, data_size.div_ceil(group_size)),
@@ -82,18 +87,18 @@ fn criterion_benchmark(c: &mut Criterion) {
                 if !dedup.insert((group_size, data_size)) {
                     continue;
                 }
+                g.throughput(throughput::<u32>(data_size));
 
                 g.bench_with_input(
                     BenchmarkId::new(format!("group-{}", group_size), data_size),
                     &data_size,
                     |b, data_size| {
-                        let shader =
-                            buffer_none_sbuffer_loop_compute::load(vulkan.device.clone()).unwrap();
+                        let shader = vulkan.load_shader(buffer_none_sbuffer_loop_compute::load);
                         let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
                             &mut vulkan,
                             Vector2::new(group_size, data_size.div_ceil(group_size)),
                             &shader,
-                            buffer_none_sbuffer_loop_compute::SpecializationConstants {
+                            PluggableConstants {
                                 TEXTURE_SIZE_X: (group_size as i32) / 1,
                                 TEXTURE_SIZE_Y: 1,
                             },
@@ -125,18 +130,18 @@ fn criterion_benchmark(c: &mut Criterion) {
                 if !dedup.insert((group_size, data_size)) {
                     continue;
                 }
+                g.throughput(throughput::<u32>(data_size));
 
                 g.bench_with_input(
                     BenchmarkId::new(format!("group-{}", group_size), data_size),
                     &data_size,
                     |b, data_size| {
-                        let shader =
-                            buffer_none_sbuffer_loop_compute::load(vulkan.device.clone()).unwrap();
+                        let shader = vulkan.load_shader(buffer_none_sbuffer_loop_compute::load);
                         let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
                             &mut vulkan,
                             Vector2::new(group_size, data_size.div_ceil(group_size)),
                             &shader,
-                            buffer_none_sbuffer_loop_compute::SpecializationConstants {
+                            PluggableConstants {
                                 TEXTURE_SIZE_X: (group_size as i32) / 1,
                                 TEXTURE_SIZE_Y: 1,
                             },
@@ -155,6 +160,10 @@ fn criterion_benchmark(c: &mut Criterion) {
             }
         }
     }
+    // Loading the subgroup shader already fails without subgroup support
+    if vulkan
+        .subgroup_support()
+        .supports(ShaderStages::COMPUTE, SubgroupSupport::writer_operations())
     {
         let mut g = c.benchmark_group(format!("optimal_accumulate_size_compute_subgroup"));
         // g.measurement_time(std::time::Duration::from_secs(30));
@@ -169,18 +178,18 @@ fn criterion_benchmark(c: &mut Criterion) {
                 if !dedup.insert((group_size, data_size)) {
                     continue;
                 }
+                g.throughput(throughput::<u32>(data_size));
 
                 g.bench_with_input(
                     BenchmarkId::new(format!("group-{}", group_size), data_size),
                     &data_size,
                     |b, data_size| {
-                        let shader =
-                            compute_none_groupbuffer_loop::load(vulkan.device.clone()).unwrap();
+                        let shader = vulkan.load_shader(compute_none_groupbuffer_loop::load);
                         let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
                             &mut vulkan,
                             Vector2::new(group_size, data_size.div_ceil(group_size)),
                             &shader,
-                            compute_none_groupbuffer_loop::SpecializationConstants {
+                            PluggableConstants {
                                 TEXTURE_SIZE_X: (group_size as i32) / 1,
                                 TEXTURE_SIZE_Y: 1,
                             },
@@ -199,6 +208,24 @@ fn criterion_benchmark(c: &mut Criterion) {
             }
         }
     }
+    {
+        let mut g = c.benchmark_group("optimal_workgroup_size");
+        g.sample_size(10);
+
+        let matrix = BenchMatrix::new("optimal_workgroup_size", &vulkan)
+            .workgroup_sizes([32, 64, 128, 256, 512, 1024])
+            .shader(BenchShader::compute(
+                "compute_buffer_to_buffer",
+                vulkan.load_shader(buffer_none_sbuffer_loop_compute::load),
+                ComputeParameters::default(),
+            ));
+
+        let mut results = ResultSet::new(&mut vulkan);
+        matrix.run::<u32, _>(&mut g, &mut vulkan, &mut results, |a, b| a + b);
+        results
+            .save("target/results", "optimal_workgroup_size")
+            .unwrap();
+    }
 }
 
 criterion_group!(benches, criterion_benchmark);
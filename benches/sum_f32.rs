@@ -0,0 +1,79 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use gpu_compute::{
+    bench_matrix::{BenchMatrix, BenchShader},
+    cpu::ReduceOp,
+    execute_util::DataGenerator,
+    execute_util_compute::ComputeParameters,
+    results::ResultSet,
+    shader_registry::loader,
+    vulkan_util::VulkanData,
+};
+
+// Speed and error of the float summation modes of `get_data`, against the
+// data summed in f64. Every thread writes its own result and the partial
+// results are added on the CPU in order, so results are reproducible
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut vulkan = VulkanData::init();
+
+    let mut g = c.benchmark_group("gpu_sum_f32");
+    g.sample_size(10);
+
+    let profiling_sizes = vulkan.profiling_sizes();
+    println!("{:X?}", profiling_sizes);
+
+    let matrix = BenchMatrix::new("gpu_sum_f32", &vulkan)
+        .sizes(profiling_sizes)
+        .data(DataGenerator::from_env())
+        .reference(|a, b| a + b)
+        .shader(
+            BenchShader::compute(
+                "compute_buffer_to_buffer",
+                vulkan.load_shader(loader::<f32>(ReduceOp::Sum, "compute_buffer_to_buffer")),
+                ComputeParameters::default(),
+            )
+            .separate_read_buffer(&[false]),
+        )
+        .shader(
+            BenchShader::compute(
+                "compute_buffer_to_buffer_kahan",
+                vulkan.load_shader(loader::<f32>(
+                    ReduceOp::Sum,
+                    "compute_buffer_to_buffer_kahan",
+                )),
+                ComputeParameters::default(),
+            )
+            .separate_read_buffer(&[false]),
+        )
+        .shader(
+            BenchShader::compute(
+                "compute_buffer_to_buffer_pairwise",
+                vulkan.load_shader(loader::<f32>(
+                    ReduceOp::Sum,
+                    "compute_buffer_to_buffer_pairwise",
+                )),
+                ComputeParameters::default(),
+            )
+            .separate_read_buffer(&[false]),
+        );
+
+    let mut results = ResultSet::new(&mut vulkan);
+    if std::env::var_os("CALIBRATE_BANDWIDTH").is_some() {
+        results.calibrate(&mut vulkan);
+    }
+    matrix.run::<f32, _>(&mut g, &mut vulkan, &mut results, |a, b| a + b);
+    results.save("target/results", "gpu_sum_f32").unwrap();
+
+    for result in results.results.iter() {
+        if let Some(accuracy) = &result.accuracy {
+            println!(
+                "{}: relative error {:e}, reproducible {}",
+                result.id, accuracy.relative_error, accuracy.reproducible
+            );
+        }
+    }
+
+    drop(g);
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,13 @@
+#version 460
+
+#include <prelude.glsl>
+
+#include <raw_get_data/storage_buffer.glsl>
+#include <get_data/many_calls.glsl>
+#include <writer/attachment_blend.glsl>
+
+// The operation is the blend state or logic op of the pipeline
+
+bool condition(int x, int y, int z, DATA_TYPE data) {
+    return true;
+}
@@ -2,9 +2,9 @@
 
 #include <prelude.glsl>
 
-#include <raw_get_data/storage_buffer.glsl>
-#include <get_data/many_calls.glsl>
-#include <writer/attachment.glsl>
+#include <raw_get_data/storage_buffer_axis.glsl>
+#include <get_data/loop.glsl>
+#include <writer/buffer.glsl>
 
 bool condition(int x, int y, int z, DATA_TYPE data) {
     return true;
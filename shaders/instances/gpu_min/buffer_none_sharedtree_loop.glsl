@@ -0,0 +1,23 @@
+#version 460
+
+#ifdef TREE_SUBGROUP_FINAL
+#extension GL_KHR_shader_subgroup_basic : require
+#extension GL_KHR_shader_subgroup_shuffle : require
+#endif
+
+#include <prelude.glsl>
+
+#include <raw_get_data/storage_buffer.glsl>
+#include <get_data/loop.glsl>
+#include <writer/shared_tree.glsl>
+
+bool condition(int x, int y, int z, DATA_TYPE data) {
+    return true;
+}
+
+DATA_TYPE get_identity() {
+    return pos_infinity;
+}
+DATA_TYPE accumulate(DATA_TYPE acc, DATA_TYPE data) {
+    return min(acc, data);
+}
@@ -0,0 +1,18 @@
+#version 460
+
+#include <prelude.glsl>
+
+#include <raw_get_data/storage_buffer.glsl>
+#include <get_data/kahan.glsl>
+#include <writer/buffer.glsl>
+
+bool condition(int x, int y, int z, DATA_TYPE data) {
+    return true;
+}
+
+DATA_TYPE get_identity() {
+    return 0;
+}
+DATA_TYPE accumulate(DATA_TYPE acc, DATA_TYPE data) {
+    return acc + data;
+}
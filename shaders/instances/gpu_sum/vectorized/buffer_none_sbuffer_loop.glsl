@@ -3,6 +3,7 @@
 #ifndef DATA_TYPE
 #define DATA_TYPE uvec4
 #endif
+#define VECTOR_WIDTH 4
 
 #include <prelude.glsl>
 
@@ -0,0 +1,51 @@
+#include "../constants.glsl"
+
+// Sums the layers along z with Neumaier's compensated summation, the error no
+// longer grows with the number of layers. Adds the values directly, accumulate
+// and get_identity are not used. Scalar types only
+#if VECTOR_WIDTH > 1
+#error "get_data/kahan.glsl does not support vectorized shaders"
+#endif
+
+struct GetData {
+    DATA_TYPE data;
+    bool do_discard;
+};
+
+bool condition(int x, int y, int z, DATA_TYPE data);
+DATA_TYPE get_identity();
+DATA_TYPE accumulate(DATA_TYPE acc, DATA_TYPE data);
+
+GetData get_data(int x, int y) {
+    int to_z = get_z();
+
+    // precise keeps the compiler from reassociating the compensation away or
+    // fusing it into FMAs
+    precise DATA_TYPE sum = DATA_TYPE(0);
+    precise DATA_TYPE compensation = DATA_TYPE(0);
+    bool do_discard = true;
+
+    for (int z = 0; z < to_z; z++) {
+        if (!is_in_bounds(x, y, z)) {
+            continue;
+        }
+        DATA_TYPE data = get_data_raw(x, y, z, TEXTURE_SIZE_X, TEXTURE_SIZE_Y);
+
+#ifndef UNCONDITIONAL
+        if (!condition(x, y, z, data)) {
+            continue;
+        }
+#endif
+
+        precise DATA_TYPE t = sum + data;
+        if (abs(sum) >= abs(data)) {
+            compensation += (sum - t) + data;
+        } else {
+            compensation += (data - t) + sum;
+        }
+        sum = t;
+        do_discard = false;
+    }
+
+    return GetData(sum + compensation, do_discard);
+}
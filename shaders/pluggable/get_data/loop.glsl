@@ -9,6 +9,22 @@ bool condition(int x, int y, int z, DATA_TYPE data);
 DATA_TYPE get_identity();
 DATA_TYPE accumulate(DATA_TYPE acc, DATA_TYPE data);
 
+#if VECTOR_WIDTH > 1
+// The input is padded to whole vectors, the lanes of the last vector beyond
+// data_size are replaced by the identity
+DATA_TYPE mask_partial(int x, int y, int z, DATA_TYPE data) {
+    uint first = uint(x + (y * TEXTURE_SIZE_X) + (z * TEXTURE_SIZE_X * TEXTURE_SIZE_Y)) * VECTOR_WIDTH;
+    DATA_TYPE identity = get_identity();
+
+    for (int lane = 0; lane < VECTOR_WIDTH; lane++) {
+        if (first + uint(lane) >= pc.data_size) {
+            data[lane] = identity[lane];
+        }
+    }
+    return data;
+}
+#endif
+
 GetData get_data(int x, int y) {
     int to_z = get_z();
 
@@ -19,6 +35,9 @@ GetData get_data(int x, int y) {
             continue;
         }
         DATA_TYPE data = get_data_raw(x, y, z, TEXTURE_SIZE_X, TEXTURE_SIZE_Y);
+#if VECTOR_WIDTH > 1
+        data = mask_partial(x, y, z, data);
+#endif
 
 #ifndef UNCONDITIONAL
         if (condition(x, y, z, data)) {
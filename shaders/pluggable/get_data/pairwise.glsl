@@ -0,0 +1,70 @@
+#include "../constants.glsl"
+
+// Sums the layers along z pairwise: blocks of PAIRWISE_BLOCK layers are
+// accumulated in order, the block results are combined as a binary tree, so the
+// error grows with log(z) instead of z. Scalar types only
+#if VECTOR_WIDTH > 1
+#error "get_data/pairwise.glsl does not support vectorized shaders"
+#endif
+
+#ifndef PAIRWISE_BLOCK
+#define PAIRWISE_BLOCK 8
+#endif
+
+// Enough for 2^32 blocks
+#define PAIRWISE_LEVELS 32
+
+struct GetData {
+    DATA_TYPE data;
+    bool do_discard;
+};
+
+bool condition(int x, int y, int z, DATA_TYPE data);
+DATA_TYPE get_identity();
+DATA_TYPE accumulate(DATA_TYPE acc, DATA_TYPE data);
+
+GetData get_data(int x, int y) {
+    int to_z = get_z();
+
+    // levels[i] holds the result of 2^i blocks while bit i of blocks is set
+    DATA_TYPE levels[PAIRWISE_LEVELS];
+    uint blocks = 0;
+    bool do_discard = true;
+
+    for (int start = 0; start < to_z; start += PAIRWISE_BLOCK) {
+        precise DATA_TYPE block = get_identity();
+
+        for (int z = start; z < min(start + PAIRWISE_BLOCK, to_z); z++) {
+            if (!is_in_bounds(x, y, z)) {
+                continue;
+            }
+            DATA_TYPE data = get_data_raw(x, y, z, TEXTURE_SIZE_X, TEXTURE_SIZE_Y);
+
+#ifndef UNCONDITIONAL
+            if (!condition(x, y, z, data)) {
+                continue;
+            }
+#endif
+            block = accumulate(block, data);
+            do_discard = false;
+        }
+
+        // Carries like incrementing a binary counter, only results of equally
+        // many blocks are combined
+        uint level = 0;
+        for (; (blocks & (1u << level)) != 0; level++) {
+            block = accumulate(levels[level], block);
+        }
+        levels[level] = block;
+        blocks++;
+    }
+
+    precise DATA_TYPE acc = get_identity();
+    for (uint level = 0; level < PAIRWISE_LEVELS; level++) {
+        if ((blocks & (1u << level)) != 0) {
+            acc = accumulate(levels[level], acc);
+        }
+    }
+
+    return GetData(acc, do_discard);
+}
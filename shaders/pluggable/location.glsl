@@ -7,9 +7,10 @@ ivec2 get_coord();
 
 #ifdef COMPUTE_SHADER
 
-const uint WORKGROUP_SIZE = 64;
+// The local size defaults to 64 and is overridden by specialization constant 2
+layout(local_size_x = 64, local_size_x_id = 2, local_size_y = 1) in;
 
-layout(local_size_x = WORKGROUP_SIZE, local_size_y = 1) in;
+#define WORKGROUP_SIZE gl_WorkGroupSize.x
 
 int get_z() {
     return pc.z;
@@ -12,6 +12,11 @@
 #define OUTPUT_DATA_TYPE DATA_TYPE
 #endif
 
+// Number of values loaded at once by vectorized shaders
+#ifndef VECTOR_WIDTH
+#define VECTOR_WIDTH 1
+#endif
+
 #include "constants.glsl"
 
 
@@ -23,11 +28,16 @@ layout(push_constant) uniform pc_l{
 #endif
 } pc;
 
+// data_size counts scalar values, a partially filled last vector is still in
+// bounds
 bool is_in_bounds(uint index) {
-    return index < pc.data_size;
+    return index * VECTOR_WIDTH < pc.data_size;
 }
+// Compute dispatches are rounded up to whole workgroups, the surplus
+// invocations are beyond TEXTURE_SIZE_X
 bool is_in_bounds(uint x, uint y, uint z) {
-    return is_in_bounds(x + (y * TEXTURE_SIZE_X) + (z * TEXTURE_SIZE_X * TEXTURE_SIZE_Y));
+    return x < TEXTURE_SIZE_X
+        && is_in_bounds(x + (y * TEXTURE_SIZE_X) + (z * TEXTURE_SIZE_X * TEXTURE_SIZE_Y));
 }
 
 
@@ -0,0 +1,22 @@
+#include "../constants.glsl"
+
+#extension GL_EXT_scalar_block_layout: require
+
+// The tensor is viewed as [outer, AXIS_LENGTH, AXIS_INNER] in row-major order,
+// each (x, y) owns one output cell of [outer, AXIS_INNER] and z walks the axis
+layout (constant_id = 3) const int AXIS_LENGTH = 1;
+layout (constant_id = 4) const int AXIS_INNER = 1;
+
+layout(set = 0, binding = 0, scalar) readonly buffer b {
+    INPUT_DATA_TYPE values[];
+};
+
+INPUT_DATA_TYPE get_data_raw(
+        int x, int y, int z,
+        int size_x, int size_y
+        ) {
+    int cell = x + (y * size_x);
+    int index = ((cell / AXIS_INNER) * AXIS_LENGTH + z) * AXIS_INNER + (cell % AXIS_INNER);
+    INPUT_DATA_TYPE data = values[index];
+    return data;
+}
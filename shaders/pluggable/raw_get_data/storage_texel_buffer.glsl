@@ -0,0 +1,11 @@
+#include "../constants.glsl"
+
+layout(set = 0, binding = 0, r32ui) uniform readonly uimageBuffer values;
+
+INPUT_DATA_TYPE get_data_raw(
+        int x, int y, int z,
+        int size_x, int size_y
+        ) {
+    int index = x + (y * size_x) + (z * size_x * size_y);
+    return imageLoad(values, index).x;
+}
@@ -0,0 +1,18 @@
+#include "../constants.glsl"
+
+// One chunk of the input, the executors run once per chunk with data_size
+// set to the values in it. Must match UNIFORM_CHUNK_SIZE in src/executor.rs
+#define UNIFORM_CHUNK_VECTORS 4096
+
+// std140 pads scalar arrays to 16 bytes, so the values are packed in vectors
+layout(set = 0, binding = 0, std140) uniform b {
+    uvec4 values[UNIFORM_CHUNK_VECTORS];
+};
+
+INPUT_DATA_TYPE get_data_raw(
+        int x, int y, int z,
+        int size_x, int size_y
+        ) {
+    int index = x + (y * size_x) + (z * size_x * size_y);
+    return values[index / 4][index % 4];
+}
@@ -0,0 +1,20 @@
+#include "../constants.glsl"
+
+// The attachment format decides the output type, a float format needs vec4
+#ifndef OUTPUT_VECTOR_TYPE
+#define OUTPUT_VECTOR_TYPE uvec4
+#endif
+
+layout(location = 0) out OUTPUT_VECTOR_TYPE f_color;
+
+void main() {
+    ivec2 coord = get_coord();
+
+    GetData d = get_data(coord.x, coord.y);
+    // Blending or logic ops accumulate every instance into the attachment,
+    // fragments without data must leave it unchanged
+    if (d.do_discard) {
+        discard;
+    }
+    f_color = OUTPUT_VECTOR_TYPE(d.data, 0, 0, 0);
+}
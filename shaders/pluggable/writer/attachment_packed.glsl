@@ -0,0 +1,35 @@
+#include "../constants.glsl"
+
+// Every fragment writes OUTPUT_COMPONENTS partial results to each of
+// OUTPUT_ATTACHMENTS color attachments, TEXTURE_SIZE_X counts all of them
+#ifndef OUTPUT_COMPONENTS
+#define OUTPUT_COMPONENTS 1
+#endif
+
+#ifndef OUTPUT_ATTACHMENTS
+#define OUTPUT_ATTACHMENTS 1
+#endif
+
+#ifndef OUTPUT_VECTOR_TYPE
+#define OUTPUT_VECTOR_TYPE uvec4
+#endif
+
+#define OUTPUT_PARTIALS (OUTPUT_COMPONENTS * OUTPUT_ATTACHMENTS)
+
+layout(location = 0) out OUTPUT_VECTOR_TYPE f_color[OUTPUT_ATTACHMENTS];
+
+void main() {
+    ivec2 coord = get_coord();
+    // Partial i reads block i of the columns, so neighbouring fragments still
+    // read neighbouring data
+    int stride = TEXTURE_SIZE_X / OUTPUT_PARTIALS;
+
+    for (int a = 0; a < OUTPUT_ATTACHMENTS; a++) {
+        OUTPUT_VECTOR_TYPE texel = OUTPUT_VECTOR_TYPE(0);
+        for (int c = 0; c < OUTPUT_COMPONENTS; c++) {
+            int partial = a * OUTPUT_COMPONENTS + c;
+            texel[c] = get_data(coord.x + partial * stride, coord.y).data;
+        }
+        f_color[a] = texel;
+    }
+}
@@ -0,0 +1,33 @@
+#include "../constants.glsl"
+
+// Fallback for the subgroup writers on devices without subgroup arithmetic or
+// shuffles, reduces every workgroup in shared memory. Compute only
+layout(set = 1, binding = 0, std430) writeonly buffer out_buffer {
+    OUTPUT_DATA_TYPE out_values[];
+};
+
+shared DATA_TYPE partials[WORKGROUP_SIZE];
+
+void main() {
+    ivec2 coord = get_coord();
+    uint local = gl_LocalInvocationID.x;
+
+    partials[local] = get_data(coord.x, coord.y).data;
+    barrier();
+
+    // Halves the active invocations, rounded up as the workgroup size need not
+    // be a power of two
+    for (uint active = WORKGROUP_SIZE; active > 1; active = (active + 1) / 2) {
+        uint half_size = (active + 1) / 2;
+        if (local + half_size < active) {
+            partials[local] = accumulate(partials[local], partials[local + half_size]);
+        }
+        barrier();
+    }
+
+    if (local == 0) {
+        out_values[
+            (int(coord.x) + int(coord.y*TEXTURE_SIZE_X)) / WORKGROUP_SIZE
+        ] = partials[0];
+    }
+}
@@ -0,0 +1,83 @@
+#include "../constants.glsl"
+
+// Shared memory tree reduction of every workgroup, like kernelSumReduce in
+// src/cuda/cuda_sum.cu but writing one value per workgroup. Compute only
+//
+// TREE_INTERLEAVED: invocation i combines 2*s*i and 2*s*i + s, as the CUDA
+//     kernel does. Otherwise sequential addressing combines i and
+//     i + active/2, which keeps the active invocations together
+// TREE_SUBGROUP_FINAL: stop once a single subgroup can take over and finish
+//     with subgroup shuffles. Needs GL_KHR_shader_subgroup_shuffle and a fully
+//     populated first subgroup
+layout(set = 1, binding = 0, std430) writeonly buffer out_buffer {
+    OUTPUT_DATA_TYPE out_values[];
+};
+
+shared DATA_TYPE partials[WORKGROUP_SIZE];
+
+#ifdef TREE_SUBGROUP_FINAL
+// Lanes from `active` on only hold the identity and are skipped
+DATA_TYPE subgroup_reduce(DATA_TYPE acc, uint active) {
+    for (uint i = 1; i < gl_SubgroupSize; i *= 2) {
+        uint take_from = gl_SubgroupInvocationID + i;
+        DATA_TYPE received = subgroupShuffle(acc, take_from);
+
+        if (take_from < active) {
+            acc = accumulate(acc, received);
+        }
+    }
+    return acc;
+}
+#endif
+
+void main() {
+    ivec2 coord = get_coord();
+    uint local = gl_LocalInvocationID.x;
+
+    partials[local] = get_data(coord.x, coord.y).data;
+    barrier();
+
+#ifdef TREE_SUBGROUP_FINAL
+    uint until = gl_SubgroupSize;
+#else
+    uint until = 1;
+#endif
+
+    // The remaining values are partials[j * stride] for j < active. Halves
+    // rounded up as the workgroup size need not be a power of two
+    uint stride = 1;
+    uint active = WORKGROUP_SIZE;
+    for (; active > until; active = (active + 1) / 2) {
+#ifdef TREE_INTERLEAVED
+        uint index = 2 * stride * local;
+        if (index + stride < WORKGROUP_SIZE) {
+            partials[index] = accumulate(partials[index], partials[index + stride]);
+        }
+        stride *= 2;
+#else
+        uint half_size = (active + 1) / 2;
+        if (local + half_size < active) {
+            partials[local] = accumulate(partials[local], partials[local + half_size]);
+        }
+#endif
+        barrier();
+    }
+
+    uint out_index = (int(coord.x) + int(coord.y*TEXTURE_SIZE_X)) / WORKGROUP_SIZE;
+
+#ifdef TREE_SUBGROUP_FINAL
+    if (gl_SubgroupID == 0) {
+        uint lane = gl_SubgroupInvocationID;
+        DATA_TYPE acc = lane < active ? partials[lane * stride] : get_identity();
+        acc = subgroup_reduce(acc, active);
+
+        if (lane == 0) {
+            out_values[out_index] = acc;
+        }
+    }
+#else
+    if (local == 0) {
+        out_values[out_index] = partials[0];
+    }
+#endif
+}
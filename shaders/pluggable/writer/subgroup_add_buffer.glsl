@@ -4,9 +4,23 @@ layout(set = 1, binding = 0, std430) writeonly buffer out_buffer {
     OUTPUT_DATA_TYPE out_values[];
 };
 
+#ifdef COMPUTE_SHADER
+// The subgroup size the pipeline runs with, read back by the host to size the
+// output
+layout(set = 1, binding = 1, std430) writeonly buffer subgroup_size_buffer {
+    uint pipeline_subgroup_size;
+};
+#endif
+
 void main() {
     ivec2 coord = get_coord();
 
+#ifdef COMPUTE_SHADER
+    if (gl_GlobalInvocationID.x == 0) {
+        pipeline_subgroup_size = gl_SubgroupSize;
+    }
+#endif
+
     // gl_SubgroupSize
     // gl_SubgroupInvocationID
 
@@ -0,0 +1,491 @@
+use crate::{
+    bench_matrix::ShaderKind,
+    cpu::{CpuReducible, CpuStrategy, ReduceOp},
+    dispatch::{DispatchBucket, DispatchTable},
+    execute_util::{generate_data, ExecuteParameters, ExecuteUtil},
+    execute_util_compute::{ComputeExecuteUtil, ComputeParameters},
+    executor::Executor,
+    results::{Recorder, ResultSet, RunParameters},
+    shader_registry::{loader, ShaderLoader},
+    tuner::{AutoTuner, TuningConfig},
+    vulkan_util::VulkanData,
+};
+use bytemuck::Pod;
+use itertools::Itertools;
+use nalgebra::Vector2;
+use num::NumCast;
+use std::{any::type_name, fmt::Debug, iter::Sum, path::Path, sync::Arc};
+use vulkano::{buffer::BufferContents, shader::ShaderModule};
+
+/// Shaders implementing an operation for one element type
+pub struct GpuShaders {
+    pub graphics: ShaderLoader,
+    pub compute: ShaderLoader,
+
+    /// Variants loading 4 values at once, for a vectorization factor of 4
+    pub vectorized_graphics: Option<ShaderLoader>,
+    pub vectorized_compute: Option<ShaderLoader>,
+
+    /// Variants reducing along one axis of a tensor, see
+    /// [crate::axis_reduce]
+    pub axis_graphics: Option<ShaderLoader>,
+    pub axis_compute: Option<ShaderLoader>,
+}
+
+impl GpuShaders {
+    /// The shader of a GPU `strategy`, if there is one for the vectorization
+    /// factor
+    pub fn loader(&self, strategy: Strategy, vectorization_factor: u32) -> Option<ShaderLoader> {
+        match (strategy, vectorization_factor) {
+            (Strategy::Graphics, 1) => Some(self.graphics),
+            (Strategy::Compute, 1) => Some(self.compute),
+            (Strategy::Graphics, 4) => self.vectorized_graphics,
+            (Strategy::Compute, 4) => self.vectorized_compute,
+            _ => None,
+        }
+    }
+
+    /// The axis reduction shader of a GPU `strategy`
+    pub fn axis_loader(&self, strategy: Strategy) -> Option<ShaderLoader> {
+        match strategy {
+            Strategy::Graphics => self.axis_graphics,
+            Strategy::Compute => self.axis_compute,
+            Strategy::Cpu(_) => None,
+        }
+    }
+}
+
+/// Element types the [AutoReducer] can run on the CPU and, if there are
+/// shaders for the operation, the GPU
+pub trait AutoReducible:
+    CpuReducible + NumCast + Pod + BufferContents + PartialEq + Debug + Sum
+{
+    fn shaders(op: ReduceOp) -> Option<GpuShaders>;
+}
+
+impl AutoReducible for u32 {
+    fn shaders(op: ReduceOp) -> Option<GpuShaders> {
+        match op {
+            ReduceOp::Sum => Some(GpuShaders {
+                graphics: loader::<u32>(ReduceOp::Sum, "buffer_to_buffer"),
+                compute: loader::<u32>(ReduceOp::Sum, "compute_buffer_to_buffer"),
+                vectorized_graphics: Some(loader::<u32>(ReduceOp::Sum, "vector_buffer_to_buffer")),
+                vectorized_compute: Some(loader::<u32>(
+                    ReduceOp::Sum,
+                    "vector_compute_buffer_to_buffer",
+                )),
+                axis_graphics: Some(axis_sum_u32_graphics::load),
+                axis_compute: Some(axis_sum_u32_compute::load),
+            }),
+            ReduceOp::Min => None,
+        }
+    }
+}
+
+impl AutoReducible for f32 {
+    fn shaders(op: ReduceOp) -> Option<GpuShaders> {
+        match op {
+            ReduceOp::Sum => None,
+            ReduceOp::Min => Some(GpuShaders {
+                graphics: loader::<f32>(ReduceOp::Min, "graphics_buffer_to_buffer"),
+                compute: loader::<f32>(ReduceOp::Min, "compute_buffer_to_buffer"),
+                vectorized_graphics: Some(vector_min_f32_graphics::load),
+                vectorized_compute: Some(loader::<f32>(
+                    ReduceOp::Min,
+                    "vector_compute_buffer_to_buffer",
+                )),
+                axis_graphics: Some(axis_min_f32_graphics::load),
+                axis_compute: Some(axis_min_f32_compute::load),
+            }),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Strategy {
+    Cpu(CpuStrategy),
+    Graphics,
+    Compute,
+}
+
+impl Strategy {
+    pub fn all() -> impl Iterator<Item = Strategy> {
+        CpuStrategy::all()
+            .iter()
+            .copied()
+            .map(Strategy::Cpu)
+            .chain([Strategy::Graphics, Strategy::Compute])
+    }
+
+    /// Maps a bucket of a [DispatchTable] onto the implementation the reducer
+    /// has for its backend. Understands both the names of the calibration
+    /// run and the benchmark ids of `benches/cpu_perf.rs`.
+    pub fn from_bucket(bucket: &DispatchBucket) -> Self {
+        match bucket.backend.as_str() {
+            "graphics" => Strategy::Graphics,
+            "compute" => Strategy::Compute,
+            _ => {
+                let parallel = bucket.strategy.contains("parallel");
+                let simd = bucket.strategy.contains("simd") || bucket.strategy.contains("vector");
+
+                Strategy::Cpu(match (parallel, simd) {
+                    (false, false) => CpuStrategy::Scalar,
+                    (true, false) => CpuStrategy::Parallel,
+                    (false, true) => CpuStrategy::Simd,
+                    (true, true) => CpuStrategy::ParallelSimd,
+                })
+            },
+        }
+    }
+
+    pub fn backend(self) -> &'static str {
+        match self {
+            Strategy::Cpu(_) => "cpu",
+            Strategy::Graphics => "graphics",
+            Strategy::Compute => "compute",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::Cpu(CpuStrategy::Scalar) => "scalar",
+            Strategy::Cpu(CpuStrategy::Parallel) => "parallel",
+            Strategy::Cpu(CpuStrategy::Simd) => "simd",
+            Strategy::Cpu(CpuStrategy::ParallelSimd) => "parallel_simd",
+            Strategy::Graphics => "graphics",
+            Strategy::Compute => "compute",
+        }
+    }
+}
+
+/// Reduces slices with whichever CPU or GPU path a tuning table says is
+/// fastest for their size
+pub struct AutoReducer {
+    pub vulkan: VulkanData,
+    tables: Vec<DispatchTable>,
+    tuner: Option<AutoTuner>,
+}
+
+impl AutoReducer {
+    pub fn new(vulkan: VulkanData, tables: Vec<DispatchTable>) -> Self {
+        Self {
+            vulkan,
+            tables,
+            tuner: None,
+        }
+    }
+
+    /// Launches the GPU paths with the configurations `tuner` found, instead
+    /// of [TuningConfig::fallback]
+    pub fn with_tuner(mut self, tuner: AutoTuner) -> Self {
+        self.tuner = Some(tuner);
+        self
+    }
+
+    /// Loads a table written by the `crossover` tool or [AutoReducer::save]
+    pub fn load<P: AsRef<Path>>(vulkan: VulkanData, path: P) -> std::io::Result<Self> {
+        Ok(Self::new(vulkan, DispatchTable::read_json(path)?))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        DispatchTable::write_json(&self.tables, path)
+    }
+
+    /// Builds the tuning table by timing every strategy on this device,
+    /// including upload and read back
+    pub fn calibrate(vulkan: VulkanData) -> Self {
+        let mut reducer = Self::new(vulkan, vec![]);
+        let mut results = ResultSet::new(&mut reducer.vulkan);
+
+        let sizes = reducer
+            .vulkan
+            .profiling_sizes()
+            .into_iter()
+            .step_by(2)
+            .filter(|size| *size <= 1 << 26)
+            .collect_vec();
+
+        reducer.calibrate_op::<u32>(&mut results, ReduceOp::Sum, &sizes);
+        reducer.calibrate_op::<f32>(&mut results, ReduceOp::Min, &sizes);
+
+        reducer.tables = DispatchTable::analyze(&[results]);
+        reducer
+    }
+
+    fn calibrate_op<T: AutoReducible>(
+        &mut self,
+        results: &mut ResultSet,
+        op: ReduceOp,
+        sizes: &[u32],
+    ) {
+        const ITERATIONS: usize = 5;
+
+        for size in sizes.iter().copied() {
+            println!("Calibrating {} with {} elements", op.name(), size);
+            let data = generate_data::<T>(size).collect_vec();
+
+            for strategy in Strategy::all() {
+                if !self.supports::<T>(op, strategy) {
+                    continue;
+                }
+
+                let mut recorder = Recorder::default();
+                for _ in 0..ITERATIONS {
+                    recorder.measure_cpu(|| self.reduce_with(&data, op, strategy));
+                }
+
+                results.record(
+                    &format!("auto_{}", op.name()),
+                    &format!("{}/{size}", strategy.name()),
+                    RunParameters {
+                        backend: strategy.backend().to_string(),
+                        ..RunParameters::cpu::<T>(strategy.name(), size)
+                    },
+                    recorder,
+                );
+            }
+        }
+    }
+
+    pub fn tables(&self) -> &[DispatchTable] {
+        &self.tables
+    }
+
+    pub fn supports<T: AutoReducible>(&self, op: ReduceOp, strategy: Strategy) -> bool {
+        match strategy {
+            Strategy::Cpu(_) => true,
+            Strategy::Graphics | Strategy::Compute => T::shaders(op).is_some(),
+        }
+    }
+
+    /// The strategy the tuning table picks for `len` elements, falling back
+    /// to the parallel SIMD CPU path if the table has no entry or picks a GPU
+    /// path there are no shaders for
+    pub fn strategy<T: AutoReducible>(&self, op: ReduceOp, len: usize) -> Strategy {
+        self.tables
+            .iter()
+            .find(|table| table.operation == op.name() && table.element_type == type_name::<T>())
+            .and_then(|table| table.lookup(len as u64))
+            .map(Strategy::from_bucket)
+            .filter(|strategy| self.supports::<T>(op, *strategy))
+            .unwrap_or(Strategy::Cpu(CpuStrategy::ParallelSimd))
+    }
+
+    pub fn reduce<T: AutoReducible>(&mut self, data: &[T], op: ReduceOp) -> T {
+        let strategy = self.strategy::<T>(op, data.len());
+        self.reduce_with(data, op, strategy)
+    }
+
+    pub fn reduce_with<T: AutoReducible>(
+        &mut self,
+        data: &[T],
+        op: ReduceOp,
+        strategy: Strategy,
+    ) -> T {
+        match strategy {
+            Strategy::Cpu(cpu) => cpu.reduce(data, op),
+            _ if data.is_empty() => op.identity(),
+            Strategy::Graphics | Strategy::Compute => {
+                let config = self
+                    .tuner
+                    .as_ref()
+                    .and_then(|tuner| tuner.lookup::<T>(op, strategy, data.len()))
+                    .map(|entry| entry.config)
+                    .unwrap_or_else(|| TuningConfig::fallback(&self.vulkan));
+
+                GpuExecutor::setup(&mut self.vulkan, op, strategy, &config, data)
+                    .run(&mut self.vulkan, true)
+            },
+        }
+    }
+}
+
+/// A GPU reduction on either pipeline, set up by the reducer with a
+/// [TuningConfig] or with any parameters by `gpu_compute-bench`
+pub enum GpuExecutor<T> {
+    Graphics(ExecuteUtil<T>),
+    Compute(ComputeExecuteUtil<T>),
+}
+
+impl<T: AutoReducible> GpuExecutor<T> {
+    /// Uploads `data` in the layout of [TuningConfig::layout]
+    pub(crate) fn setup(
+        vulkan: &mut VulkanData,
+        op: ReduceOp,
+        strategy: Strategy,
+        config: &TuningConfig,
+        data: &[T],
+    ) -> Self {
+        let load = T::shaders(op)
+            .and_then(|shaders| shaders.loader(strategy, config.vectorization_factor))
+            .expect("No shaders for this operation, type and vectorization factor");
+        let shader = vulkan.load_shader(load);
+
+        let kind = match strategy {
+            Strategy::Graphics => ShaderKind::Graphics(ExecuteParameters {
+                vectorization_factor: config.vectorization_factor,
+                framebuffer_y: config.framebuffer_y,
+                final_accumulation: Some(op),
+                ..Default::default()
+            }),
+            Strategy::Compute => ShaderKind::Compute(ComputeParameters {
+                vectorization_factor: config.vectorization_factor,
+                workgroup_size: config.workgroup_size,
+                final_accumulation: Some(op),
+                ..Default::default()
+            }),
+            Strategy::Cpu(_) => panic!("{strategy:?} is not a GPU strategy"),
+        };
+
+        Self::with_parameters(
+            vulkan,
+            &shader,
+            kind,
+            data.to_vec(),
+            config.layout(data.len()),
+            op,
+        )
+    }
+
+    /// Uploads `data` for `shader`, run with the parameters of `kind`
+    pub fn with_parameters(
+        vulkan: &mut VulkanData,
+        shader: &Arc<ShaderModule>,
+        kind: ShaderKind,
+        data: Vec<T>,
+        data_size: Vector2<u32>,
+        op: ReduceOp,
+    ) -> Self {
+        let accumulate = move |a, b| op.apply(a, b);
+
+        match kind {
+            ShaderKind::Graphics(parameters) => GpuExecutor::Graphics(ExecuteUtil::setup(
+                vulkan, shader, data, data_size, parameters, accumulate,
+            )),
+            ShaderKind::Compute(parameters) => GpuExecutor::Compute(ComputeExecuteUtil::setup(
+                vulkan, shader, data, data_size, parameters, accumulate,
+            )),
+        }
+    }
+
+    pub fn run(&mut self, vulkan: &mut VulkanData, separate_read_buffer: bool) -> T {
+        match self {
+            GpuExecutor::Graphics(execute) => execute.run(vulkan, separate_read_buffer),
+            GpuExecutor::Compute(execute) => execute.run(vulkan, separate_read_buffer),
+        }
+    }
+
+    pub fn expected(&self) -> T {
+        match self {
+            GpuExecutor::Graphics(execute) => execute.expected(),
+            GpuExecutor::Compute(execute) => execute.expected(),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            GpuExecutor::Graphics(execute) => execute.describe(),
+            GpuExecutor::Compute(execute) => execute.describe(),
+        }
+    }
+}
+
+mod vector_min_f32_graphics {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "shaders/instances/gpu_min/vectorized/buffer_none_sbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("DATA_TYPE", "vec4")],
+    }
+}
+
+mod axis_sum_u32_graphics {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "shaders/instances/gpu_sum/axis_buffer_none_sbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+    }
+}
+
+mod axis_sum_u32_compute {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_sum/axis_buffer_none_sbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1")],
+    }
+}
+
+mod axis_min_f32_graphics {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "shaders/instances/gpu_min/axis_buffer_none_sbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("DATA_TYPE", "float")],
+    }
+}
+
+mod axis_min_f32_compute {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_min/axis_buffer_none_sbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1"), ("DATA_TYPE", "float")],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::iproduct;
+
+    const PRIME_LENGTHS: &[usize] = &[2, 3, 61, 127, 4099, 65537, 1_000_003];
+
+    /// Every GPU layout has to give the CPU result, whatever remainder is left
+    /// in the last row, workgroup or vector
+    fn check<T: AutoReducible>(op: ReduceOp) {
+        let mut vulkan = VulkanData::init();
+
+        for len in PRIME_LENGTHS.iter().copied() {
+            let data = generate_data::<T>(len as u32).collect_vec();
+            let expected = CpuStrategy::Scalar.reduce(&data, op);
+
+            let configs = iproduct!(
+                [Strategy::Graphics, Strategy::Compute],
+                [64, 1000],
+                [1, 4],
+                [1, 4]
+            );
+            for (strategy, thread_count, framebuffer_y, vectorization_factor) in configs {
+                if strategy == Strategy::Compute && framebuffer_y != 1 {
+                    continue;
+                }
+                let config = TuningConfig {
+                    workgroup_size: 64,
+                    thread_count,
+                    framebuffer_y,
+                    vectorization_factor,
+                };
+
+                let result = GpuExecutor::setup(&mut vulkan, op, strategy, &config, &data)
+                    .run(&mut vulkan, true);
+                assert_eq!(
+                    result, expected,
+                    "{strategy:?} {config:?} with {len} elements"
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn prime_lengths_sum_u32() {
+        check::<u32>(ReduceOp::Sum);
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn prime_lengths_min_f32() {
+        check::<f32>(ReduceOp::Min);
+    }
+}
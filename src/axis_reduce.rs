@@ -0,0 +1,435 @@
+use crate::{
+    auto_reducer::{AutoReducible, Strategy},
+    cpu::{CpuReducible, ReduceOp},
+    execute_util::{ExecuteParameters, ExecuteUtil, OutputKind},
+    execute_util_compute::{ComputeExecuteUtil, ComputeParameters},
+    executor::AxisConstants,
+    vulkan_util::VulkanData,
+};
+use itertools::Itertools;
+use nalgebra::Vector2;
+
+/// Order of the dimensions of a tensor in memory
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Layout {
+    /// The last dimension is contiguous
+    RowMajor,
+    /// The first dimension is contiguous
+    ColumnMajor,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TensorShape {
+    pub dims: Vec<usize>,
+    pub layout: Layout,
+}
+
+/// An axis of a tensor seen as `[outer, length, inner]` in memory, whatever
+/// the layout of the tensor
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct AxisView {
+    pub outer: usize,
+    pub length: usize,
+    pub inner: usize,
+}
+
+impl AxisView {
+    /// Values left after reducing the axis
+    pub fn cells(&self) -> usize {
+        self.outer * self.inner
+    }
+}
+
+impl TensorShape {
+    pub fn row_major(dims: impl Into<Vec<usize>>) -> Self {
+        Self {
+            dims: dims.into(),
+            layout: Layout::RowMajor,
+        }
+    }
+
+    pub fn column_major(dims: impl Into<Vec<usize>>) -> Self {
+        Self {
+            dims: dims.into(),
+            layout: Layout::ColumnMajor,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.dims.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// How `axis` lies in memory
+    pub fn axis_view(&self, axis: usize) -> AxisView {
+        let before: usize = self.dims[..axis].iter().product();
+        let after: usize = self.dims[axis + 1..].iter().product();
+
+        let (outer, inner) = match self.layout {
+            Layout::RowMajor => (before, after),
+            Layout::ColumnMajor => (after, before),
+        };
+
+        AxisView {
+            outer,
+            length: self.dims[axis],
+            inner,
+        }
+    }
+
+    /// The shape left after reducing `axes`, in the same layout
+    pub fn reduced(&self, axes: &[usize]) -> Self {
+        Self {
+            dims: self
+                .dims
+                .iter()
+                .enumerate()
+                .filter(|(axis, _)| !axes.contains(axis))
+                .map(|(_, dim)| *dim)
+                .collect(),
+            layout: self.layout,
+        }
+    }
+}
+
+/// Reduces one axis of `data` on the CPU
+pub fn reduce_axis_cpu<T: CpuReducible>(data: &[T], view: AxisView, op: ReduceOp) -> Vec<T> {
+    itertools::iproduct!(0..view.outer, 0..view.inner)
+        .map(|(outer, inner)| {
+            (0..view.length)
+                .map(|i| data[(outer * view.length + i) * view.inner + inner])
+                .fold(op.identity(), |acc, value| op.apply(acc, value))
+        })
+        .collect()
+}
+
+/// Reduces `axes` of a tensor, returning the smaller tensor and its shape
+pub fn reduce_axes_cpu<T: CpuReducible>(
+    data: &[T],
+    shape: &TensorShape,
+    axes: &[usize],
+    op: ReduceOp,
+) -> (Vec<T>, TensorShape) {
+    reduce_each_axis(data, shape, axes, |data, view| {
+        reduce_axis_cpu(data, view, op)
+    })
+}
+
+/// Reduces `axes` of a tensor with `strategy`, one pass per axis. The GPU
+/// paths let every fragment or invocation own one output value and walk the
+/// axis along z. Axes whose output no framebuffer covers exactly are reduced
+/// with compute instead of graphics.
+pub fn reduce_axes<T: AutoReducible>(
+    vulkan: &mut VulkanData,
+    data: &[T],
+    shape: &TensorShape,
+    axes: &[usize],
+    op: ReduceOp,
+    strategy: Strategy,
+) -> (Vec<T>, TensorShape) {
+    reduce_each_axis(data, shape, axes, |data, view| match strategy {
+        Strategy::Cpu(_) => reduce_axis_cpu(data, view, op),
+        _ if data.is_empty() => vec![op.identity(); view.cells()],
+        Strategy::Graphics | Strategy::Compute => reduce_axis_gpu(vulkan, data, view, op, strategy),
+    })
+}
+
+/// Reduces the axes from the last to the first, so the indices of the
+/// remaining ones stay valid
+fn reduce_each_axis<T: Clone>(
+    data: &[T],
+    shape: &TensorShape,
+    axes: &[usize],
+    mut reduce: impl FnMut(&[T], AxisView) -> Vec<T>,
+) -> (Vec<T>, TensorShape) {
+    assert_eq!(data.len(), shape.len(), "Data does not match {shape:?}");
+    assert!(
+        axes.iter().all(|axis| *axis < shape.dims.len()),
+        "Axes {axes:?} out of range for {shape:?}"
+    );
+
+    let mut data = data.to_vec();
+    let mut shape = shape.clone();
+    for axis in axes.iter().copied().sorted_by(|a, b| b.cmp(a)).dedup() {
+        data = reduce(&data, shape.axis_view(axis));
+        shape = shape.reduced(&[axis]);
+    }
+
+    (data, shape)
+}
+
+fn reduce_axis_gpu<T: AutoReducible>(
+    vulkan: &mut VulkanData,
+    data: &[T],
+    view: AxisView,
+    op: ReduceOp,
+    strategy: Strategy,
+) -> Vec<T> {
+    let cells = view.cells() as u32;
+    let data_size = Vector2::new(cells, view.length as u32);
+
+    let properties = vulkan.physical_device.properties();
+    let framebuffer_y = framebuffer_rows(
+        cells,
+        properties.max_framebuffer_width,
+        properties.max_framebuffer_height,
+    );
+    // Cell counts no framebuffer covers exactly, e.g. primes above the maximum
+    // width, run on the compute path
+    let strategy = match (strategy, framebuffer_y) {
+        (Strategy::Graphics, None) => Strategy::Compute,
+        _ => strategy,
+    };
+
+    let load = T::shaders(op)
+        .and_then(|shaders| shaders.axis_loader(strategy))
+        .expect("No axis shaders for this operation and type");
+    let shader = vulkan.load_shader(load);
+
+    let mut partials = match strategy {
+        Strategy::Graphics => {
+            let framebuffer_y = framebuffer_y.unwrap();
+            let sc = AxisConstants {
+                TEXTURE_SIZE_X: (cells / framebuffer_y) as _,
+                TEXTURE_SIZE_Y: framebuffer_y as _,
+                AXIS_LENGTH: view.length as _,
+                AXIS_INNER: view.inner as _,
+            };
+
+            ExecuteUtil::setup_storage_buffer_from_data(
+                vulkan,
+                data.to_vec(),
+                data_size,
+                &shader,
+                sc,
+                ExecuteParameters {
+                    output: OutputKind::Buffer,
+                    framebuffer_y,
+                    final_accumulation: Some(op),
+                    ..Default::default()
+                },
+                move |a, b| op.apply(a, b),
+            )
+            .run_partials(vulkan)
+        },
+        Strategy::Compute => {
+            let sc = AxisConstants {
+                TEXTURE_SIZE_X: cells as _,
+                TEXTURE_SIZE_Y: 1,
+                AXIS_LENGTH: view.length as _,
+                AXIS_INNER: view.inner as _,
+            };
+
+            ComputeExecuteUtil::setup_storage_buffer_from_data(
+                vulkan,
+                data.to_vec(),
+                data_size,
+                &shader,
+                sc,
+                ComputeParameters {
+                    final_accumulation: Some(op),
+                    ..Default::default()
+                },
+                move |a, b| op.apply(a, b),
+            )
+            .run_partials(vulkan)
+        },
+        Strategy::Cpu(_) => panic!("{strategy:?} is not a GPU strategy"),
+    };
+
+    // Compute dispatches are rounded up to whole workgroups
+    partials.truncate(view.cells());
+    partials
+}
+
+/// Rows of a framebuffer whose fragments cover the cells exactly, as the
+/// bounds check of the shader needs
+fn framebuffer_rows(cells: u32, max_width: u32, max_height: u32) -> Option<u32> {
+    (1..=cells.min(max_height)).find(|y| cells % y == 0 && cells / y <= max_width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execute_util::generate_data;
+
+    /// Reduces by walking every element with its full index, independent of
+    /// [AxisView]
+    fn naive<T: CpuReducible>(
+        data: &[T],
+        shape: &TensorShape,
+        axes: &[usize],
+        op: ReduceOp,
+    ) -> Vec<T> {
+        let reduced = shape.reduced(axes);
+        let mut out = vec![op.identity(); reduced.len()];
+
+        // Strides of the dimensions in memory
+        let strides = |dims: &[usize], layout: Layout| {
+            let mut strides = vec![0; dims.len()];
+            let mut stride = 1;
+            let order = match layout {
+                Layout::RowMajor => (0..dims.len()).rev().collect_vec(),
+                Layout::ColumnMajor => (0..dims.len()).collect_vec(),
+            };
+            for axis in order {
+                strides[axis] = stride;
+                stride *= dims[axis];
+            }
+            strides
+        };
+        let in_strides = strides(&shape.dims, shape.layout);
+        let out_strides = strides(&reduced.dims, reduced.layout);
+
+        for index in shape
+            .dims
+            .iter()
+            .map(|dim| 0..*dim)
+            .multi_cartesian_product()
+        {
+            let from = index
+                .iter()
+                .zip(&in_strides)
+                .map(|(i, s)| i * s)
+                .sum::<usize>();
+            let to = index
+                .iter()
+                .enumerate()
+                .filter(|(axis, _)| !axes.contains(axis))
+                .zip(&out_strides)
+                .map(|((_, i), s)| i * s)
+                .sum::<usize>();
+            out[to] = op.apply(out[to], data[from]);
+        }
+
+        out
+    }
+
+    fn shapes() -> Vec<TensorShape> {
+        [
+            vec![7],
+            vec![3, 5],
+            vec![1, 4],
+            vec![4, 3, 5],
+            vec![2, 3, 1, 4],
+        ]
+        .into_iter()
+        .flat_map(|dims| {
+            [
+                TensorShape::row_major(dims.clone()),
+                TensorShape::column_major(dims),
+            ]
+        })
+        .collect()
+    }
+
+    fn axis_sets(rank: usize) -> Vec<Vec<usize>> {
+        (0..rank)
+            .powerset()
+            .filter(|axes| !axes.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn framebuffer_rows_cover_cells_exactly() {
+        assert_eq!(framebuffer_rows(100, 16384, 16384), Some(1));
+        assert_eq!(framebuffer_rows(2 * 16384, 16384, 16384), Some(2));
+        assert_eq!(framebuffer_rows(3 * 16384, 16384, 2), None);
+        // A prime above the maximum width has no exact layout
+        assert_eq!(framebuffer_rows(16411, 16384, 16384), None);
+    }
+
+    #[test]
+    fn cpu_matches_naive() {
+        for shape in shapes() {
+            let data = generate_data::<u32>(shape.len() as u32).collect_vec();
+
+            for axes in axis_sets(shape.dims.len()) {
+                for op in [ReduceOp::Sum, ReduceOp::Min] {
+                    let (result, reduced) = reduce_axes_cpu(&data, &shape, &axes, op);
+                    assert_eq!(reduced, shape.reduced(&axes));
+                    assert_eq!(
+                        result,
+                        naive(&data, &shape, &axes, op),
+                        "{op:?} of {axes:?} in {shape:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    /// Like the OpenCL `sum_row_major` and `sum_column_major` kernels, the
+    /// sums of a matrix do not depend on how it is stored
+    #[test]
+    fn matrix_sums_ignore_layout() {
+        let (rows, columns) = (3, 4);
+        let row_major = (0..(rows * columns) as u32).collect_vec();
+        let column_major = (0..columns)
+            .flat_map(|column| {
+                let row_major = &row_major;
+                (0..rows).map(move |row| row_major[row * columns + column])
+            })
+            .collect_vec();
+
+        let sums = |data: &[u32], shape: TensorShape, axis| {
+            reduce_axes_cpu(data, &shape, &[axis], ReduceOp::Sum).0
+        };
+
+        let row_sums = vec![6, 22, 38];
+        let column_sums = vec![12, 15, 18, 21];
+        for (data, shape) in [
+            (&row_major, TensorShape::row_major([rows, columns])),
+            (&column_major, TensorShape::column_major([rows, columns])),
+        ] {
+            assert_eq!(sums(data, shape.clone(), 1), row_sums);
+            assert_eq!(sums(data, shape, 0), column_sums);
+        }
+    }
+
+    #[test]
+    fn reducing_every_axis_gives_a_scalar() {
+        let shape = TensorShape::row_major([2, 3, 4]);
+        let data = generate_data::<u32>(shape.len() as u32).collect_vec();
+
+        let (result, reduced) = reduce_axes_cpu(&data, &shape, &[2, 0, 1, 0], ReduceOp::Sum);
+        assert!(reduced.dims.is_empty());
+        assert_eq!(result, vec![data.iter().sum::<u32>()]);
+    }
+
+    fn check_gpu<T: AutoReducible>(op: ReduceOp) {
+        let mut vulkan = VulkanData::init();
+
+        for shape in shapes().into_iter().chain([
+            TensorShape::row_major([61, 127]),
+            TensorShape::column_major([127, 61]),
+        ]) {
+            let data = generate_data::<T>(shape.len() as u32).collect_vec();
+
+            for axes in axis_sets(shape.dims.len()) {
+                let expected = reduce_axes_cpu(&data, &shape, &axes, op);
+                for strategy in [Strategy::Graphics, Strategy::Compute] {
+                    assert_eq!(
+                        reduce_axes(&mut vulkan, &data, &shape, &axes, op, strategy),
+                        expected,
+                        "{strategy:?} {op:?} of {axes:?} in {shape:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn gpu_matches_cpu_sum_u32() {
+        check_gpu::<u32>(ReduceOp::Sum);
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn gpu_matches_cpu_min_f32() {
+        check_gpu::<f32>(ReduceOp::Min);
+    }
+}
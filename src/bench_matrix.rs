@@ -0,0 +1,777 @@
+use crate::{
+    cpu::CpuReducible,
+    execute_util::{DataGenerator, ExecuteParameters, ExecuteUtil, OutputKind, QuadMethod},
+    execute_util_compute::{ComputeExecuteUtil, ComputeParameters},
+    executor::{Executor, PluggableConstants, UNIFORM_CHUNK_SIZE},
+    results::{throughput, Recorder, ResultSet, RunParameters},
+    vulkan_util::VulkanData,
+};
+use bytemuck::Pod;
+use criterion::{measurement::WallTime, BenchmarkGroup, BenchmarkId};
+use itertools::{iproduct, Itertools};
+use nalgebra::Vector2;
+use num::{NumCast, Zero};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    iter::Sum,
+    mem::discriminant,
+    rc::Rc,
+    sync::Arc,
+};
+use vulkano::{
+    buffer::BufferContents,
+    device::physical::SubgroupFeatures,
+    format::Format,
+    shader::{ShaderModule, ShaderStages},
+};
+
+pub type Constraint = Rc<dyn Fn(&BenchCase) -> bool>;
+pub type Reference = Rc<dyn Fn(f64, f64) -> f64>;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum InputKind {
+    StorageBuffer,
+    Sampler2D,
+    StorageImage1D,
+    StorageImage2D,
+    UniformTexelBuffer,
+    StorageTexelBuffer,
+    /// Bound in chunks, see [UNIFORM_CHUNK_SIZE]. Devices with a smaller
+    /// `max_uniform_buffer_range` than a chunk don't support it
+    UniformBuffer,
+}
+
+impl InputKind {
+    /// Whether the device supports the input format and can create an image
+    /// or texel buffer holding `data_size`, or bind a whole uniform buffer
+    /// chunk
+    pub fn supports(self, vulkan: &VulkanData, data_size: Vector2<u32>) -> bool {
+        let properties = vulkan.physical_device.properties();
+        // Every image and texel buffer input holds R32_UINT texels
+        let capability = vulkan.format_capability(Format::R32_UINT);
+        match self {
+            InputKind::StorageBuffer => true,
+            InputKind::UniformBuffer => {
+                UNIFORM_CHUNK_SIZE * 4 <= properties.max_uniform_buffer_range as usize
+            },
+            InputKind::StorageImage1D => {
+                capability.storage_image
+                    && data_size.x * data_size.y <= properties.max_image_dimension1_d
+            },
+            InputKind::Sampler2D => {
+                capability.sampled_image
+                    && data_size.x <= properties.max_image_dimension2_d
+                    && data_size.y <= properties.max_image_dimension2_d
+            },
+            InputKind::StorageImage2D => {
+                capability.storage_image
+                    && data_size.x <= properties.max_image_dimension2_d
+                    && data_size.y <= properties.max_image_dimension2_d
+            },
+            InputKind::UniformTexelBuffer => {
+                capability.uniform_texel_buffer
+                    && data_size.x * data_size.y <= properties.max_texel_buffer_elements
+            },
+            InputKind::StorageTexelBuffer => {
+                capability.storage_texel_buffer
+                    && data_size.x * data_size.y <= properties.max_texel_buffer_elements
+            },
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum ShaderKind {
+    Graphics(ExecuteParameters),
+    Compute(ComputeParameters),
+}
+
+/// A shader and the parameters it has to be run with.
+///
+/// The output kind and vectorization factor of the parameters describe what
+/// the shader was compiled for, the matrix only combines it with axes that
+/// match.
+#[derive(Clone)]
+pub struct BenchShader {
+    pub name: String,
+    pub module: Arc<ShaderModule>,
+    pub kind: ShaderKind,
+    pub input: InputKind,
+    pub separate_read_buffer: Vec<bool>,
+    /// Appended to the name of cases that read back from cpu visible memory
+    pub memory_suffix: String,
+
+    constraints: Vec<Constraint>,
+}
+
+impl BenchShader {
+    pub fn graphics(name: &str, module: Arc<ShaderModule>, parameters: ExecuteParameters) -> Self {
+        Self::new(name, module, ShaderKind::Graphics(parameters))
+    }
+
+    pub fn compute(name: &str, module: Arc<ShaderModule>, parameters: ComputeParameters) -> Self {
+        Self::new(name, module, ShaderKind::Compute(parameters))
+    }
+
+    fn new(name: &str, module: Arc<ShaderModule>, kind: ShaderKind) -> Self {
+        Self {
+            name: name.to_string(),
+            module,
+            kind,
+            input: InputKind::StorageBuffer,
+            separate_read_buffer: vec![true],
+            memory_suffix: "_cpu_visible_memory".to_string(),
+            constraints: vec![],
+        }
+    }
+
+    pub fn input(mut self, input: InputKind) -> Self {
+        self.input = input;
+        self
+    }
+
+    /// Which read back methods to run this shader with, see
+    /// [ExecuteUtil::run]
+    pub fn separate_read_buffer(mut self, values: &[bool]) -> Self {
+        self.separate_read_buffer = values.to_vec();
+        self
+    }
+
+    /// Keeps the id of benchmarks that named cpu visible memory differently
+    pub fn memory_suffix(mut self, suffix: &str) -> Self {
+        self.memory_suffix = suffix.to_string();
+        self
+    }
+
+    /// Only run this shader for cases that satisfy `constraint`
+    pub fn constraint<F>(mut self, constraint: F) -> Self
+    where
+        F: 'static + Fn(&BenchCase) -> bool,
+    {
+        self.constraints.push(Rc::new(constraint));
+        self
+    }
+
+    fn vectorization_factor(&self) -> u32 {
+        match &self.kind {
+            ShaderKind::Graphics(p) => p.vectorization_factor,
+            ShaderKind::Compute(p) => p.vectorization_factor,
+        }
+    }
+
+    /// Attachment outputs keep the format and attachment count the shader was
+    /// declared with
+    fn resolve_output(&self, output: OutputKind) -> OutputKind {
+        match (&self.kind, output) {
+            (ShaderKind::Graphics(p), output)
+                if output.is_attachment() && p.output.is_attachment() =>
+            {
+                p.output
+            },
+            _ => output,
+        }
+    }
+
+    fn is_graphics(&self) -> bool {
+        matches!(self.kind, ShaderKind::Graphics(_))
+    }
+}
+
+/// One point of the cartesian product of all axes
+pub struct BenchCase<'l> {
+    pub shader: &'l BenchShader,
+    /// The profiling size the case was created for
+    pub size: u32,
+    pub data_size: Vector2<u32>,
+    pub quad_method: QuadMethod,
+    pub framebuffer_y: u32,
+    pub output: OutputKind,
+    pub vectorization_factor: u32,
+    /// Always 64 for graphics shaders
+    pub workgroup_size: u32,
+    pub separate_read_buffer: bool,
+    pub data: DataGenerator,
+}
+
+impl<'l> BenchCase<'l> {
+    pub fn total(&self) -> u32 {
+        self.data_size.x * self.data_size.y
+    }
+
+    /// The benchmark id, following the naming scheme of the hand written
+    /// benchmarks
+    pub fn id(&self) -> String {
+        let memory = if self.separate_read_buffer {
+            ""
+        } else {
+            &self.shader.memory_suffix
+        };
+
+        match &self.shader.kind {
+            ShaderKind::Graphics(_) => format!(
+                "{}{memory}_{:?}_{}x{}",
+                self.shader.name,
+                self.quad_method,
+                self.data_size.x / self.framebuffer_y,
+                self.framebuffer_y,
+            ),
+            ShaderKind::Compute(p) => format!(
+                "{}{memory}{}{}",
+                self.shader.name,
+                p.override_thread_count
+                    .map(|threads| format!("_{threads}_threads"))
+                    .unwrap_or_default(),
+                Some(self.workgroup_size)
+                    .filter(|size| *size != ComputeParameters::default().workgroup_size)
+                    .map(|size| format!("_group_{size}"))
+                    .unwrap_or_default(),
+            ),
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        if self.vectorization_factor != self.shader.vectorization_factor()
+            || self.data_size.y % self.vectorization_factor != 0
+        {
+            return false;
+        }
+
+        if let ShaderKind::Graphics(p) = &self.shader.kind {
+            if discriminant(&p.output) != discriminant(&self.output)
+                || self.data_size.x % (self.framebuffer_y * self.output.partials_per_fragment())
+                    != 0
+            {
+                return false;
+            }
+        }
+
+        self.shader.constraints.iter().all(|c| c(self))
+    }
+
+    pub fn run_parameters<Type>(&self) -> RunParameters {
+        match self.parameters() {
+            ShaderKind::Graphics(p) => RunParameters::graphics::<Type>(
+                &self.shader.name,
+                self.data_size,
+                &p,
+                self.separate_read_buffer,
+            ),
+            ShaderKind::Compute(p) => RunParameters::compute::<Type>(
+                &self.shader.name,
+                self.data_size,
+                &p,
+                self.separate_read_buffer,
+            ),
+        }
+    }
+
+    fn parameters(&self) -> ShaderKind {
+        match &self.shader.kind {
+            ShaderKind::Graphics(p) => ShaderKind::Graphics(ExecuteParameters {
+                vectorization_factor: self.vectorization_factor,
+                framebuffer_y: self.framebuffer_y,
+                output: self.output,
+                quad_method: self.quad_method,
+                data: self.data,
+                ..p.clone()
+            }),
+            ShaderKind::Compute(p) => ShaderKind::Compute(ComputeParameters {
+                vectorization_factor: self.vectorization_factor,
+                workgroup_size: self.workgroup_size,
+                data: self.data,
+                ..p.clone()
+            }),
+        }
+    }
+}
+
+/// Declarative description of a set of benchmarks.
+///
+/// Every shader is run for every combination of the axes that is valid for
+/// it. Graphics only axes (quad method, framebuffer shape, output kind) are
+/// ignored for compute shaders, the workgroup sizes for graphics shaders.
+pub struct BenchMatrix {
+    name: String,
+    sizes: Vec<u32>,
+    quad_methods: Vec<QuadMethod>,
+    framebuffer_ys: Vec<u32>,
+    outputs: Vec<OutputKind>,
+    vectorization_factors: Vec<u32>,
+    workgroup_sizes: Vec<u32>,
+    data: DataGenerator,
+    shaders: Vec<BenchShader>,
+    /// Recorded in the device report of the results, see
+    /// [BenchMatrix::subgroup_shader]
+    subgroup_fallbacks: Vec<String>,
+    /// Accumulates the generated data in f64, see [BenchMatrix::reference]
+    reference: Option<Reference>,
+
+    constraints: Vec<Constraint>,
+}
+
+impl BenchMatrix {
+    /// `name` should be the name of the criterion group the matrix is run in
+    pub fn new(name: &str, vulkan: &VulkanData) -> Self {
+        Self {
+            name: name.to_string(),
+            sizes: vulkan.profiling_sizes(),
+            quad_methods: vec![QuadMethod::default()],
+            framebuffer_ys: vec![1],
+            outputs: vec![OutputKind::Buffer],
+            vectorization_factors: vec![1],
+            workgroup_sizes: vec![ComputeParameters::default().workgroup_size],
+            data: DataGenerator::default(),
+            shaders: vec![],
+            subgroup_fallbacks: vec![],
+            reference: None,
+            constraints: vec![],
+        }
+    }
+
+    pub fn sizes<I>(mut self, sizes: I) -> Self
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        self.sizes = sizes.into_iter().collect_vec();
+        self
+    }
+
+    pub fn quad_methods<I>(mut self, quad_methods: I) -> Self
+    where
+        I: IntoIterator<Item = QuadMethod>,
+    {
+        self.quad_methods = quad_methods.into_iter().collect_vec();
+        self
+    }
+
+    pub fn framebuffer_ys<I>(mut self, framebuffer_ys: I) -> Self
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        self.framebuffer_ys = framebuffer_ys.into_iter().collect_vec();
+        self
+    }
+
+    pub fn outputs<I>(mut self, outputs: I) -> Self
+    where
+        I: IntoIterator<Item = OutputKind>,
+    {
+        self.outputs = outputs.into_iter().collect_vec();
+        self
+    }
+
+    pub fn vectorization_factors<I>(mut self, vectorization_factors: I) -> Self
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        self.vectorization_factors = vectorization_factors.into_iter().collect_vec();
+        self
+    }
+
+    /// Local sizes to run compute shaders with, sizes the device does not
+    /// support are skipped
+    pub fn workgroup_sizes<I>(mut self, workgroup_sizes: I) -> Self
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        self.workgroup_sizes = workgroup_sizes.into_iter().collect_vec();
+        self
+    }
+
+    /// Input of every case, replaces the one of the shader parameters
+    pub fn data(mut self, data: DataGenerator) -> Self {
+        self.data = data;
+        self
+    }
+
+    pub fn shader(mut self, shader: BenchShader) -> Self {
+        self.shaders.push(shader);
+        self
+    }
+
+    /// Adds the shader named `name` if the device supports the subgroup
+    /// `operations` it uses in `stage`, otherwise `fallback`, e.g. a shared
+    /// memory variant, if there is one. Loading a shader with unsupported
+    /// operations already fails, so both are only created when chosen.
+    pub fn subgroup_shader<S, F>(
+        mut self,
+        vulkan: &mut VulkanData,
+        name: &str,
+        stage: ShaderStages,
+        operations: SubgroupFeatures,
+        shader: S,
+        fallback: F,
+    ) -> Self
+    where
+        S: FnOnce(&mut VulkanData, &str) -> BenchShader,
+        F: FnOnce(&mut VulkanData, &str) -> Option<BenchShader>,
+    {
+        if vulkan.subgroup_support().supports(stage, operations) {
+            self.shaders.push(shader(vulkan, name));
+            return self;
+        }
+
+        let fallback = fallback(vulkan, name);
+        let decision = format!(
+            "{name}: no {operations:?} in {stage:?}, {}",
+            match &fallback {
+                Some(fallback) => format!("using {}", fallback.name),
+                None => "skipped".to_string(),
+            }
+        );
+        println!("{decision}");
+
+        self.subgroup_fallbacks.push(decision);
+        self.shaders.extend(fallback);
+        self
+    }
+
+    /// Report the error of every case against the generated data accumulated
+    /// in f64 with `accumulate`, and whether its results are reproducible. The
+    /// inputs are widened to f64 after being generated as the element type, so
+    /// only the error of the accumulation is reported
+    pub fn reference<F>(mut self, accumulate: F) -> Self
+    where
+        F: 'static + Fn(f64, f64) -> f64,
+    {
+        self.reference = Some(Rc::new(accumulate));
+        self
+    }
+
+    /// Only run cases that satisfy `constraint`
+    pub fn constraint<F>(mut self, constraint: F) -> Self
+    where
+        F: 'static + Fn(&BenchCase) -> bool,
+    {
+        self.constraints.push(Rc::new(constraint));
+        self
+    }
+
+    /// All valid cases for a single data size, without duplicates
+    pub fn cases(&self, vulkan: &VulkanData, size: u32) -> Vec<BenchCase> {
+        let data_size = Vector2::new(vulkan.gpu_thread_count(), size / vulkan.gpu_thread_count());
+
+        let graphics_only = |shader: &BenchShader| {
+            if shader.is_graphics() {
+                (
+                    self.quad_methods.clone(),
+                    self.framebuffer_ys.clone(),
+                    self.outputs.clone(),
+                )
+            } else {
+                (
+                    vec![QuadMethod::default()],
+                    vec![1],
+                    vec![OutputKind::Buffer],
+                )
+            }
+        };
+        let compute_only = |shader: &BenchShader| {
+            if shader.is_graphics() {
+                vec![ComputeParameters::default().workgroup_size]
+            } else {
+                self.workgroup_sizes
+                    .iter()
+                    .copied()
+                    .filter(|size| *size <= vulkan.max_workgroup_size())
+                    .collect_vec()
+            }
+        };
+
+        let mut ids = HashSet::new();
+        let mut cases = vec![];
+
+        for shader in self.shaders.iter() {
+            let (quad_methods, framebuffer_ys, outputs) = graphics_only(shader);
+            let workgroup_sizes = compute_only(shader);
+
+            for quad_method in quad_methods.iter().copied() {
+                for framebuffer_y in framebuffer_ys.iter().copied() {
+                    for output in outputs.iter().copied() {
+                        let output = shader.resolve_output(output);
+                        let rest = iproduct!(
+                            self.vectorization_factors.iter().copied(),
+                            workgroup_sizes.iter().copied(),
+                            shader.separate_read_buffer.iter().copied()
+                        );
+                        for (vectorization_factor, workgroup_size, separate_read_buffer) in rest {
+                            let case = BenchCase {
+                                shader,
+                                size,
+                                data_size,
+                                quad_method,
+                                framebuffer_y,
+                                output,
+                                vectorization_factor,
+                                workgroup_size,
+                                separate_read_buffer,
+                                data: self.data,
+                            };
+
+                            if case.is_valid()
+                                && case.shader.input.supports(vulkan, case.data_size)
+                                && case.output.attachment_count()
+                                    <= vulkan.physical_device.properties().max_color_attachments
+                                && case.output.format().map_or(true, |format| {
+                                    vulkan.format_capability(format).color_attachment
+                                })
+                                && self.constraints.iter().all(|c| c(&case))
+                                && ids.insert(case.id())
+                            {
+                                cases.push(case);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        cases
+    }
+
+    /// Runs every valid case through criterion, then records
+    /// [crate::results::RECORDED_RUNS] more runs of it into `results`
+    pub fn run<Type, Acc>(
+        &self,
+        g: &mut BenchmarkGroup<WallTime>,
+        vulkan: &mut VulkanData,
+        results: &mut ResultSet,
+        accumulate: Acc,
+    ) where
+        Type: Copy + NumCast + Pod + BufferContents + PartialEq + Zero + Debug + Sum + CpuReducible,
+        Acc: 'static + Clone + Fn(Type, Type) -> Type,
+    {
+        for decision in self.subgroup_fallbacks.iter() {
+            if !results.device.subgroup_fallbacks.contains(decision) {
+                results.device.subgroup_fallbacks.push(decision.clone());
+            }
+        }
+
+        // By total, the data is the same for every case of a size
+        let mut references = HashMap::new();
+
+        for size in self.sizes.iter().copied() {
+            for case in self.cases(vulkan, size) {
+                g.throughput(throughput::<Type>(case.total()));
+
+                match case.parameters() {
+                    ShaderKind::Graphics(parameters) => {
+                        self.bench_case(g, vulkan, results, &mut references, &case, |vulkan| {
+                            Self::setup_graphics(vulkan, &case, parameters, accumulate.clone())
+                        })
+                    },
+                    ShaderKind::Compute(parameters) => {
+                        self.bench_case(g, vulkan, results, &mut references, &case, |vulkan| {
+                            Self::setup_compute(vulkan, &case, parameters, accumulate.clone())
+                        })
+                    },
+                }
+            }
+        }
+    }
+
+    /// Runs `case` through criterion with the executor `setup` creates, then
+    /// records it into `results`
+    fn bench_case<Type, E>(
+        &self,
+        g: &mut BenchmarkGroup<WallTime>,
+        vulkan: &mut VulkanData,
+        results: &mut ResultSet,
+        references: &mut HashMap<u32, f64>,
+        case: &BenchCase,
+        setup: impl FnOnce(&mut VulkanData) -> E,
+    ) where
+        Type: NumCast + CpuReducible,
+        E: Executor<Type>,
+    {
+        let size = case.size;
+
+        // Criterion calls the routine for every sample, the case is set up only
+        // once
+        let mut setup = Some(setup);
+        let mut execute = None;
+        g.bench_with_input(BenchmarkId::new(case.id(), size), &size, |b, _| {
+            let execute = execute.get_or_insert_with(|| (setup.take().unwrap())(vulkan));
+
+            b.iter(|| execute.run(vulkan, case.separate_read_buffer));
+        });
+
+        // Criterion skips cases its filter doesn't match
+        let Some(mut execute) = execute else {
+            return;
+        };
+
+        let mut recorder = match &self.reference {
+            Some(reference) => {
+                Recorder::with_reference(*references.entry(case.total()).or_insert_with(|| {
+                    self.data
+                        .generate::<Type>(case.total())
+                        .map(|value| <f64 as NumCast>::from(value).unwrap())
+                        .reduce(|a, b| reference(a, b))
+                        .unwrap()
+                }))
+            },
+            None => Recorder::default(),
+        };
+        let runs = recorder.measure_runs(vulkan, |vulkan| {
+            execute.run(vulkan, case.separate_read_buffer)
+        });
+        for result in runs {
+            recorder.result(NumCast::from(result).unwrap());
+        }
+
+        results.record(
+            &self.name,
+            &format!("{}/{size}", case.id()),
+            case.run_parameters::<Type>(),
+            recorder,
+        );
+    }
+
+    fn setup_graphics<Type, Acc>(
+        vulkan: &mut VulkanData,
+        case: &BenchCase,
+        parameters: ExecuteParameters,
+        accumulate: Acc,
+    ) -> ExecuteUtil<Type>
+    where
+        Type: Copy + NumCast + Pod + BufferContents + PartialEq + Zero + Debug + Sum + CpuReducible,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        let data_size = case.data_size;
+        let texture_size = parameters.texture_size(data_size);
+        let sc = PluggableConstants {
+            TEXTURE_SIZE_X: texture_size.x as _,
+            TEXTURE_SIZE_Y: texture_size.y as _,
+        };
+
+        match case.shader.input {
+            InputKind::StorageBuffer => ExecuteUtil::setup_storage_buffer(
+                vulkan,
+                data_size,
+                &case.shader.module,
+                sc,
+                parameters,
+                accumulate,
+            ),
+            InputKind::Sampler2D => ExecuteUtil::setup_2d_sampler(
+                vulkan,
+                data_size,
+                &case.shader.module,
+                sc,
+                parameters,
+                accumulate,
+            ),
+            InputKind::StorageImage1D => ExecuteUtil::setup_storage_image_1d(
+                vulkan,
+                data_size,
+                &case.shader.module,
+                sc,
+                parameters,
+                accumulate,
+            ),
+            InputKind::StorageImage2D => ExecuteUtil::setup_storage_image_2d(
+                vulkan,
+                data_size,
+                &case.shader.module,
+                sc,
+                parameters,
+                accumulate,
+            ),
+            InputKind::UniformTexelBuffer => ExecuteUtil::setup_uniform_texel_buffer(
+                vulkan,
+                data_size,
+                &case.shader.module,
+                sc,
+                parameters,
+                accumulate,
+            ),
+            InputKind::StorageTexelBuffer => ExecuteUtil::setup_storage_texel_buffer(
+                vulkan,
+                data_size,
+                &case.shader.module,
+                sc,
+                parameters,
+                accumulate,
+            ),
+            InputKind::UniformBuffer => ExecuteUtil::setup_uniform_buffer(
+                vulkan,
+                data_size,
+                &case.shader.module,
+                sc,
+                parameters,
+                accumulate,
+            ),
+        }
+    }
+
+    fn setup_compute<Type, Acc>(
+        vulkan: &mut VulkanData,
+        case: &BenchCase,
+        parameters: ComputeParameters,
+        accumulate: Acc,
+    ) -> ComputeExecuteUtil<Type>
+    where
+        Type: Copy + NumCast + Pod + BufferContents + PartialEq + Zero + Debug + Sum + CpuReducible,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        let data_size = case.data_size;
+        let sc = PluggableConstants {
+            TEXTURE_SIZE_X: data_size.x as _,
+            TEXTURE_SIZE_Y: 1,
+        };
+
+        match case.shader.input {
+            InputKind::StorageBuffer => ComputeExecuteUtil::setup_storage_buffer(
+                vulkan,
+                data_size,
+                &case.shader.module,
+                sc,
+                parameters,
+                accumulate,
+            ),
+            InputKind::StorageImage1D => ComputeExecuteUtil::setup_storage_image_1d(
+                vulkan,
+                data_size,
+                &case.shader.module,
+                sc,
+                parameters,
+                accumulate,
+            ),
+            InputKind::StorageImage2D => ComputeExecuteUtil::setup_storage_image_2d(
+                vulkan,
+                data_size,
+                &case.shader.module,
+                sc,
+                parameters,
+                accumulate,
+            ),
+            InputKind::UniformTexelBuffer => ComputeExecuteUtil::setup_uniform_texel_buffer(
+                vulkan,
+                data_size,
+                &case.shader.module,
+                sc,
+                parameters,
+                accumulate,
+            ),
+            InputKind::StorageTexelBuffer => ComputeExecuteUtil::setup_storage_texel_buffer(
+                vulkan,
+                data_size,
+                &case.shader.module,
+                sc,
+                parameters,
+                accumulate,
+            ),
+            InputKind::UniformBuffer => ComputeExecuteUtil::setup_uniform_buffer(
+                vulkan,
+                data_size,
+                &case.shader.module,
+                sc,
+                parameters,
+                accumulate,
+            ),
+            InputKind::Sampler2D => panic!("Compute shaders do not sample images"),
+        }
+    }
+}
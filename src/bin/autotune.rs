@@ -0,0 +1,61 @@
+use clap::Parser;
+use gpu_compute::{
+    auto_reducer::{AutoReducible, Strategy},
+    cpu::ReduceOp,
+    tuner::AutoTuner,
+    vulkan_util::VulkanData,
+};
+
+/// Searches the fastest launch configuration of every GPU reduction and
+/// stores it for this device, where [gpu_compute::auto_reducer::AutoReducer]
+/// picks it up
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Data sizes to tune for, defaults to the profiling sizes up to 2^26
+    #[arg(short, long)]
+    pub sizes: Vec<u32>,
+
+    #[arg(short, long, default_value_t = 5)]
+    pub iterations: usize,
+}
+
+fn tune<T: AutoReducible>(
+    tuner: &mut AutoTuner,
+    vulkan: &mut VulkanData,
+    op: ReduceOp,
+    sizes: &[u32],
+) {
+    for size in sizes.iter().copied() {
+        for strategy in [Strategy::Graphics, Strategy::Compute] {
+            let entry = tuner.tune::<T>(vulkan, op, strategy, size);
+            println!(
+                "{} {} {}: {:?} in {:.0} ns",
+                entry.operation, entry.backend, size, entry.config, entry.median_ns
+            );
+        }
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    let mut vulkan = VulkanData::init();
+
+    let sizes = if args.sizes.is_empty() {
+        vulkan
+            .profiling_sizes()
+            .into_iter()
+            .filter(|size| *size <= 1 << 26)
+            .collect()
+    } else {
+        args.sizes.clone()
+    };
+
+    let mut tuner = AutoTuner::load(&vulkan);
+    tuner.space.iterations = args.iterations;
+
+    tune::<u32>(&mut tuner, &mut vulkan, ReduceOp::Sum, &sizes);
+    tune::<f32>(&mut tuner, &mut vulkan, ReduceOp::Min, &sizes);
+
+    tuner.save().unwrap();
+}
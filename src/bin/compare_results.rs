@@ -0,0 +1,343 @@
+use clap::{Parser, ValueEnum};
+use gpu_compute::results::{BenchResult, ResultSet, RunParameters, Timing};
+use itertools::Itertools;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    path::PathBuf,
+};
+
+/// Compares two result sets exported by the benches and reports regressions
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// JSON result set to compare against
+    pub baseline: PathBuf,
+    /// JSON result set of the new run
+    pub candidate: PathBuf,
+
+    /// Change of the mean in percent a significant difference must exceed to
+    /// count, the same statistic the t-test compares
+    #[arg(short, long, default_value_t = 5.0)]
+    pub threshold: f64,
+
+    #[arg(short, long, value_enum, default_value_t = Metric::Wall)]
+    pub metric: Metric,
+
+    #[arg(long)]
+    pub markdown: Option<PathBuf>,
+    #[arg(long)]
+    pub json: Option<PathBuf>,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum, Serialize)]
+enum Metric {
+    Wall,
+    Gpu,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+enum Verdict {
+    Regression,
+    Improvement,
+    Unchanged,
+}
+
+#[derive(Debug, Serialize)]
+struct Change {
+    group: String,
+    id: String,
+    parameters: RunParameters,
+    baseline_ns: f64,
+    candidate_ns: f64,
+    change_percent: f64,
+    t_statistic: f64,
+    verdict: Verdict,
+}
+
+#[derive(Debug, Serialize)]
+struct Comparison {
+    baseline_device: String,
+    candidate_device: String,
+    metric: Metric,
+    threshold_percent: f64,
+    changes: Vec<Change>,
+    /// Benchmarks only present in the baseline
+    missing: Vec<String>,
+    /// Benchmarks only present in the candidate
+    added: Vec<String>,
+    /// Benchmarks recorded more than once in either set, they aren't compared
+    duplicates: Vec<String>,
+}
+
+/// Two sided 95% critical values of Student's t for 1 to 30 degrees of freedom
+const T_TABLE: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+/// Two sided 95% critical value of Student's t. Fractional degrees of freedom
+/// are rounded down, past the table the Cornish-Fisher expansion is used.
+fn significant_t(degrees_of_freedom: f64) -> f64 {
+    const Z: f64 = 1.959964;
+
+    let df = degrees_of_freedom.floor().max(1.0);
+    if df <= T_TABLE.len() as f64 {
+        T_TABLE[df as usize - 1]
+    } else {
+        Z + (Z.powi(3) + Z) / (4.0 * df)
+            + (5.0 * Z.powi(5) + 16.0 * Z.powi(3) + 3.0 * Z) / (96.0 * df.powi(2))
+    }
+}
+
+/// Benchmarks are matched by their structured parameters instead of their ids
+fn key(result: &BenchResult) -> String {
+    format!(
+        "{}/{}",
+        result.group,
+        serde_json::to_string(&result.parameters).unwrap()
+    )
+}
+
+fn timing(result: &BenchResult, metric: Metric) -> Option<Timing> {
+    match metric {
+        Metric::Wall => Some(result.wall_ns),
+        Metric::Gpu => result.gpu_ns,
+    }
+}
+
+/// Welch's t-test on the summary statistics of both runs, the t statistic and
+/// its Welch-Satterthwaite degrees of freedom
+fn welch_test(
+    baseline: &Timing,
+    baseline_n: usize,
+    candidate: &Timing,
+    candidate_n: usize,
+) -> (f64, f64) {
+    let baseline_n = baseline_n.max(2) as f64;
+    let candidate_n = candidate_n.max(2) as f64;
+    let baseline_variance = baseline.std_dev.powi(2) / baseline_n;
+    let candidate_variance = candidate.std_dev.powi(2) / candidate_n;
+    let variance = baseline_variance + candidate_variance;
+
+    if variance == 0.0 {
+        let t = if candidate.mean == baseline.mean {
+            0.0
+        } else {
+            f64::INFINITY.copysign(candidate.mean - baseline.mean)
+        };
+        return (t, f64::INFINITY);
+    }
+
+    let degrees_of_freedom = variance.powi(2)
+        / (baseline_variance.powi(2) / (baseline_n - 1.0)
+            + candidate_variance.powi(2) / (candidate_n - 1.0));
+
+    (
+        (candidate.mean - baseline.mean) / variance.sqrt(),
+        degrees_of_freedom,
+    )
+}
+
+/// Results by [key], and the keys recorded more than once
+fn index(set: &ResultSet) -> (HashMap<String, &BenchResult>, HashSet<String>) {
+    let mut results = HashMap::new();
+    let mut duplicates = HashSet::new();
+
+    for result in set.results.iter() {
+        if results.insert(key(result), result).is_some() {
+            duplicates.insert(key(result));
+        }
+    }
+
+    (results, duplicates)
+}
+
+fn compare(baseline: &ResultSet, candidate: &ResultSet, args: &Args) -> Comparison {
+    let (candidates, candidate_duplicates) = index(candidate);
+    let (baselines, baseline_duplicates) = index(baseline);
+    let duplicate_keys = &baseline_duplicates | &candidate_duplicates;
+
+    let mut changes = vec![];
+    let mut missing = vec![];
+
+    for base in baseline.results.iter() {
+        if duplicate_keys.contains(&key(base)) {
+            continue;
+        }
+        let Some(cand) = candidates.get(&key(base)) else {
+            missing.push(format!("{}/{}", base.group, base.id));
+            continue;
+        };
+        let (Some(base_time), Some(cand_time)) =
+            (timing(base, args.metric), timing(cand, args.metric))
+        else {
+            continue;
+        };
+
+        let change_percent = (cand_time.mean - base_time.mean) / base_time.mean * 100.0;
+        let (t, degrees_of_freedom) =
+            welch_test(&base_time, base.samples, &cand_time, cand.samples);
+
+        let verdict = if t.abs() < significant_t(degrees_of_freedom)
+            || change_percent.abs() <= args.threshold
+        {
+            Verdict::Unchanged
+        } else if change_percent > 0.0 {
+            Verdict::Regression
+        } else {
+            Verdict::Improvement
+        };
+
+        changes.push(Change {
+            group: base.group.clone(),
+            id: base.id.clone(),
+            parameters: base.parameters.clone(),
+            baseline_ns: base_time.mean,
+            candidate_ns: cand_time.mean,
+            change_percent,
+            t_statistic: t,
+            verdict,
+        });
+    }
+
+    let added = candidate
+        .results
+        .iter()
+        .filter(|r| !baselines.contains_key(&key(r)))
+        .map(|r| format!("{}/{}", r.group, r.id))
+        .collect_vec();
+
+    let duplicates = baseline
+        .results
+        .iter()
+        .chain(candidate.results.iter())
+        .filter(|r| duplicate_keys.contains(&key(r)))
+        .map(|r| format!("{}/{}", r.group, r.id))
+        .unique()
+        .collect_vec();
+
+    let describe = |set: &ResultSet| {
+        format!(
+            "{} (driver {:x})",
+            set.device.name, set.device.driver_version
+        )
+    };
+
+    Comparison {
+        baseline_device: describe(baseline),
+        candidate_device: describe(candidate),
+        metric: args.metric,
+        threshold_percent: args.threshold,
+        changes,
+        missing,
+        added,
+        duplicates,
+    }
+}
+
+fn markdown(comparison: &Comparison) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "# Benchmark comparison\n").unwrap();
+    writeln!(out, "- Baseline: {}", comparison.baseline_device).unwrap();
+    writeln!(out, "- Candidate: {}", comparison.candidate_device).unwrap();
+    writeln!(
+        out,
+        "- Metric: {:?} time, threshold {}%\n",
+        comparison.metric, comparison.threshold_percent
+    )
+    .unwrap();
+
+    for (title, verdict) in [
+        ("Regressions", Verdict::Regression),
+        ("Improvements", Verdict::Improvement),
+    ] {
+        let changes = comparison
+            .changes
+            .iter()
+            .filter(|c| c.verdict == verdict)
+            .sorted_by(|a, b| b.change_percent.abs().total_cmp(&a.change_percent.abs()))
+            .collect_vec();
+
+        writeln!(out, "## {title} ({})\n", changes.len()).unwrap();
+        if changes.is_empty() {
+            continue;
+        }
+
+        writeln!(out, "| Benchmark | Baseline | Candidate | Change | t |").unwrap();
+        writeln!(out, "|---|---:|---:|---:|---:|").unwrap();
+        for c in changes {
+            writeln!(
+                out,
+                "| {}/{} | {:.0} ns | {:.0} ns | {:+.2}% | {:.1} |",
+                c.group, c.id, c.baseline_ns, c.candidate_ns, c.change_percent, c.t_statistic
+            )
+            .unwrap();
+        }
+        writeln!(out).unwrap();
+    }
+
+    writeln!(
+        out,
+        "{} unchanged, {} only in baseline, {} only in candidate",
+        comparison
+            .changes
+            .iter()
+            .filter(|c| c.verdict == Verdict::Unchanged)
+            .count(),
+        comparison.missing.len(),
+        comparison.added.len(),
+    )
+    .unwrap();
+
+    if !comparison.duplicates.is_empty() {
+        writeln!(
+            out,
+            "\n## Duplicates ({})\n\nRecorded more than once and not compared:\n",
+            comparison.duplicates.len()
+        )
+        .unwrap();
+        for duplicate in comparison.duplicates.iter() {
+            writeln!(out, "- {duplicate}").unwrap();
+        }
+    }
+
+    out
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let baseline = ResultSet::read_json(&args.baseline).unwrap();
+    let candidate = ResultSet::read_json(&args.candidate).unwrap();
+
+    if baseline.device.name != candidate.device.name {
+        println!(
+            "Warning: comparing different devices {:?} and {:?}",
+            baseline.device.name, candidate.device.name
+        );
+    }
+
+    let comparison = compare(&baseline, &candidate, &args);
+
+    let report = markdown(&comparison);
+    println!("{report}");
+
+    if let Some(path) = &args.markdown {
+        std::fs::write(path, &report).unwrap();
+    }
+    if let Some(path) = &args.json {
+        std::fs::write(path, serde_json::to_string_pretty(&comparison).unwrap()).unwrap();
+    }
+
+    if comparison
+        .changes
+        .iter()
+        .any(|c| c.verdict == Verdict::Regression)
+    {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,62 @@
+use clap::Parser;
+use gpu_compute::{dispatch::DispatchTable, results::ResultSet};
+use itertools::Itertools;
+use std::path::PathBuf;
+
+/// Finds the data sizes at which GPU strategies start beating the CPU and
+/// writes the best strategy per size bucket as dispatch table
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// JSON result sets of the CPU and GPU benches, e.g. target/results/*.json
+    #[arg(required = true)]
+    pub inputs: Vec<PathBuf>,
+
+    #[arg(short, long, default_value = "dispatch_table.json")]
+    pub output: PathBuf,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let sets = args
+        .inputs
+        .iter()
+        .map(|path| ResultSet::read_json(path).unwrap())
+        .collect_vec();
+
+    let tables = DispatchTable::analyze(&sets);
+
+    for table in tables.iter() {
+        println!(
+            "# {} of {} on {}\n",
+            table.operation, table.element_type, table.device
+        );
+
+        println!("| GPU strategy | Crossover |");
+        println!("|---|---:|");
+        for crossover in table.crossovers.iter() {
+            println!(
+                "| {} | {} |",
+                crossover.strategy,
+                crossover
+                    .elements
+                    .map(|e| e.to_string())
+                    .unwrap_or_else(|| "never".to_string()),
+            );
+        }
+
+        println!("\n| Up to elements | Backend | Strategy | Median |");
+        println!("|---:|---|---|---:|");
+        for bucket in table.buckets.iter() {
+            println!(
+                "| {} | {} | {} | {:.0} ns |",
+                bucket.max_elements, bucket.backend, bucket.strategy, bucket.median_ns
+            );
+        }
+        println!();
+    }
+
+    DispatchTable::write_json(&tables, &args.output).unwrap();
+    println!("Dispatch table written to {:?}", args.output);
+}
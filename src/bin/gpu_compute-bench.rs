@@ -0,0 +1,853 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use gpu_compute::{
+    auto_reducer::{AutoReducible, GpuExecutor},
+    bench_matrix::ShaderKind,
+    cpu::ReduceOp,
+    data_source::{DataSet, Element},
+    execute_util::{
+        BlendMethod, DataDistribution, DataGenerator, ExecuteParameters, OutputKind, QuadMethod,
+    },
+    execute_util_compute::{ComputeParameters, OutputModification, SubgroupSizeRequest},
+    executor::grid_layout,
+    results::{BenchResult, Recorder, ResultSet, RunParameters},
+    shader_registry::{shaders, RegisteredShader},
+    vulkan_util::VulkanData,
+};
+use itertools::Itertools;
+use num::NumCast;
+use std::{fmt::Write, path::PathBuf};
+use vulkano::format::{ClearValue, Format};
+
+/// Runs the reduction shaders of the benches with any parameters, for
+/// experiments that don't warrant new bench code
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Index of the device in `list-devices`, picked like the benches do
+    /// otherwise
+    #[arg(short, long, global = true)]
+    pub device: Option<usize>,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Lists the Vulkan devices `--device` picks from
+    ListDevices,
+    /// Lists the shaders `--shader` accepts
+    ListShaders,
+    /// Times one shader with one set of parameters
+    Run(RunArgs),
+    /// Times one shader over data sizes, workgroup sizes and framebuffer
+    /// heights
+    Sweep(SweepArgs),
+    /// Checks shaders against the reduction on the CPU
+    Verify(VerifyArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct RunArgs {
+    #[arg(short, long)]
+    pub shader: String,
+    #[command(flatten)]
+    pub selection: Selection,
+
+    /// Number of generated values
+    #[arg(short = 'n', long, default_value_t = 1 << 20, conflicts_with = "input")]
+    pub size: u32,
+    #[command(flatten)]
+    pub data: DataArgs,
+
+    #[command(flatten)]
+    pub knobs: Knobs,
+    #[command(flatten)]
+    pub timing: TimingArgs,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct SweepArgs {
+    #[arg(short, long)]
+    pub shader: String,
+    #[command(flatten)]
+    pub selection: Selection,
+
+    /// Numbers of generated values, defaults to the profiling sizes
+    #[arg(long, value_delimiter = ',', conflicts_with = "input")]
+    pub sizes: Vec<u32>,
+    /// Only for compute shaders, defaults to the shader's
+    #[arg(long, value_delimiter = ',')]
+    pub workgroup_sizes: Vec<u32>,
+    /// Only for graphics shaders, defaults to the shader's
+    #[arg(long, value_delimiter = ',')]
+    pub framebuffer_ys: Vec<u32>,
+    #[command(flatten)]
+    pub data: DataArgs,
+
+    /// Applied to every run, the swept values take precedence
+    #[command(flatten)]
+    pub knobs: Knobs,
+    #[command(flatten)]
+    pub timing: TimingArgs,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct VerifyArgs {
+    /// Every shader of the operation and type if not given
+    #[arg(short, long)]
+    pub shader: Option<String>,
+    #[command(flatten)]
+    pub selection: Selection,
+
+    /// Numbers of generated values, uneven ones exercise the end of the grid
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_values_t = [1, 1000, 65537, 1 << 20],
+        conflicts_with = "input"
+    )]
+    pub sizes: Vec<u32>,
+    #[command(flatten)]
+    pub data: DataArgs,
+
+    /// Largest relative error against the sum in f64 float sums may have,
+    /// every other result has to match the CPU exactly
+    #[arg(long, default_value_t = 1e-3)]
+    pub tolerance: f64,
+
+    #[command(flatten)]
+    pub knobs: Knobs,
+}
+
+#[derive(clap::Args, Debug)]
+struct Selection {
+    #[arg(long, value_enum, default_value_t = Op::Sum)]
+    pub op: Op,
+    #[arg(long = "type", value_enum, default_value_t = ElementType::U32)]
+    pub element_type: ElementType,
+}
+
+#[derive(clap::Args, Debug)]
+struct DataArgs {
+    /// Distribution of generated values, e.g. `uniform:0:10`, `normal:0:1`,
+    /// `ascending`, `constant:3`, `identity:min`, `sparse:0.01` or
+    /// `cancellation`
+    #[arg(long, default_value_t = DataDistribution::default(), conflicts_with = "input")]
+    pub distribution: DataDistribution,
+    #[arg(long, default_value_t = DataGenerator::default().seed, conflicts_with = "input")]
+    pub seed: u64,
+
+    /// Raw little endian, `.npy` or single column `.csv` file of the element
+    /// type to reduce instead of generated data
+    #[arg(long)]
+    pub input: Option<PathBuf>,
+
+    /// Parallel invocations the data is laid out for, defaults to the GPU
+    /// thread count
+    #[arg(long)]
+    pub threads: Option<u32>,
+}
+
+/// Overrides of the parameters a shader is registered with
+#[derive(clap::Args, Debug, Clone)]
+struct Knobs {
+    /// Has to match what the shader loads at once
+    #[arg(long)]
+    pub vectorization_factor: Option<u32>,
+    #[arg(long)]
+    pub separate_read_buffer: Option<bool>,
+    /// Reduce the partial results with the reducers of `cpu` instead of
+    /// folding them
+    #[arg(long, value_enum)]
+    pub final_accumulation: Option<Op>,
+
+    /// Graphics only
+    #[arg(long)]
+    pub framebuffer_y: Option<u32>,
+    /// Graphics only: `two_triangles`, `large_triangle` or `fill_rectangle`
+    #[arg(long, value_parser = parse_quad_method)]
+    pub quad_method: Option<QuadMethod>,
+    /// Graphics only: `buffer`, `blend`, `attachment:<format>` or
+    /// `packed:<format>:<attachments>`, formats as in vulkano, e.g.
+    /// `R32G32B32A32_UINT`
+    #[arg(long, value_parser = parse_output_kind)]
+    pub output_kind: Option<OutputKind>,
+    /// Graphics only: `uint:<value>` or `float:<value>`
+    #[arg(long, value_parser = parse_clear_value)]
+    pub clear_value: Option<ClearValue>,
+    /// Graphics only: `add`, `min`, `max`, `and`, `or` or `xor`
+    #[arg(long, value_parser = parse_blend_method)]
+    pub blend: Option<BlendMethod>,
+    /// Graphics only
+    #[arg(long)]
+    pub use_instances_and_blend: Option<bool>,
+
+    /// Compute only: `one-for-one`, `single-value`, `one-per-subgroup`,
+    /// `one-per-workgroup` or `fixed:<values>`
+    #[arg(long, value_parser = parse_output_modification)]
+    pub output_modification: Option<OutputModification>,
+    /// Compute only
+    #[arg(long)]
+    pub clear_buffer: Option<bool>,
+    /// Compute only
+    #[arg(long)]
+    pub skip_cpu_final_accumulation: Option<bool>,
+    /// Compute only
+    #[arg(long)]
+    pub override_thread_count: Option<u32>,
+    /// Compute only
+    #[arg(long)]
+    pub workgroup_size: Option<u32>,
+    /// Compute only: `any`, `full` or the required size
+    #[arg(long, value_parser = parse_subgroup_size)]
+    pub subgroup_size: Option<SubgroupSizeRequest>,
+}
+
+#[derive(clap::Args, Debug)]
+struct TimingArgs {
+    #[arg(short, long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    pub iterations: u32,
+    /// Runs before timing starts, the first one builds the pipeline
+    #[arg(long, default_value_t = 1)]
+    pub warmup: u32,
+}
+
+#[derive(clap::Args, Debug)]
+struct OutputArgs {
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+    /// Where to write the results, the text is always printed as well
+    #[arg(short, long, required_if_eq_any([("format", "json"), ("format", "csv")]))]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+enum Op {
+    Sum,
+    Min,
+}
+
+impl From<Op> for ReduceOp {
+    fn from(op: Op) -> Self {
+        match op {
+            Op::Sum => ReduceOp::Sum,
+            Op::Min => ReduceOp::Min,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+enum ElementType {
+    U32,
+    F32,
+}
+
+impl ElementType {
+    fn name(self) -> &'static str {
+        match self {
+            ElementType::U32 => "u32",
+            ElementType::F32 => "f32",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+fn parse_quad_method(s: &str) -> Result<QuadMethod, String> {
+    match s {
+        "two_triangles" => Ok(QuadMethod::two_triangles),
+        "large_triangle" => Ok(QuadMethod::large_triangle),
+        #[cfg(feature = "fill_rectangle")]
+        "fill_rectangle" => Ok(QuadMethod::fill_rectangle),
+        _ => Err(format!("Unknown quad method {s:?}")),
+    }
+}
+
+fn parse_blend_method(s: &str) -> Result<BlendMethod, String> {
+    [
+        BlendMethod::Add,
+        BlendMethod::Min,
+        BlendMethod::Max,
+        BlendMethod::And,
+        BlendMethod::Or,
+        BlendMethod::Xor,
+    ]
+    .into_iter()
+    .find(|blend| format!("{blend:?}").eq_ignore_ascii_case(s))
+    .ok_or_else(|| format!("Unknown blend method {s:?}"))
+}
+
+/// The formats the attachment writers are instantiated for
+fn parse_format(s: &str) -> Result<Format, String> {
+    [
+        Format::R32_UINT,
+        Format::R32G32_UINT,
+        Format::R32G32B32A32_UINT,
+        Format::R32_SFLOAT,
+        Format::R32G32_SFLOAT,
+        Format::R32G32B32A32_SFLOAT,
+    ]
+    .into_iter()
+    .find(|format| format!("{format:?}").eq_ignore_ascii_case(s))
+    .ok_or_else(|| format!("Unsupported attachment format {s:?}"))
+}
+
+fn parse_output_kind(s: &str) -> Result<OutputKind, String> {
+    let parts = s.split(':').collect_vec();
+    match parts.as_slice() {
+        ["buffer"] => Ok(OutputKind::Buffer),
+        ["blend"] => Ok(OutputKind::BlendAttachment),
+        ["attachment"] => Ok(OutputKind::Attachment),
+        ["attachment", format] => Ok(OutputKind::RenderAttachment(parse_format(format)?)),
+        ["packed", format, attachments] => Ok(OutputKind::PackedAttachments {
+            format: parse_format(format)?,
+            attachments: attachments
+                .parse()
+                .map_err(|_| format!("Invalid attachment count {attachments:?}"))?,
+        }),
+        _ => Err(format!("Unknown output kind {s:?}")),
+    }
+}
+
+fn parse_clear_value(s: &str) -> Result<ClearValue, String> {
+    let invalid = || format!("Invalid clear value {s:?}");
+
+    match s.split_once(':') {
+        Some(("uint", value)) => Ok(ClearValue::Uint([value.parse().map_err(|_| invalid())?; 4])),
+        Some(("float", value)) => Ok(ClearValue::Float(
+            [value.parse().map_err(|_| invalid())?; 4],
+        )),
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_output_modification(s: &str) -> Result<OutputModification, String> {
+    match s.split_once(':') {
+        Some(("fixed", size)) => Ok(OutputModification::FixedSize(
+            size.parse()
+                .map_err(|_| format!("Invalid output size {size:?}"))?,
+        )),
+        None => match s {
+            "one-for-one" => Ok(OutputModification::OneForOne),
+            "single-value" => Ok(OutputModification::SingleValue),
+            "one-per-subgroup" => Ok(OutputModification::OnePerSubgroup),
+            "one-per-workgroup" => Ok(OutputModification::OnePerWorkgroup),
+            _ => Err(format!("Unknown output modification {s:?}")),
+        },
+        _ => Err(format!("Unknown output modification {s:?}")),
+    }
+}
+
+fn parse_subgroup_size(s: &str) -> Result<SubgroupSizeRequest, String> {
+    match s {
+        "any" => Ok(SubgroupSizeRequest::Any),
+        "full" => Ok(SubgroupSizeRequest::Full),
+        size => size
+            .parse()
+            .map(SubgroupSizeRequest::Required)
+            .map_err(|_| format!("Invalid subgroup size {s:?}")),
+    }
+}
+
+impl DataArgs {
+    fn generator(&self) -> DataGenerator {
+        DataGenerator {
+            distribution: self.distribution,
+            seed: self.seed,
+        }
+    }
+
+    /// `size` generated values, or the input file, and what they are called
+    /// in the results
+    fn load<Type: AutoReducible + Element>(&self, size: u32) -> (Vec<Type>, String) {
+        match &self.input {
+            Some(path) => (
+                DataSet::<Type>::read(path).unwrap().values,
+                path.display().to_string(),
+            ),
+            None => {
+                let generator = self.generator();
+                (
+                    generator.generate(size).collect_vec(),
+                    generator.to_string(),
+                )
+            },
+        }
+    }
+}
+
+impl Knobs {
+    fn graphics(
+        &self,
+        mut parameters: ExecuteParameters,
+        data: DataGenerator,
+    ) -> ExecuteParameters {
+        assert!(
+            self.output_modification.is_none()
+                && self.clear_buffer.is_none()
+                && self.skip_cpu_final_accumulation.is_none()
+                && self.override_thread_count.is_none()
+                && self.workgroup_size.is_none()
+                && self.subgroup_size.is_none(),
+            "Compute only parameters passed for a graphics shader"
+        );
+
+        if let Some(value) = self.vectorization_factor {
+            parameters.vectorization_factor = value;
+        }
+        if let Some(value) = self.final_accumulation {
+            parameters.final_accumulation = Some(value.into());
+        }
+        if let Some(value) = self.framebuffer_y {
+            parameters.framebuffer_y = value;
+        }
+        if let Some(value) = self.quad_method {
+            parameters.quad_method = value;
+        }
+        if let Some(value) = self.output_kind {
+            parameters.output = value;
+        }
+        if let Some(value) = self.clear_value {
+            parameters.clear_value = value;
+        }
+        if let Some(value) = self.blend {
+            parameters.blend = Some(value);
+        }
+        if let Some(value) = self.use_instances_and_blend {
+            parameters.use_instances_and_blend = value;
+        }
+        parameters.data = data;
+
+        parameters
+    }
+
+    fn compute(&self, mut parameters: ComputeParameters, data: DataGenerator) -> ComputeParameters {
+        assert!(
+            self.framebuffer_y.is_none()
+                && self.quad_method.is_none()
+                && self.output_kind.is_none()
+                && self.clear_value.is_none()
+                && self.blend.is_none()
+                && self.use_instances_and_blend.is_none(),
+            "Graphics only parameters passed for a compute shader"
+        );
+
+        if let Some(value) = self.vectorization_factor {
+            parameters.vectorization_factor = value;
+        }
+        if let Some(value) = self.final_accumulation {
+            parameters.final_accumulation = Some(value.into());
+        }
+        if let Some(value) = self.output_modification {
+            parameters.output = value;
+        }
+        if let Some(value) = self.clear_buffer {
+            parameters.clear_buffer = value;
+        }
+        if let Some(value) = self.skip_cpu_final_accumulation {
+            parameters.skip_cpu_final_accumulation = value;
+        }
+        if let Some(value) = self.override_thread_count {
+            parameters.override_thread_count = Some(value);
+        }
+        if let Some(value) = self.workgroup_size {
+            parameters.workgroup_size = value;
+        }
+        if let Some(value) = self.subgroup_size {
+            parameters.subgroup_size = value;
+        }
+        parameters.data = data;
+
+        parameters
+    }
+}
+
+impl Selection {
+    fn matches(&self, shader: &RegisteredShader) -> bool {
+        shader.reduces(self.op.into(), self.element_type.name())
+    }
+}
+
+fn find_shader(name: &str, selection: &Selection) -> RegisteredShader {
+    shaders()
+        .into_iter()
+        .find(|shader| shader.name == name && selection.matches(shader))
+        .unwrap_or_else(|| {
+            panic!(
+                "No shader {name:?} for {} {}, see list-shaders",
+                ReduceOp::from(selection.op).name(),
+                selection.element_type.name(),
+            )
+        })
+}
+
+/// Sets up `entry` with the `knobs` applied, also returns how the run is
+/// recorded
+fn prepare<Type: AutoReducible>(
+    vulkan: &mut VulkanData,
+    entry: &RegisteredShader,
+    knobs: &Knobs,
+    (data, data_label): (Vec<Type>, String),
+    data_args: &DataArgs,
+) -> (GpuExecutor<Type>, RunParameters) {
+    assert!(
+        entry.supported(vulkan),
+        "{} needs subgroup operations the device doesn't support",
+        entry.name
+    );
+
+    let separate_read_buffer = knobs.separate_read_buffer.unwrap_or(false);
+    let data_size = grid_layout(
+        data.len(),
+        data_args
+            .threads
+            .unwrap_or_else(|| vulkan.gpu_thread_count()),
+    );
+    let shader = vulkan.load_shader(entry.load);
+
+    let (kind, parameters) = match &entry.kind {
+        ShaderKind::Graphics(parameters) => {
+            let parameters = knobs.graphics(parameters.clone(), data_args.generator());
+            let run_parameters = RunParameters::graphics::<Type>(
+                entry.name,
+                data_size,
+                &parameters,
+                separate_read_buffer,
+            );
+
+            (ShaderKind::Graphics(parameters), run_parameters)
+        },
+        ShaderKind::Compute(parameters) => {
+            let parameters = knobs.compute(parameters.clone(), data_args.generator());
+            let run_parameters = RunParameters::compute::<Type>(
+                entry.name,
+                data_size,
+                &parameters,
+                separate_read_buffer,
+            );
+
+            (ShaderKind::Compute(parameters), run_parameters)
+        },
+    };
+
+    (
+        GpuExecutor::with_parameters(vulkan, &shader, kind, data, data_size, entry.op),
+        RunParameters {
+            data: Some(data_label),
+            ..parameters
+        },
+    )
+}
+
+/// The reduction in f64, what float results are compared against
+fn reference<Type: AutoReducible>(data: &[Type], op: ReduceOp) -> f64 {
+    data.iter()
+        .map(|value| <f64 as NumCast>::from(*value).unwrap())
+        .fold(op.identity::<f64>(), |a, b| op.apply(a, b))
+}
+
+/// Id like the ones of [gpu_compute::bench_matrix::BenchCase::id], with the
+/// parameters sweeps vary
+fn run_id(parameters: &RunParameters) -> String {
+    let memory = if parameters.separate_read_buffer {
+        ""
+    } else {
+        "_cpu_visible_memory"
+    };
+    let variant = match parameters.framebuffer_y {
+        Some(y) => format!("_{}x{y}", parameters.data_size_x / y),
+        None => parameters
+            .workgroup_size
+            .map(|size| format!("_group_{size}"))
+            .unwrap_or_default(),
+    };
+
+    format!(
+        "{}{memory}{variant}/{}",
+        parameters.shader,
+        parameters.elements()
+    )
+}
+
+/// Runs `entry` on the `input` values and records the timed runs into
+/// `results`
+fn measure<Type: AutoReducible>(
+    vulkan: &mut VulkanData,
+    results: &mut ResultSet,
+    entry: &RegisteredShader,
+    knobs: &Knobs,
+    timing: &TimingArgs,
+    input: (Vec<Type>, String),
+    data_args: &DataArgs,
+) {
+    let separate_read_buffer = knobs.separate_read_buffer.unwrap_or(false);
+    let mut recorder = Recorder::with_reference(reference(&input.0, entry.op));
+
+    let (mut execute, parameters) = prepare(vulkan, entry, knobs, input, data_args);
+    println!("{}: {}", entry.name, execute.describe());
+
+    for _ in 0..timing.warmup {
+        execute.run(vulkan, separate_read_buffer);
+    }
+    vulkan.with_gpu_timing(|vulkan| {
+        for _ in 0..timing.iterations {
+            let result =
+                recorder.measure(vulkan, |vulkan| execute.run(vulkan, separate_read_buffer));
+            recorder.result(NumCast::from(result).unwrap());
+        }
+    });
+
+    let group = format!("gpu_{}_{}", entry.op.name(), entry.element_type);
+    results.record(&group, &run_id(&parameters), parameters, recorder);
+    println!("{}", summary(results.results.last().unwrap()));
+}
+
+fn summary(result: &BenchResult) -> String {
+    let mut summary = format!(
+        "{:<72} median {:>12.0} ns",
+        result.id, result.wall_ns.median
+    );
+    if let Some(gpu) = &result.gpu_ns {
+        write!(summary, " gpu {:>12.0} ns", gpu.median).unwrap();
+    }
+    write!(summary, " {:>8.2} GB/s", result.gb_per_s).unwrap();
+    if let Some(accuracy) = &result.accuracy {
+        write!(
+            summary,
+            " relative error {:e}{}",
+            accuracy.relative_error,
+            if accuracy.reproducible {
+                ""
+            } else {
+                " not reproducible"
+            }
+        )
+        .unwrap();
+    }
+
+    summary
+}
+
+fn write_results(results: &ResultSet, output: &OutputArgs) {
+    let Some(path) = &output.output else {
+        return;
+    };
+
+    match output.format {
+        OutputFormat::Text => {
+            std::fs::write(path, results.results.iter().map(summary).join("\n") + "\n")
+        },
+        OutputFormat::Json => results.write_json(path),
+        OutputFormat::Csv => results.write_csv(path),
+    }
+    .unwrap();
+    println!("Results written to {:?}", path);
+}
+
+fn run<Type: AutoReducible + Element>(vulkan: &mut VulkanData, args: &RunArgs) {
+    let entry = find_shader(&args.shader, &args.selection);
+    let mut results = ResultSet::new(vulkan);
+
+    measure(
+        vulkan,
+        &mut results,
+        &entry,
+        &args.knobs,
+        &args.timing,
+        args.data.load::<Type>(args.size),
+        &args.data,
+    );
+
+    write_results(&results, &args.output);
+}
+
+fn sweep<Type: AutoReducible + Element>(vulkan: &mut VulkanData, args: &SweepArgs) {
+    let entry = find_shader(&args.shader, &args.selection);
+    let mut results = ResultSet::new(vulkan);
+
+    let sizes = match (&args.data.input, args.sizes.is_empty()) {
+        // The size of the file
+        (Some(_), _) => vec![0],
+        (None, true) => vulkan.profiling_sizes(),
+        (None, false) => args.sizes.clone(),
+    };
+    // One variant with the shader's parameters if nothing is swept
+    let variants = match entry.kind {
+        ShaderKind::Graphics(_) => args
+            .framebuffer_ys
+            .iter()
+            .map(|y| Knobs {
+                framebuffer_y: Some(*y),
+                ..args.knobs.clone()
+            })
+            .collect_vec(),
+        ShaderKind::Compute(_) => args
+            .workgroup_sizes
+            .iter()
+            .map(|size| Knobs {
+                workgroup_size: Some(*size),
+                ..args.knobs.clone()
+            })
+            .collect_vec(),
+    };
+    let variants = if variants.is_empty() {
+        vec![args.knobs.clone()]
+    } else {
+        variants
+    };
+
+    for size in sizes {
+        let input = args.data.load::<Type>(size);
+
+        for knobs in variants.iter() {
+            measure(
+                vulkan,
+                &mut results,
+                &entry,
+                knobs,
+                &args.timing,
+                input.clone(),
+                &args.data,
+            );
+        }
+    }
+
+    write_results(&results, &args.output);
+}
+
+/// Whether every selected shader matched the CPU for every size
+fn verify<Type: AutoReducible + Element>(vulkan: &mut VulkanData, args: &VerifyArgs) -> bool {
+    let entries = match &args.shader {
+        Some(name) => vec![find_shader(name, &args.selection)],
+        None => shaders()
+            .into_iter()
+            .filter(|entry| args.selection.matches(entry))
+            .collect(),
+    };
+    let sizes = match args.data.input {
+        Some(_) => vec![0],
+        None => args.sizes.clone(),
+    };
+    let float_sum = args.selection.op == Op::Sum && args.selection.element_type == ElementType::F32;
+
+    let mut passed = true;
+    for size in sizes {
+        let input = args.data.load::<Type>(size);
+        let reference = reference(&input.0, args.selection.op.into());
+
+        for entry in entries.iter() {
+            if !entry.supported(vulkan) {
+                println!("{:<48} skipped, needs subgroup operations", entry.name);
+                continue;
+            }
+
+            let (mut execute, parameters) =
+                prepare(vulkan, entry, &args.knobs, input.clone(), &args.data);
+            let result = execute.run(vulkan, parameters.separate_read_buffer);
+            let expected = execute.expected();
+
+            let (ok, difference) = if float_sum {
+                let result = <f64 as NumCast>::from(result).unwrap();
+                let error = (result - reference).abs() / reference.abs().max(f64::MIN_POSITIVE);
+                (error <= args.tolerance, format!("relative error {error:e}"))
+            } else {
+                (result == expected, format!("expected {expected:?}"))
+            };
+            passed &= ok;
+
+            println!(
+                "{:<48} {:>10} values: {} {result:?}, {difference}",
+                entry.name,
+                parameters.elements(),
+                if ok { "ok" } else { "FAILED" },
+            );
+        }
+    }
+
+    passed
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut vulkan = match &args.command {
+        Command::ListDevices => {
+            for (index, device) in VulkanData::physical_devices().iter().enumerate() {
+                let properties = device.properties();
+                println!(
+                    "{index}: {} ({:?}) vendor {:#06x} device {:#06x} driver {} api {} subgroup size {}",
+                    properties.device_name,
+                    properties.device_type,
+                    properties.vendor_id,
+                    properties.device_id,
+                    properties
+                        .driver_info
+                        .clone()
+                        .unwrap_or_else(|| properties.driver_version.to_string()),
+                    properties.api_version,
+                    properties
+                        .subgroup_size
+                        .map(|size| size.to_string())
+                        .unwrap_or_else(|| "unknown".to_string()),
+                );
+            }
+            return;
+        },
+        Command::ListShaders => {
+            println!("| Op | Type | Shader | Backend | Vectorization | Output |");
+            println!("|---|---|---|---|---:|---|");
+            for entry in shaders() {
+                let (backend, vectorization_factor, output) = match &entry.kind {
+                    ShaderKind::Graphics(p) => (
+                        "graphics",
+                        p.vectorization_factor,
+                        format!("{:?}", p.output),
+                    ),
+                    ShaderKind::Compute(p) => {
+                        ("compute", p.vectorization_factor, format!("{:?}", p.output))
+                    },
+                };
+                println!(
+                    "| {} | {} | {}{} | {backend} | {vectorization_factor} | {output} |",
+                    entry.op.name(),
+                    entry.element_type,
+                    entry.name,
+                    if entry.subgroups { " (subgroups)" } else { "" },
+                );
+            }
+            return;
+        },
+        _ => VulkanData::init_device(args.device),
+    };
+
+    match &args.command {
+        Command::Run(run_args) => match run_args.selection.element_type {
+            ElementType::U32 => run::<u32>(&mut vulkan, run_args),
+            ElementType::F32 => run::<f32>(&mut vulkan, run_args),
+        },
+        Command::Sweep(sweep_args) => match sweep_args.selection.element_type {
+            ElementType::U32 => sweep::<u32>(&mut vulkan, sweep_args),
+            ElementType::F32 => sweep::<f32>(&mut vulkan, sweep_args),
+        },
+        Command::Verify(verify_args) => {
+            let passed = match verify_args.selection.element_type {
+                ElementType::U32 => verify::<u32>(&mut vulkan, verify_args),
+                ElementType::F32 => verify::<f32>(&mut vulkan, verify_args),
+            };
+            if !passed {
+                std::process::exit(1);
+            }
+        },
+        Command::ListDevices | Command::ListShaders => unreachable!(),
+    }
+}
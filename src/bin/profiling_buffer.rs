@@ -2,11 +2,13 @@
 
 use clap::Parser;
 use gpu_compute::{
-    execute_util::{ExecuteParameters, ExecuteUtil, OutputKind, QuadMethod},
+    data_source::DataSet,
+    execute_util::{generate_data, ExecuteParameters, ExecuteUtil, OutputKind, QuadMethod},
     vulkan_util::VulkanData,
 };
+use itertools::Itertools;
 use nalgebra::Vector2;
-use std::{io::stdin, sync::Arc, time::Duration};
+use std::{io::stdin, path::PathBuf, sync::Arc, time::Duration};
 use vulkano::{
     image::ImageUsage,
     swapchain::{acquire_next_image, Swapchain, SwapchainCreateInfo, SwapchainPresentInfo},
@@ -31,6 +33,11 @@ struct Args {
 
     #[arg(short, long, default_value_t = 100_000_000u32)]
     pub data_size: u32,
+
+    /// Raw little endian u32, `.npy` or single column `.csv` file to sum
+    /// instead of generated data
+    #[arg(long)]
+    pub input: Option<PathBuf>,
 }
 
 fn main() {
@@ -62,12 +69,26 @@ fn main() {
     )
     .unwrap();
 
-    let data_size = Vector2::<u32>::new(16384, args.data_size.div_ceil(16384));
+    let input = args
+        .input
+        .as_ref()
+        .map(|path| DataSet::<u32>::read(path).unwrap());
+    let data_size = match &input {
+        Some(input) => input.data_size(16384),
+        None => Vector2::<u32>::new(16384, args.data_size.div_ceil(16384)),
+    };
     println!("{:?}", data_size);
 
-    let shader = attach_none_sampled_loop::load(vulkan.device.clone()).unwrap();
-    let mut execute = ExecuteUtil::<u32>::setup_2d_sampler(
+    // Zero, the identity of the sum, fills the rest of the image
+    let data = match &input {
+        Some(input) => input.padded(data_size, 0),
+        None => generate_data(data_size.x * data_size.y).collect_vec(),
+    };
+
+    let shader = vulkan.load_shader(attach_none_sampled_loop::load);
+    let mut execute = ExecuteUtil::<u32>::setup_2d_sampler_from_data(
         &mut vulkan,
+        data,
         data_size,
         &shader,
         attach_none_sampled_loop::SpecializationConstants {
@@ -131,4 +152,4 @@ mod attach_none_sampled_loop {
         path: "shaders/instances/gpu_sum/attach_none_sampled2D_loop.glsl",
         include: ["shaders/pluggable"],
     }
-}
\ No newline at end of file
+}
@@ -2,11 +2,15 @@
 
 use clap::Parser;
 use gpu_compute::{
+    data_source::DataSet,
+    execute_util::generate_data,
     execute_util_compute::{ComputeExecuteUtil, ComputeParameters, OutputModification},
+    executor::PluggableConstants,
     vulkan_util::VulkanData,
 };
+use itertools::Itertools;
 use nalgebra::Vector2;
-use std::{io::stdin, sync::Arc, time::Duration};
+use std::{io::stdin, path::PathBuf, sync::Arc, time::Duration};
 use vulkano::{
     image::ImageUsage,
     swapchain::{acquire_next_image, Swapchain, SwapchainCreateInfo, SwapchainPresentInfo},
@@ -29,11 +33,17 @@ struct Args {
 
     #[arg(short, long)]
     invocation_size: u32,
-    #[arg(short, long)]
-    work_size: u32,
+    /// Number of generated values, the size of the input file otherwise
+    #[arg(short, long, required_unless_present = "input")]
+    work_size: Option<u32>,
 
     #[arg(short, long, default_value_t = false)]
     accumulate_in_subgroup: bool,
+
+    /// Raw little endian u32, `.npy` or single column `.csv` file to sum
+    /// instead of generated data
+    #[arg(long)]
+    input: Option<PathBuf>,
 }
 
 fn main() {
@@ -71,21 +81,35 @@ fn main() {
     )
     .unwrap();
 
-    let data_size = Vector2::<u32>::new(
-        args.invocation_size,
-        args.work_size.div_ceil(args.invocation_size),
-    );
+    let (data, data_size) = match &args.input {
+        Some(path) => {
+            let input = DataSet::<u32>::read(path).unwrap();
+            let data_size = input.data_size(args.invocation_size);
+            (input.values, data_size)
+        },
+        None => {
+            let data_size = Vector2::<u32>::new(
+                args.invocation_size,
+                args.work_size.unwrap().div_ceil(args.invocation_size),
+            );
+            (
+                generate_data(data_size.x * data_size.y).collect_vec(),
+                data_size,
+            )
+        },
+    };
 
     let shader = if args.accumulate_in_subgroup {
-        compute_none_groupbuffer_loop::load(vulkan.device.clone()).unwrap()
+        vulkan.load_shader(compute_none_groupbuffer_loop::load)
     } else {
-        compute_none_sbuffer_loop::load(vulkan.device.clone()).unwrap()
+        vulkan.load_shader(compute_none_sbuffer_loop::load)
     };
-    let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
+    let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer_from_data(
         &mut vulkan,
+        data,
         data_size,
         &shader,
-        compute_none_sbuffer_loop::SpecializationConstants {
+        PluggableConstants {
             TEXTURE_SIZE_X: data_size.x as _,
             TEXTURE_SIZE_Y: 1,
         },
@@ -1,11 +1,14 @@
 #![feature(int_roundings)]
 
+use clap::Parser;
 use gpu_compute::{
-    execute_util::{ExecuteParameters, ExecuteUtil, OutputKind, QuadMethod},
+    data_source::DataSet,
+    execute_util::{generate_data, ExecuteParameters, ExecuteUtil, OutputKind, QuadMethod},
     vulkan_util::VulkanData,
 };
+use itertools::Itertools;
 use nalgebra::Vector2;
-use std::{sync::Arc, time::Duration};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 use vulkano::{
     image::ImageUsage,
     swapchain::{acquire_next_image, Swapchain, SwapchainCreateInfo, SwapchainPresentInfo},
@@ -17,7 +20,18 @@ use winit::{
     window::Window,
 };
 
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Raw little endian u32, `.npy` or single column `.csv` file to sum
+    /// instead of generated data
+    #[arg(long)]
+    input: Option<PathBuf>,
+}
+
 fn main() {
+    let args = Args::parse();
+
     let mut vulkan = VulkanData::init();
 
     let event_loop = EventLoop::new();
@@ -37,12 +51,26 @@ fn main() {
     )
     .unwrap();
 
-    let data_size = Vector2::<u32>::new(256, 256);
+    let input = args
+        .input
+        .as_ref()
+        .map(|path| DataSet::<u32>::read(path).unwrap());
+    let data_size = match &input {
+        Some(input) => input.data_size(256),
+        None => Vector2::<u32>::new(256, 256),
+    };
     let work_size_y = 16u32;
 
-    let shader = attach_none_sampled_loop::load(vulkan.device.clone()).unwrap();
-    let mut execute = ExecuteUtil::<u32>::setup_2d_sampler(
+    // Zero, the identity of the sum, fills the rest of the image
+    let data = match &input {
+        Some(input) => input.padded(data_size, 0),
+        None => generate_data(data_size.x * data_size.y).collect_vec(),
+    };
+
+    let shader = vulkan.load_shader(attach_none_sampled_loop::load);
+    let mut execute = ExecuteUtil::<u32>::setup_2d_sampler_from_data(
         &mut vulkan,
+        data,
         data_size,
         &shader,
         attach_none_sampled_loop::SpecializationConstants {
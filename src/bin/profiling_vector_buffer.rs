@@ -1,11 +1,16 @@
 #![feature(int_roundings)]
 
+use clap::Parser;
 use gpu_compute::{
+    data_source::DataSet,
+    execute_util::generate_data,
     execute_util_compute::{ComputeExecuteUtil, ComputeParameters},
+    executor::PluggableConstants,
     vulkan_util::VulkanData,
 };
+use itertools::Itertools;
 use nalgebra::Vector2;
-use std::{io::stdin, sync::Arc, time::Duration};
+use std::{io::stdin, path::PathBuf, sync::Arc, time::Duration};
 use vulkano::{
     image::ImageUsage,
     swapchain::{acquire_next_image, Swapchain, SwapchainCreateInfo, SwapchainPresentInfo},
@@ -17,7 +22,18 @@ use winit::{
     window::Window,
 };
 
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Raw little endian u32, `.npy` or single column `.csv` file to sum
+    /// instead of generated data
+    #[arg(long)]
+    input: Option<PathBuf>,
+}
+
 fn main() {
+    let args = Args::parse();
+
     let hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |p| {
         hook(p);
@@ -43,14 +59,28 @@ fn main() {
     )
     .unwrap();
 
-    let data_size = Vector2::<u32>::new(32768, 4);
+    let (data, data_size) = match args.input {
+        Some(path) => {
+            let input = DataSet::<u32>::read(path).unwrap();
+            let data_size = input.data_size(32768);
+            (input.values, data_size)
+        },
+        None => {
+            let data_size = Vector2::<u32>::new(32768, 4);
+            (
+                generate_data(data_size.x * data_size.y).collect_vec(),
+                data_size,
+            )
+        },
+    };
 
-    let shader = compute_none_sbuffer_loop::load(vulkan.device.clone()).unwrap();
-    let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer(
+    let shader = vulkan.load_shader(compute_none_sbuffer_loop::load);
+    let mut execute = ComputeExecuteUtil::<u32>::setup_storage_buffer_from_data(
         &mut vulkan,
+        data,
         data_size,
         &shader,
-        compute_none_sbuffer_loop::SpecializationConstants {
+        PluggableConstants {
             TEXTURE_SIZE_X: data_size.x as _,
             TEXTURE_SIZE_Y: 1,
         },
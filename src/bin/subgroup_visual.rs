@@ -43,6 +43,7 @@ fn run(vulkan: &mut VulkanData, size: Vector2<u32>, method: QuadMethod, subgroup
 
     let render_pass = vulkan.create_render_pass(RenderPassKey {
         format: Some(FORMAT),
+        attachments: 1,
     });
 
     let vs = vs::load(vulkan.device.clone()).unwrap();
@@ -0,0 +1,269 @@
+use num::Zero;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    ops::Add,
+    simd::{f32x16, f64x8, i32x16, u32x16, SimdFloat, SimdInt, SimdOrd, SimdUint},
+};
+
+/// The reductions there are shaders and benchmarks for
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum ReduceOp {
+    Sum,
+    Min,
+}
+
+impl ReduceOp {
+    /// Name of the operation in benchmark groups, e.g. `gpu_sum`
+    pub fn name(self) -> &'static str {
+        match self {
+            ReduceOp::Sum => "sum",
+            ReduceOp::Min => "min",
+        }
+    }
+
+    pub fn identity<T: CpuReducible>(self) -> T {
+        match self {
+            ReduceOp::Sum => T::zero(),
+            ReduceOp::Min => T::min_identity(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn apply<T: CpuReducible>(self, a: T, b: T) -> T {
+        match self {
+            ReduceOp::Sum => a.wrapping_add(b),
+            ReduceOp::Min => {
+                if b < a {
+                    b
+                } else {
+                    a
+                }
+            },
+        }
+    }
+}
+
+/// The CPU implementations from `benches/cpu_perf.rs`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum CpuStrategy {
+    Scalar,
+    Parallel,
+    Simd,
+    ParallelSimd,
+}
+
+impl CpuStrategy {
+    pub fn all() -> &'static [CpuStrategy] {
+        &[
+            CpuStrategy::Scalar,
+            CpuStrategy::Parallel,
+            CpuStrategy::Simd,
+            CpuStrategy::ParallelSimd,
+        ]
+    }
+
+    /// Picks the fastest strategy for `len` elements, threading only pays off
+    /// once there is enough work to split
+    pub fn for_len(len: usize) -> Self {
+        if len < SIMD_THRESHOLD {
+            CpuStrategy::Scalar
+        } else if len < PARALLEL_THRESHOLD {
+            CpuStrategy::Simd
+        } else {
+            CpuStrategy::ParallelSimd
+        }
+    }
+
+    pub fn reduce<T: CpuReducible>(self, data: &[T], op: ReduceOp) -> T {
+        match self {
+            CpuStrategy::Scalar => data
+                .iter()
+                .copied()
+                .fold(op.identity(), |a, b| op.apply(a, b)),
+            CpuStrategy::Parallel => data
+                .par_iter()
+                .copied()
+                .reduce(|| op.identity(), |a, b| op.apply(a, b)),
+            CpuStrategy::Simd => T::reduce_simd(data, op),
+            CpuStrategy::ParallelSimd => data
+                .par_chunks(PARALLEL_CHUNK)
+                .map(|chunk| T::reduce_simd(chunk, op))
+                .reduce(|| op.identity(), |a, b| op.apply(a, b)),
+        }
+    }
+}
+
+/// Elements each rayon task reduces with SIMD
+const PARALLEL_CHUNK: usize = 1 << 16;
+
+const SIMD_THRESHOLD: usize = 64;
+const PARALLEL_THRESHOLD: usize = 1 << 18;
+
+pub trait CpuReducible: Copy + Send + Sync + PartialOrd + Add<Output = Self> + Zero {
+    /// Identity of [ReduceOp::Min]
+    fn min_identity() -> Self;
+
+    /// Wraps integers on overflow like the SIMD and GPU sums do, instead of
+    /// panicking in debug builds
+    fn wrapping_add(self, other: Self) -> Self;
+
+    fn reduce_simd(data: &[Self], op: ReduceOp) -> Self;
+}
+
+macro_rules! impl_cpu_reducible {
+    ($t:ty, $simd:ty, $min_identity:expr, $wrapping_add:expr) => {
+        impl CpuReducible for $t {
+            fn min_identity() -> Self {
+                $min_identity
+            }
+
+            fn wrapping_add(self, other: Self) -> Self {
+                $wrapping_add(self, other)
+            }
+
+            fn reduce_simd(data: &[Self], op: ReduceOp) -> Self {
+                let chunks = data.chunks_exact(<$simd>::LANES);
+                let remainder = chunks.remainder();
+
+                let vector = <$simd>::splat(op.identity());
+                let vector = match op {
+                    ReduceOp::Sum => chunks.fold(vector, |acc, it| acc + <$simd>::from_slice(it)),
+                    ReduceOp::Min => {
+                        chunks.fold(vector, |acc, it| acc.simd_min(<$simd>::from_slice(it)))
+                    },
+                };
+                let vector = match op {
+                    ReduceOp::Sum => vector.reduce_sum(),
+                    ReduceOp::Min => vector.reduce_min(),
+                };
+
+                remainder
+                    .iter()
+                    .copied()
+                    .fold(vector, |a, b| op.apply(a, b))
+            }
+        }
+    };
+}
+
+impl_cpu_reducible!(u32, u32x16, u32::MAX, u32::wrapping_add);
+impl_cpu_reducible!(i32, i32x16, i32::MAX, i32::wrapping_add);
+impl_cpu_reducible!(f32, f32x16, f32::INFINITY, f32::add);
+impl_cpu_reducible!(f64, f64x8, f64::INFINITY, f64::add);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+    use num::NumCast;
+
+    /// Lengths around the SIMD width and the parallel chunk size, so remainders
+    /// and partial chunks are covered
+    const LENGTHS: &[usize] = &[
+        0,
+        1,
+        7,
+        16,
+        17,
+        255,
+        1000,
+        PARALLEL_CHUNK + 3,
+        3 * PARALLEL_CHUNK,
+    ];
+
+    fn data<T: NumCast>(len: usize) -> Vec<T> {
+        // Small integers keep float sums exact regardless of summation order
+        (0..len)
+            .map(|i| T::from((i * 7919) % 101).unwrap())
+            .collect_vec()
+    }
+
+    fn check<T>()
+    where
+        T: CpuReducible + NumCast + std::fmt::Debug,
+    {
+        for op in [ReduceOp::Sum, ReduceOp::Min] {
+            for len in LENGTHS.iter().copied() {
+                let data = data::<T>(len);
+                let expected = CpuStrategy::Scalar.reduce(&data, op);
+
+                for strategy in CpuStrategy::all() {
+                    assert_eq!(
+                        strategy.reduce(&data, op),
+                        expected,
+                        "{strategy:?} {op:?} with {len} elements"
+                    );
+                }
+                assert_eq!(CpuStrategy::for_len(len).reduce(&data, op), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn identity_of_empty() {
+        assert_eq!(CpuStrategy::Simd.reduce::<u32>(&[], ReduceOp::Sum), 0);
+        assert_eq!(
+            CpuStrategy::Simd.reduce::<u32>(&[], ReduceOp::Min),
+            u32::MAX
+        );
+        assert_eq!(
+            CpuStrategy::ParallelSimd.reduce::<f32>(&[], ReduceOp::Min),
+            f32::INFINITY
+        );
+    }
+
+    #[test]
+    fn strategies_agree_u32() {
+        check::<u32>();
+    }
+
+    #[test]
+    fn strategies_agree_i32() {
+        check::<i32>();
+    }
+
+    #[test]
+    fn strategies_agree_f32() {
+        check::<f32>();
+    }
+
+    #[test]
+    fn strategies_agree_f64() {
+        check::<f64>();
+    }
+
+    #[test]
+    fn strategies_agree_on_overflow() {
+        for len in LENGTHS.iter().copied().filter(|len| *len > 1) {
+            let data = vec![u32::MAX - 1; len];
+            let expected = (len as u32).wrapping_mul(u32::MAX - 1);
+
+            for strategy in CpuStrategy::all() {
+                assert_eq!(
+                    strategy.reduce(&data, ReduceOp::Sum),
+                    expected,
+                    "{strategy:?} with {len} elements"
+                );
+            }
+        }
+
+        let data = vec![i32::MAX; 100];
+        for strategy in CpuStrategy::all() {
+            assert_eq!(
+                strategy.reduce(&data, ReduceOp::Sum),
+                100i32.wrapping_mul(i32::MAX)
+            );
+        }
+    }
+
+    #[test]
+    fn min_finds_single_minimum() {
+        let mut data = vec![5.0f32; 1000];
+        data[613] = -1.0;
+
+        for strategy in CpuStrategy::all() {
+            assert_eq!(strategy.reduce(&data, ReduceOp::Min), -1.0);
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use crate::executor::grid_layout;
+use bytemuck::Pod;
+use nalgebra::Vector2;
+use num::NumCast;
+use std::{any::type_name, fmt::Debug, io, path::Path, str::FromStr};
+
+/// Types input files can hold
+pub trait Element: Pod + NumCast + FromStr + Debug {
+    /// The `descr` of the little endian type in `.npy` headers
+    const NPY_DESCR: &'static str;
+
+    fn from_le_bytes(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_element {
+    ($t:ty, $descr:expr) => {
+        impl Element for $t {
+            const NPY_DESCR: &'static str = $descr;
+
+            fn from_le_bytes(bytes: &[u8]) -> Self {
+                <$t>::from_le_bytes(bytes.try_into().unwrap())
+            }
+        }
+    };
+}
+
+impl_element!(u32, "<u4");
+impl_element!(i32, "<i4");
+impl_element!(f32, "<f4");
+impl_element!(f64, "<f8");
+
+/// Values read from a file, in row-major order
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataSet<Type> {
+    pub values: Vec<Type>,
+    /// `[len]` for one dimensional data, `[rows, columns]` for two
+    pub shape: Vec<usize>,
+}
+
+impl<Type: Element> DataSet<Type> {
+    /// Picks the format by extension: `.npy`, `.csv`, anything else is read as
+    /// raw binary
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("npy") => Self::read_npy(path),
+            Some("csv") => Self::read_csv(path),
+            _ => Self::read_raw(path),
+        }
+    }
+
+    /// Little endian values of `Type` without any header
+    pub fn read_raw<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let values = from_le_bytes(&bytes)?;
+
+        Ok(Self {
+            shape: vec![values.len()],
+            values,
+        })
+    }
+
+    /// NumPy arrays of one or two dimensions holding any of the [Element]
+    /// types, converted to `Type` if every value fits
+    pub fn read_npy<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+
+        let Some(rest) = bytes.strip_prefix(b"\x93NUMPY") else {
+            return Err(invalid("Not a .npy file"));
+        };
+        let (header_len, rest) = match rest {
+            [1, _, a, b, rest @ ..] => (u16::from_le_bytes([*a, *b]) as usize, rest),
+            [2 | 3, _, a, b, c, d, rest @ ..] => {
+                (u32::from_le_bytes([*a, *b, *c, *d]) as usize, rest)
+            },
+            _ => return Err(invalid("Unsupported .npy version")),
+        };
+        if rest.len() < header_len {
+            return Err(invalid("Truncated .npy header"));
+        }
+        let (header, data) = rest.split_at(header_len);
+        let header = std::str::from_utf8(header).map_err(|_| invalid("Invalid .npy header"))?;
+
+        let descr = header_value(header, "descr")?.trim_matches('\'');
+        let fortran_order = match header_value(header, "fortran_order")? {
+            "True" => true,
+            "False" => false,
+            other => return Err(invalid(format!("Invalid fortran_order {other}"))),
+        };
+        let shape = header_value(header, "shape")?
+            .trim_matches(&['(', ')'][..])
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                s.parse::<usize>()
+                    .map_err(|_| invalid(format!("Invalid shape {s}")))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        if !(1..=2).contains(&shape.len()) {
+            return Err(invalid(format!(
+                "Only 1D and 2D arrays are supported, not {shape:?}"
+            )));
+        }
+
+        let values = if descr == u32::NPY_DESCR {
+            convert::<u32, Type>(data)?
+        } else if descr == i32::NPY_DESCR {
+            convert::<i32, Type>(data)?
+        } else if descr == f32::NPY_DESCR {
+            convert::<f32, Type>(data)?
+        } else if descr == f64::NPY_DESCR {
+            convert::<f64, Type>(data)?
+        } else {
+            return Err(invalid(format!("Unsupported dtype {descr}")));
+        };
+        if values.len() != shape.iter().product::<usize>() {
+            return Err(invalid(format!(
+                "{} values for shape {shape:?}",
+                values.len()
+            )));
+        }
+
+        let values = match (fortran_order, shape.as_slice()) {
+            (true, [rows, columns]) => (0..rows * columns)
+                .map(|i| values[(i % columns) * rows + i / columns])
+                .collect(),
+            _ => values,
+        };
+
+        Ok(Self { values, shape })
+    }
+
+    /// One value per line, an optional header in the first line is skipped
+    pub fn read_csv<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+
+        let mut values = vec![];
+        for (index, line) in text.lines().map(str::trim).enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            if line.contains(',') {
+                return Err(invalid(format!(
+                    "Line {} has more than one column",
+                    index + 1
+                )));
+            }
+
+            match line.parse() {
+                Ok(value) => values.push(value),
+                Err(_) if index == 0 => {},
+                Err(_) => {
+                    return Err(invalid(format!(
+                        "Line {}: {line:?} is not a {}",
+                        index + 1,
+                        type_name::<Type>()
+                    )))
+                },
+            }
+        }
+
+        Ok(Self {
+            shape: vec![values.len()],
+            values,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Grid for the values with at most `max_threads` parallel invocations,
+    /// see [grid_layout]
+    pub fn data_size(&self, max_threads: u32) -> Vector2<u32> {
+        grid_layout(self.len(), max_threads)
+    }
+
+    /// The values padded with `fill` to cover all of `data_size`, for inputs
+    /// like images that can't skip the end of the last row
+    pub fn padded(&self, data_size: Vector2<u32>, fill: Type) -> Vec<Type> {
+        let total = (data_size.x * data_size.y) as usize;
+        assert!(
+            self.len() <= total,
+            "{} values don't fit {data_size:?}",
+            self.len()
+        );
+
+        let mut values = self.values.clone();
+        values.resize(total, fill);
+        values
+    }
+}
+
+fn invalid<M: Into<String>>(message: M) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn from_le_bytes<Type: Element>(bytes: &[u8]) -> io::Result<Vec<Type>> {
+    let size = std::mem::size_of::<Type>();
+    if bytes.len() % size != 0 {
+        return Err(invalid(format!(
+            "{} bytes are no whole number of {}",
+            bytes.len(),
+            type_name::<Type>()
+        )));
+    }
+
+    Ok(bytes.chunks_exact(size).map(Type::from_le_bytes).collect())
+}
+
+fn convert<Source: Element, Type: Element>(bytes: &[u8]) -> io::Result<Vec<Type>> {
+    from_le_bytes::<Source>(bytes)?
+        .into_iter()
+        .map(|value| {
+            Type::from(value)
+                .ok_or_else(|| invalid(format!("{value:?} does not fit {}", type_name::<Type>())))
+        })
+        .collect()
+}
+
+/// The raw value of `key` in the Python dict literal of a `.npy` header
+fn header_value<'h>(header: &'h str, key: &str) -> io::Result<&'h str> {
+    let pattern = format!("'{key}':");
+    let start = header
+        .find(&pattern)
+        .ok_or_else(|| invalid(format!("No {key} in .npy header")))?;
+    let value = header[start + pattern.len()..].trim_start();
+
+    let end = match value.chars().next() {
+        Some('(') => value.find(')').map(|end| end + 1),
+        Some('\'') => value[1..].find('\'').map(|end| end + 2),
+        _ => value.find([',', '}']),
+    };
+    end.map(|end| value[..end].trim())
+        .ok_or_else(|| invalid(format!("Invalid {key} in .npy header")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/data_source")
+            .join(name)
+    }
+
+    #[test]
+    fn npy_1d() {
+        let data = DataSet::<u32>::read(fixture("u32_1d.npy")).unwrap();
+
+        assert_eq!(data.values, [1, 2, 3, 4, 5]);
+        assert_eq!(data.shape, [5]);
+    }
+
+    #[test]
+    fn npy_2d() {
+        let data = DataSet::<i32>::read(fixture("i32_2d.npy")).unwrap();
+
+        assert_eq!(data.values, [-1, 2, -3, 4, -5, 6]);
+        assert_eq!(data.shape, [2, 3]);
+    }
+
+    #[test]
+    fn npy_fortran_order_is_transposed() {
+        let data = DataSet::<f32>::read(fixture("f32_2d_fortran.npy")).unwrap();
+
+        assert_eq!(data.values, [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(data.shape, [2, 3]);
+    }
+
+    #[test]
+    fn npy_version_2_converts() {
+        let data = DataSet::<f32>::read(fixture("f64_1d_v2.npy")).unwrap();
+        assert_eq!(data.values, [0.5, 1.5, 2.5, 3.5]);
+
+        // Negative values don't fit
+        assert!(DataSet::<u32>::read(fixture("i32_2d.npy")).is_err());
+    }
+
+    #[test]
+    fn raw() {
+        let data = DataSet::<u32>::read(fixture("u32.bin")).unwrap();
+        assert_eq!(data.values, [7, 8, 9, u32::MAX]);
+
+        let data = DataSet::<f64>::read_raw(fixture("u32.bin")).unwrap();
+        assert_eq!(data.len(), 2);
+    }
+
+    #[test]
+    fn csv() {
+        let data = DataSet::<f32>::read(fixture("column.csv")).unwrap();
+        assert_eq!(data.values, [1.5, -2.0, 3.25]);
+        assert_eq!(data.shape, [3]);
+
+        assert!(DataSet::<u32>::read(fixture("column.csv")).is_err());
+        assert!(DataSet::<u32>::read(fixture("two_columns.csv")).is_err());
+    }
+
+    #[test]
+    fn padded_to_grid() {
+        let data = DataSet::<u32>::read(fixture("u32_1d.npy")).unwrap();
+        let data_size = data.data_size(2);
+
+        assert_eq!(data_size, Vector2::new(2, 3));
+        assert_eq!(data.padded(data_size, 0), [1, 2, 3, 4, 5, 0]);
+    }
+}
@@ -0,0 +1,384 @@
+use crate::{
+    cpu::ReduceOp,
+    results::{BenchResult, ResultSet},
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+/// The strategy to use for all inputs up to `max_elements`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct DispatchBucket {
+    /// Inclusive upper bound, the last bucket also covers everything larger
+    pub max_elements: u64,
+    pub backend: String,
+    /// See [qualified_strategy]
+    pub strategy: String,
+    pub median_ns: f64,
+}
+
+/// Size from which on a GPU strategy beats every CPU strategy
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Crossover {
+    /// See [qualified_strategy]
+    pub strategy: String,
+    /// `None` if the GPU strategy never wins in the measured range
+    pub elements: Option<u64>,
+}
+
+/// Best strategy per size bucket for one operation and element type, built
+/// from measured results
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct DispatchTable {
+    pub operation: String,
+    /// See [crate::results::RunParameters::element_type]
+    pub element_type: String,
+    pub device: String,
+    pub buckets: Vec<DispatchBucket>,
+    pub crossovers: Vec<Crossover>,
+}
+
+/// `gpu_sum` and `cpu_sum` both compute `sum`, `gpu_min_f32` and `cpu_min`
+/// both compute `min`. `None` for groups that don't start with a backend and
+/// a [ReduceOp], e.g. `call_times`. The element type of a group comes from
+/// its results, see [crate::results::RunParameters::element_type]
+pub fn operation_of(group: &str) -> Option<&str> {
+    let mut parts = group.split('_');
+    match (parts.next(), parts.next()) {
+        (Some("cpu" | "gpu" | "auto"), Some(op))
+            if [ReduceOp::Sum, ReduceOp::Min]
+                .iter()
+                .any(|it| it.name() == op) =>
+        {
+            Some(op)
+        },
+        _ => None,
+    }
+}
+
+/// Benchmark ids end in the data size, everything before identifies the
+/// strategy
+pub fn strategy_of(result: &BenchResult) -> &str {
+    result
+        .id
+        .rsplit_once('/')
+        .map(|(strategy, _)| strategy)
+        .unwrap_or(&result.id)
+}
+
+/// The strategy prefixed by its backend, e.g. `cpu:u32_vector`, so equally
+/// named CPU and GPU strategies stay apart
+pub fn qualified_strategy(result: &BenchResult) -> String {
+    format!("{}:{}", result.parameters.backend, strategy_of(result))
+}
+
+impl DispatchTable {
+    /// Builds one table per operation and element type found in `sets`.
+    /// Results that don't record their element type are skipped
+    pub fn analyze(sets: &[ResultSet]) -> Vec<Self> {
+        let device = sets
+            .iter()
+            .map(|set| set.device.name.clone())
+            .unique()
+            .join(", ");
+
+        sets.iter()
+            .flat_map(|set| set.results.iter())
+            .filter_map(|result| {
+                let operation = operation_of(&result.group)?.to_string();
+                let element_type = result.parameters.element_type.clone()?;
+                Some(((operation, element_type), result))
+            })
+            .into_group_map()
+            .into_iter()
+            .sorted_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|((operation, element_type), results)| {
+                Self::analyze_operation(operation, element_type, device.clone(), &results)
+            })
+            .collect_vec()
+    }
+
+    fn analyze_operation(
+        operation: String,
+        element_type: String,
+        device: String,
+        results: &[&BenchResult],
+    ) -> Self {
+        // elements -> (backend, strategy) -> median
+        let mut by_size: BTreeMap<u64, Vec<(&str, String, f64)>> = BTreeMap::new();
+        for result in results {
+            by_size
+                .entry(result.parameters.elements())
+                .or_default()
+                .push((
+                    &result.parameters.backend,
+                    qualified_strategy(result),
+                    result.wall_ns.median,
+                ));
+        }
+
+        let best_cpu = |entries: &[(&str, String, f64)]| {
+            entries
+                .iter()
+                .filter(|(backend, _, _)| *backend == "cpu")
+                .map(|(_, _, time)| *time)
+                .min_by(f64::total_cmp)
+        };
+
+        let gpu_strategies = results
+            .iter()
+            .filter(|r| r.parameters.backend != "cpu")
+            .map(|r| qualified_strategy(r))
+            .unique()
+            .sorted()
+            .collect_vec();
+
+        let crossovers = gpu_strategies
+            .into_iter()
+            .map(|strategy| {
+                // The smallest size after which the GPU strategy wins at every measured size
+                let mut elements = None;
+                for (size, entries) in by_size.iter() {
+                    let gpu = entries
+                        .iter()
+                        .find(|(backend, s, _)| *backend != "cpu" && *s == strategy)
+                        .map(|(_, _, time)| *time);
+
+                    match (gpu, best_cpu(entries)) {
+                        (Some(gpu), Some(cpu)) if gpu < cpu => {
+                            elements.get_or_insert(*size);
+                        },
+                        (Some(_), Some(_)) => elements = None,
+                        _ => {},
+                    }
+                }
+
+                Crossover { strategy, elements }
+            })
+            .collect_vec();
+
+        let mut buckets: Vec<DispatchBucket> = vec![];
+        for (size, entries) in by_size.iter() {
+            let Some((backend, strategy, time)) = entries.iter().min_by(|a, b| a.2.total_cmp(&b.2))
+            else {
+                continue;
+            };
+
+            match buckets.last_mut() {
+                Some(last) if last.backend == *backend && last.strategy == *strategy => {
+                    last.max_elements = *size;
+                    last.median_ns = *time;
+                },
+                _ => buckets.push(DispatchBucket {
+                    max_elements: *size,
+                    backend: backend.to_string(),
+                    strategy: strategy.to_string(),
+                    median_ns: *time,
+                }),
+            }
+        }
+
+        Self {
+            operation,
+            element_type,
+            device,
+            buckets,
+            crossovers,
+        }
+    }
+
+    /// The bucket responsible for `elements` values
+    pub fn lookup(&self, elements: u64) -> Option<&DispatchBucket> {
+        self.buckets
+            .iter()
+            .find(|bucket| elements <= bucket.max_elements)
+            .or(self.buckets.last())
+    }
+
+    pub fn read_json<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<Self>> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    pub fn write_json<P: AsRef<Path>>(tables: &[Self], path: P) -> std::io::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(tables)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::{DeviceInfo, RunParameters, Timing};
+
+    fn result<T>(
+        group: &str,
+        backend: &str,
+        strategy: &str,
+        elements: u32,
+        median: f64,
+    ) -> BenchResult {
+        BenchResult {
+            group: group.to_string(),
+            id: format!("{strategy}/{elements}"),
+            parameters: RunParameters {
+                backend: backend.to_string(),
+                ..RunParameters::cpu::<T>(strategy, elements)
+            },
+            samples: 1,
+            wall_ns: Timing {
+                median,
+                ..Default::default()
+            },
+            gpu_ns: None,
+            gb_per_s: 0.0,
+            elements_per_s: 0.0,
+            percent_of_peak: None,
+            accuracy: None,
+        }
+    }
+
+    fn set(results: Vec<BenchResult>) -> ResultSet {
+        ResultSet {
+            device: DeviceInfo {
+                name: "test".to_string(),
+                device_type: "Other".to_string(),
+                vendor_id: 0,
+                device_id: 0,
+                driver_version: 0,
+                driver_info: None,
+                api_version: "1.3".to_string(),
+                subgroup_size: None,
+                subgroup_stages: None,
+                subgroup_operations: None,
+                subgroup_fallbacks: vec![],
+                gpu_thread_count: 1,
+            },
+            peak_gb_per_s: None,
+            results,
+        }
+    }
+
+    #[test]
+    fn operation_of_groups() {
+        assert_eq!(operation_of("gpu_sum"), Some("sum"));
+        assert_eq!(operation_of("cpu_sum"), Some("sum"));
+        assert_eq!(operation_of("gpu_min_f32"), Some("min"));
+        assert_eq!(operation_of("auto_min"), Some("min"));
+        assert_eq!(operation_of("call_times"), None);
+        assert_eq!(operation_of("optimal_accumulate_size_compute"), None);
+        assert_eq!(operation_of("gpu"), None);
+        assert_eq!(operation_of("gpu_mean"), None);
+    }
+
+    #[test]
+    fn analyze_finds_crossover_and_buckets() {
+        let mut results = vec![];
+        for (elements, cpu, gpu) in [
+            (1 << 10, 1.0, 50.0),
+            (1 << 16, 30.0, 40.0),
+            (1 << 20, 500.0, 60.0),
+            (1 << 24, 8000.0, 400.0),
+        ] {
+            results.push(result::<u32>("cpu_sum", "cpu", "u32", elements, cpu));
+            results.push(result::<u32>("gpu_sum", "compute", "u32", elements, gpu));
+        }
+        // Not an operation, ignored
+        results.push(result::<u32>("call_times", "graphics", "u32", 1 << 10, 0.1));
+
+        let tables = DispatchTable::analyze(&[set(results)]);
+        assert_eq!(tables.len(), 1);
+        let table = &tables[0];
+        assert_eq!(table.operation, "sum");
+        assert_eq!(table.element_type, "u32");
+
+        assert_eq!(
+            table.crossovers,
+            vec![Crossover {
+                strategy: "compute:u32".to_string(),
+                elements: Some(1 << 20),
+            }]
+        );
+
+        // Equally named strategies of different backends get their own buckets
+        assert_eq!(
+            table
+                .buckets
+                .iter()
+                .map(|bucket| (
+                    bucket.max_elements,
+                    bucket.backend.as_str(),
+                    bucket.strategy.as_str()
+                ))
+                .collect_vec(),
+            vec![
+                (1 << 16, "cpu", "cpu:u32"),
+                (1 << 24, "compute", "compute:u32")
+            ]
+        );
+        assert_eq!(table.buckets[0].median_ns, 30.0);
+        assert_eq!(table.lookup(1).unwrap().backend, "cpu");
+        assert_eq!(table.lookup(1 << 30).unwrap().backend, "compute");
+    }
+
+    #[test]
+    fn crossover_needs_a_win_at_every_larger_size() {
+        let mut results = vec![];
+        for (elements, cpu, gpu) in [
+            (1 << 10, 10.0, 5.0),
+            (1 << 16, 10.0, 20.0),
+            (1 << 20, 100.0, 50.0),
+        ] {
+            results.push(result::<f32>("cpu_min", "cpu", "f32", elements, cpu));
+            results.push(result::<f32>(
+                "gpu_min_f32",
+                "graphics",
+                "f32",
+                elements,
+                gpu,
+            ));
+        }
+
+        let tables = DispatchTable::analyze(&[set(results)]);
+        assert_eq!(tables[0].operation, "min");
+        assert_eq!(tables[0].crossovers[0].elements, Some(1 << 20));
+        assert_eq!(tables[0].buckets.len(), 3);
+    }
+
+    #[test]
+    fn element_types_get_their_own_tables() {
+        let mut results = vec![];
+        for elements in [1 << 10, 1 << 20] {
+            results.push(result::<u32>("cpu_sum", "cpu", "u32", elements, 100.0));
+            results.push(result::<u32>(
+                "gpu_sum",
+                "compute",
+                "compute_buffer_to_buffer",
+                elements,
+                50.0,
+            ));
+            results.push(result::<f32>(
+                "gpu_sum_f32",
+                "compute",
+                "compute_buffer_to_buffer",
+                elements,
+                200.0,
+            ));
+        }
+
+        let tables = DispatchTable::analyze(&[set(results)]);
+        assert_eq!(
+            tables
+                .iter()
+                .map(|table| (table.operation.as_str(), table.element_type.as_str()))
+                .collect_vec(),
+            vec![("sum", "f32"), ("sum", "u32")]
+        );
+
+        // f32 GPU times are not compared against u32 CPU times
+        assert_eq!(tables[0].crossovers[0].elements, None);
+        assert_eq!(tables[0].buckets.len(), 1);
+        assert_eq!(tables[0].buckets[0].median_ns, 200.0);
+        assert_eq!(tables[1].crossovers[0].elements, Some(1 << 10));
+        assert_eq!(tables[1].buckets[0].backend, "compute");
+    }
+}
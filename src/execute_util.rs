@@ -1,4 +1,12 @@
-use crate::vulkan_util::{MVertex, RenderPassKey, VulkanData};
+use crate::{
+    cpu::{CpuReducible, ReduceOp},
+    executor::{
+        accumulate_on_cpu, expected_result, layer_count, pad_to_vectors, storage_image_input,
+        texel_buffer_input, uniform_buffer_input, Executor, InputChunk, PluggableConstants,
+        UNIFORM_CHUNK_SIZE,
+    },
+    vulkan_util::{MVertex, PipelineKey, RenderPassKey, VulkanData},
+};
 use bytemuck::Pod;
 use derivative::Derivative;
 use itertools::Itertools;
@@ -6,26 +14,27 @@ use nalgebra::Vector2;
 use num::{NumCast, Zero};
 use rand::{Rng, SeedableRng};
 use rand_pcg::Pcg64Mcg;
+use serde::{Deserialize, Serialize};
 use std::{
-    fmt::Debug,
+    any::type_name,
+    f64::consts::TAU,
+    fmt::{Debug, Display, Formatter},
     hint::black_box,
     iter::{once, Sum},
+    str::FromStr,
     sync::Arc,
 };
 use vulkano::{
-    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
-    command_buffer::{
-        CopyBufferInfo, PrimaryCommandBufferAbstract, RenderPassBeginInfo, SubpassContents,
-    },
+    buffer::{BufferContents, BufferUsage, Subbuffer},
+    command_buffer::{CopyBufferInfo, RenderPassBeginInfo, SubpassContents},
     descriptor_set::{
         allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet,
     },
-    format::{ClearValue, Format},
-    image::view::ImageView,
-    memory::allocator::{AllocationCreateInfo, MemoryUsage},
+    format::{ClearValue, Format, NumericType},
+    image::{view::ImageView, ImageDimensions},
     pipeline::{
         graphics::{
-            color_blend::{AttachmentBlend, BlendFactor, BlendOp, ColorBlendState},
+            color_blend::{AttachmentBlend, BlendFactor, BlendOp, ColorBlendState, LogicOp},
             input_assembly::{InputAssemblyState, PrimitiveTopology},
             rasterization::{PolygonMode, RasterizationState},
             vertex_input::Vertex,
@@ -36,19 +45,195 @@ use vulkano::{
     render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass},
     sampler::{Sampler, SamplerCreateInfo},
     shader::{ShaderModule, SpecializationConstants},
-    sync::GpuFuture,
     DeviceSize,
 };
 
 pub fn generate_data<Type>(length: u32) -> impl ExactSizeIterator<Item = Type>
 where
-    Type: NumCast,
+    Type: NumCast + CpuReducible,
 {
-    let mut rng = Pcg64Mcg::seed_from_u64(42);
+    DataGenerator::default().generate(length)
+}
+
+/// The values [DataGenerator] yields. Values have to be representable by the
+/// generated type, e.g. [DataDistribution::Normal] and
+/// [DataDistribution::Cancellation] are negative for unsigned types.
+///
+/// Parsed from and printed as e.g. `uniform:0:10`, `normal:0:1`, `ascending`,
+/// `constant:3`, `identity:min`, `sparse:0.01` or `cancellation`
+#[derive(Derivative)]
+#[derivative(Default)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DataDistribution {
+    /// Integers in `low..high`, 0..10 by default
+    #[derivative(Default)]
+    Uniform {
+        #[derivative(Default(value = "0"))]
+        low: i32,
+        #[derivative(Default(value = "10"))]
+        high: i32,
+    },
+    Normal {
+        mean: f64,
+        std_dev: f64,
+    },
+    /// The index of every value, overflows the sum of small types
+    Ascending,
+    /// `length - 1` down to zero
+    Descending,
+    Constant(f64),
+    /// The identity of `op`, the result is the identity as well
+    Identity(ReduceOp),
+    /// Integers in `1..10` with probability `density`, zero otherwise
+    Sparse {
+        density: f64,
+    },
+    /// Integers in `0..10` mixed with ±2^26 in equal parts. Every value is
+    /// exact in f32, but naive float sums lose the small ones next to the
+    /// large partial sums
+    Cancellation,
+}
+
+impl DataDistribution {
+    fn value<Type>(self, rng: &mut Pcg64Mcg, index: u32, length: u32) -> Type
+    where
+        Type: NumCast + CpuReducible,
+    {
+        let cast = |value: f64| {
+            Type::from(value)
+                .unwrap_or_else(|| panic!("{self} yielded {value}, not a {}", type_name::<Type>()))
+        };
+
+        match self {
+            DataDistribution::Uniform { low, high } => cast(rng.gen_range(low..high) as f64),
+            DataDistribution::Normal { mean, std_dev } => {
+                // Box-Muller, the first sample must not be zero
+                let u1 = 1.0 - rng.gen::<f64>();
+                let u2 = rng.gen::<f64>();
+                cast(mean + std_dev * (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos())
+            },
+            DataDistribution::Ascending => cast(index as f64),
+            DataDistribution::Descending => cast((length - 1 - index) as f64),
+            DataDistribution::Constant(value) => cast(value),
+            DataDistribution::Identity(op) => op.identity(),
+            DataDistribution::Sparse { density } => match rng.gen_bool(density) {
+                true => cast(rng.gen_range(1..10) as f64),
+                false => Type::zero(),
+            },
+            DataDistribution::Cancellation => match rng.gen_range(0..4) {
+                0 => cast((1 << 26) as f64),
+                1 => cast(-(1 << 26) as f64),
+                _ => cast(rng.gen_range(0..10) as f64),
+            },
+        }
+    }
+}
+
+impl Display for DataDistribution {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataDistribution::Uniform { low, high } => write!(f, "uniform:{low}:{high}"),
+            DataDistribution::Normal { mean, std_dev } => write!(f, "normal:{mean}:{std_dev}"),
+            DataDistribution::Ascending => write!(f, "ascending"),
+            DataDistribution::Descending => write!(f, "descending"),
+            DataDistribution::Constant(value) => write!(f, "constant:{value}"),
+            DataDistribution::Identity(op) => write!(f, "identity:{}", op.name()),
+            DataDistribution::Sparse { density } => write!(f, "sparse:{density}"),
+            DataDistribution::Cancellation => write!(f, "cancellation"),
+        }
+    }
+}
+
+impl FromStr for DataDistribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn number<T: FromStr>(value: Option<&str>, s: &str) -> Result<T, String> {
+            value
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| format!("Invalid data distribution {s:?}"))
+        }
+
+        let mut parts = s.split(':');
+        let distribution = match parts.next().unwrap_or_default() {
+            "uniform" => DataDistribution::Uniform {
+                low: number(parts.next(), s)?,
+                high: number(parts.next(), s)?,
+            },
+            "normal" => DataDistribution::Normal {
+                mean: number(parts.next(), s)?,
+                std_dev: number(parts.next(), s)?,
+            },
+            "ascending" => DataDistribution::Ascending,
+            "descending" => DataDistribution::Descending,
+            "constant" => DataDistribution::Constant(number(parts.next(), s)?),
+            "identity" => DataDistribution::Identity(match parts.next() {
+                Some("sum") => ReduceOp::Sum,
+                Some("min") => ReduceOp::Min,
+                _ => return Err(format!("Invalid data distribution {s:?}")),
+            }),
+            "sparse" => DataDistribution::Sparse {
+                density: number(parts.next(), s)?,
+            },
+            "cancellation" => DataDistribution::Cancellation,
+            _ => return Err(format!("Unknown data distribution {s:?}")),
+        };
+
+        match parts.next() {
+            Some(_) => Err(format!("Invalid data distribution {s:?}")),
+            None => Ok(distribution),
+        }
+    }
+}
+
+/// Reproducible input data, the default is what [generate_data] yields
+#[derive(Derivative)]
+#[derivative(Default)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DataGenerator {
+    pub distribution: DataDistribution,
+    #[derivative(Default(value = "42"))]
+    pub seed: u64,
+}
+
+impl DataGenerator {
+    /// The default, overridden by the `DATA_DISTRIBUTION` and `DATA_SEED`
+    /// environment variables, for benchmarks
+    pub fn from_env() -> Self {
+        let mut generator = Self::default();
+
+        if let Ok(distribution) = std::env::var("DATA_DISTRIBUTION") {
+            generator.distribution = distribution.parse().unwrap_or_else(|e| {
+                panic!(
+                    "DATA_DISTRIBUTION: {e}, expected `uniform:low:high`, `normal:mean:std_dev`, \
+                     `ascending`, `descending`, `constant:value`, `identity:sum|min`, \
+                     `sparse:density` or `cancellation`"
+                )
+            });
+        }
+        if let Ok(seed) = std::env::var("DATA_SEED") {
+            generator.seed = seed.parse().unwrap_or_else(|e| {
+                panic!("DATA_SEED {seed:?}: {e}, expected an unsigned 64-bit integer")
+            });
+        }
+
+        generator
+    }
+
+    pub fn generate<Type>(self, length: u32) -> impl ExactSizeIterator<Item = Type>
+    where
+        Type: NumCast + CpuReducible,
+    {
+        let mut rng = Pcg64Mcg::seed_from_u64(self.seed);
+
+        (0..length).map(move |index| self.distribution.value(&mut rng, index, length))
+    }
+}
 
-    (1u32..(length + 1))
-        .map(move |_| rng.gen_range(0..10))
-        .map(|n| Type::from(n).unwrap())
+impl Display for DataGenerator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} seed {}", self.distribution, self.seed)
+    }
 }
 
 
@@ -58,20 +243,20 @@ pub struct ExecuteUtil<Type> {
 
     render_pass: Arc<RenderPass>,
     pipeline: Arc<GraphicsPipeline>,
-    set: Arc<PersistentDescriptorSet>,
+    /// Drawn one after another, each into its own output buffer
+    chunks: Vec<InputChunk>,
 
-    instance_id: u32,
+    /// Number of data layers along z, passed to the shader as instance index
+    layers: u32,
 
     expected_result: Type,
 
     accumulate: Box<dyn Fn(Type, Type) -> Type>,
-
-    data_size: u32,
 }
 
 
 #[derive(Derivative)]
-#[derivative(Default, Clone)]
+#[derivative(Default, Clone, Debug)]
 pub struct ExecuteParameters {
     #[derivative(Default(value = "1"))]
     pub vectorization_factor: u32,
@@ -87,6 +272,80 @@ pub struct ExecuteParameters {
 
     pub blend: Option<BlendMethod>,
     pub use_instances_and_blend: bool,
+
+    /// Accumulate the partial results with the reducers of [crate::cpu],
+    /// picked by size, instead of folding them with the accumulate closure
+    pub final_accumulation: Option<ReduceOp>,
+
+    /// Input of the setup functions that don't take data
+    pub data: DataGenerator,
+}
+
+
+/// Everything besides shader and specialization constants that is baked into a
+/// graphics pipeline
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct GraphicsPipelineState {
+    pub render_pass: RenderPassKey,
+    pub quad_method: QuadMethod,
+    pub blend: Option<BlendMethod>,
+}
+
+impl ExecuteParameters {
+    /// Framebuffer covering `data_size` values along x, rounded up, with
+    /// [OutputKind::partials_per_fragment] of them per fragment
+    pub fn viewport_size(&self, data_size: Vector2<u32>) -> Vector2<u32> {
+        Vector2::new(
+            data_size
+                .x
+                .div_ceil(self.framebuffer_y * self.output.partials_per_fragment()),
+            self.framebuffer_y,
+        )
+    }
+
+    /// The `TEXTURE_SIZE_X`/`TEXTURE_SIZE_Y` the shader indexes the data with,
+    /// counting every packed partial result as its own column
+    pub fn texture_size(&self, data_size: Vector2<u32>) -> Vector2<u32> {
+        let viewport_size = self.viewport_size(data_size);
+
+        Vector2::new(
+            viewport_size.x * self.output.partials_per_fragment(),
+            viewport_size.y,
+        )
+    }
+
+    /// Replaces [OutputKind::BlendAttachment] by the attachment format for
+    /// `Type` and checks that the device can blend into the output
+    fn resolve_blend_output<Type: NumCast>(mut self, vulkan: &VulkanData) -> Self {
+        let Some(blend) = self.blend.filter(|_| self.use_instances_and_blend) else {
+            return self;
+        };
+
+        if let OutputKind::BlendAttachment = self.output {
+            let format = blend
+                .attachment_format::<Type>(vulkan)
+                .unwrap_or_else(|reason| panic!("{reason}"));
+            self.output = OutputKind::RenderAttachment(format);
+            self.clear_value = blend.identity(format).unwrap();
+        }
+
+        if let Some(format) = self.output.format() {
+            assert!(
+                blend.supports(vulkan, format),
+                "{format:?} attachments can't {blend:?} on this device"
+            );
+        }
+
+        self
+    }
+
+    fn to_pipeline_state(&self) -> GraphicsPipelineState {
+        GraphicsPipelineState {
+            render_pass: self.output.to_render_pass_key(),
+            quad_method: self.quad_method,
+            blend: self.blend,
+        }
+    }
 }
 
 
@@ -95,9 +354,146 @@ pub struct ExecuteParameters {
 pub enum BlendMethod {
     Add,
     Min,
+    Max,
+    /// Logic ops replace blending and only apply to integer attachments
+    And,
+    Or,
+    Xor,
 }
 
 impl BlendMethod {
+    pub fn logic_op(self) -> Option<LogicOp> {
+        match self {
+            BlendMethod::And => Some(LogicOp::And),
+            BlendMethod::Or => Some(LogicOp::Or),
+            BlendMethod::Xor => Some(LogicOp::Xor),
+            BlendMethod::Add | BlendMethod::Min | BlendMethod::Max => None,
+        }
+    }
+
+    pub fn color_blend_state(self, attachments: u32) -> ColorBlendState {
+        let state = ColorBlendState::new(attachments);
+
+        match self.logic_op() {
+            Some(logic_op) => state.logic_op(logic_op),
+            None => state.blend(self.to_vulkano()),
+        }
+    }
+
+    /// Whether attachments of `format` can accumulate with this method
+    pub fn supports(self, vulkan: &VulkanData, format: Format) -> bool {
+        let capability = vulkan.format_capability(format);
+
+        match self.logic_op() {
+            Some(_) => {
+                vulkan.supports_logic_op
+                    && capability.color_attachment
+                    && matches!(
+                        format.type_color(),
+                        Some(NumericType::UINT | NumericType::SINT)
+                    )
+            },
+            None => capability.blend,
+        }
+    }
+
+    /// The single component format holding `Type` bit for bit that this
+    /// method can accumulate in on this device, or why there is none
+    pub fn attachment_format<Type: NumCast>(self, vulkan: &VulkanData) -> Result<Format, String> {
+        let format = self.format_holding::<Type>()?;
+
+        if self.supports(vulkan, format) {
+            Ok(format)
+        } else {
+            Err(format!(
+                "{format:?} attachments holding {} can't {self:?} on this device",
+                type_name::<Type>()
+            ))
+        }
+    }
+
+    /// The format results are read back from as `Type`. Blending converts to
+    /// floating point, so only logic ops work on integers, and 64-bit integers
+    /// whose identity a clear value can't hold are left out
+    fn format_holding<Type: NumCast>(self) -> Result<Format, String> {
+        // Told apart by what the type can represent
+        let float = Type::from(0.5).and_then(|v: Type| v.to_f64()) == Some(0.5);
+        let signed = Type::from(-1).is_some();
+
+        if float == self.logic_op().is_some() {
+            return Err(if float {
+                format!("{self:?} is a logic op and can't accumulate floats")
+            } else {
+                format!(
+                    "{self:?} blends in floating point, {} can only use logic ops",
+                    type_name::<Type>()
+                )
+            });
+        }
+
+        let format = match (std::mem::size_of::<Type>(), float, signed) {
+            (2, true, _) => Format::R16_SFLOAT,
+            (4, true, _) => Format::R32_SFLOAT,
+            (8, true, _) => Format::R64_SFLOAT,
+            (1, false, false) => Format::R8_UINT,
+            (2, false, false) => Format::R16_UINT,
+            (4, false, false) => Format::R32_UINT,
+            (8, false, false) => Format::R64_UINT,
+            (1, false, true) => Format::R8_SINT,
+            (2, false, true) => Format::R16_SINT,
+            (4, false, true) => Format::R32_SINT,
+            (8, false, true) => Format::R64_SINT,
+            _ => {
+                return Err(format!(
+                    "No attachment format holds {}",
+                    type_name::<Type>()
+                ))
+            },
+        };
+
+        match self.identity(format) {
+            Some(_) => Ok(format),
+            None => Err(format!(
+                "A clear value can't hold the {self:?} identity of {format:?}"
+            )),
+        }
+    }
+
+    /// The clear value of `format` that leaves the accumulated result
+    /// unchanged, derived from the width of its components. `None` if a
+    /// 32-bit clear value can't hold it
+    pub fn identity(self, format: Format) -> Option<ClearValue> {
+        let bits = format.components()[0] as u32;
+        let fits = bits <= 32;
+
+        Some(match format.type_color() {
+            Some(NumericType::UINT) => ClearValue::Uint(
+                [match self {
+                    BlendMethod::Min | BlendMethod::And if fits => u32::MAX >> (32 - bits),
+                    BlendMethod::Min | BlendMethod::And => return None,
+                    BlendMethod::Add | BlendMethod::Max | BlendMethod::Or | BlendMethod::Xor => 0,
+                }; 4],
+            ),
+            Some(NumericType::SINT) => ClearValue::Int(
+                [match self {
+                    BlendMethod::Min if fits => i32::MAX >> (32 - bits),
+                    BlendMethod::Max if fits => i32::MIN >> (32 - bits),
+                    BlendMethod::And if fits => -1,
+                    BlendMethod::Min | BlendMethod::Max | BlendMethod::And => return None,
+                    BlendMethod::Add | BlendMethod::Or | BlendMethod::Xor => 0,
+                }; 4],
+            ),
+            _ => ClearValue::Float(
+                [match self {
+                    BlendMethod::Min => f32::INFINITY,
+                    BlendMethod::Max => f32::NEG_INFINITY,
+                    _ => 0.0,
+                }; 4],
+            ),
+        })
+    }
+
+    /// Panics for logic ops, see [BlendMethod::color_blend_state]
     pub fn to_vulkano(self) -> AttachmentBlend {
         let mut base = AttachmentBlend {
             color_op: BlendOp::ReverseSubtract,
@@ -117,6 +513,13 @@ impl BlendMethod {
                 base.color_op = BlendOp::Min;
                 base.alpha_op = BlendOp::Min;
             },
+            BlendMethod::Max => {
+                base.color_op = BlendOp::Max;
+                base.alpha_op = BlendOp::Max;
+            },
+            BlendMethod::And | BlendMethod::Or | BlendMethod::Xor => {
+                panic!("{self:?} is a logic op, not a blend")
+            },
         }
 
         base
@@ -128,6 +531,18 @@ impl BlendMethod {
 #[derive(Copy, Clone, Debug)]
 pub enum OutputKind {
     RenderAttachment(Format),
+    /// A single attachment in the format [BlendMethod::attachment_format]
+    /// picks for the element type and device, cleared to
+    /// [BlendMethod::identity]. Becomes a [OutputKind::RenderAttachment]
+    /// during setup
+    BlendAttachment,
+    /// Every fragment writes one partial result per component of `format` to
+    /// each of `attachments` color attachments, see
+    /// `shaders/pluggable/writer/attachment_packed.glsl`
+    PackedAttachments {
+        format: Format,
+        attachments: u32,
+    },
     #[derivative(Default)]
     Buffer,
 }
@@ -136,12 +551,50 @@ impl OutputKind {
     #[allow(non_upper_case_globals)]
     pub const Attachment: Self = Self::RenderAttachment(Format::R32_UINT);
 
+    pub fn is_attachment(self) -> bool {
+        !matches!(self, OutputKind::Buffer)
+    }
+
+    pub fn format(self) -> Option<Format> {
+        match self {
+            OutputKind::RenderAttachment(format) | OutputKind::PackedAttachments { format, .. } => {
+                Some(format)
+            },
+            OutputKind::BlendAttachment | OutputKind::Buffer => None,
+        }
+    }
+
+    pub fn attachment_count(self) -> u32 {
+        match self {
+            OutputKind::RenderAttachment(_) | OutputKind::BlendAttachment => 1,
+            OutputKind::PackedAttachments { attachments, .. } => attachments,
+            OutputKind::Buffer => 0,
+        }
+    }
+
+    /// Number of partial results every fragment writes
+    pub fn partials_per_fragment(self) -> u32 {
+        match self {
+            OutputKind::PackedAttachments {
+                format,
+                attachments,
+            } => {
+                let components = format.components().iter().filter(|&&bits| bits > 0).count();
+                components as u32 * attachments
+            },
+            OutputKind::RenderAttachment(_) | OutputKind::BlendAttachment | OutputKind::Buffer => 1,
+        }
+    }
+
     fn to_render_pass_key(self) -> RenderPassKey {
+        assert!(
+            !matches!(self, OutputKind::BlendAttachment),
+            "The blend attachment format is resolved during setup"
+        );
+
         RenderPassKey {
-            format: match self {
-                OutputKind::RenderAttachment(format) => Some(format),
-                OutputKind::Buffer => None,
-            },
+            format: self.format(),
+            attachments: self.attachment_count(),
         }
     }
 }
@@ -178,12 +631,12 @@ impl QuadMethod {
 
 impl<Type> ExecuteUtil<Type>
 where
-    Type: Copy + NumCast + Pod + BufferContents + PartialEq + Zero + Debug + Sum,
+    Type: Copy + NumCast + Pod + BufferContents + PartialEq + Zero + Debug + Sum + CpuReducible,
 {
     #[inline(always)]
     fn generic_setup<SC, INIT, Acc>(
         vulkan: &mut VulkanData,
-        fs: &ShaderModule,
+        fs: &Arc<ShaderModule>,
         sc: SC,
         params: ExecuteParameters,
         data_size: u32,
@@ -200,47 +653,57 @@ where
             &Arc<GraphicsPipeline>,
         ) -> (Vector2<u32>, Arc<PersistentDescriptorSet>, Type),
     {
+        let params = params.resolve_blend_output::<Type>(vulkan);
+        if let Some(format) = params.output.format() {
+            assert!(
+                vulkan.format_capability(format).color_attachment,
+                "{format:?} can't be a color attachment on this device"
+            );
+        }
         let render_pass = vulkan.create_render_pass(params.output.to_render_pass_key());
 
-        let vert = vs::load(vulkan.device.clone()).unwrap();
-
-        let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
-        let mut pipeline = GraphicsPipeline::start()
-            .vertex_input_state(MVertex::per_vertex())
-            .vertex_shader(
-                vert.entry_point("main").unwrap(),
-                vs::SpecializationConstants {
-                    DATA_SCALE: if params.quad_method == QuadMethod::large_triangle {
-                        2
-                    } else {
-                        1
+        let key = PipelineKey::new(vulkan.shader_id(fs), &sc, params.to_pipeline_state());
+        let pipeline = vulkan.graphics_pipeline(key, |vulkan| {
+            let vert = vulkan.load_shader(vs::load);
+
+            let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
+            let mut pipeline = GraphicsPipeline::start()
+                .vertex_input_state(MVertex::per_vertex())
+                .vertex_shader(
+                    vert.entry_point("main").unwrap(),
+                    vs::SpecializationConstants {
+                        DATA_SCALE: if params.quad_method == QuadMethod::large_triangle {
+                            2
+                        } else {
+                            1
+                        },
                     },
-                },
-            )
-            .rasterization_state(RasterizationState::new().polygon_mode({
-                #[cfg(feature = "fill_rectangle")]
-                if params.quad_method == QuadMethod::fill_rectangle {
-                    PolygonMode::FillRectangle
-                } else {
+                )
+                .rasterization_state(RasterizationState::new().polygon_mode({
+                    #[cfg(feature = "fill_rectangle")]
+                    if params.quad_method == QuadMethod::fill_rectangle {
+                        PolygonMode::FillRectangle
+                    } else {
+                        PolygonMode::Fill
+                    }
+                    #[cfg(not(feature = "fill_rectangle"))]
                     PolygonMode::Fill
-                }
-                #[cfg(not(feature = "fill_rectangle"))]
-                PolygonMode::Fill
-            }))
-            .input_assembly_state(
-                InputAssemblyState::new().topology(PrimitiveTopology::TriangleStrip),
-            )
-            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
-            .fragment_shader(fs.entry_point("main").unwrap(), sc);
-        if let Some(blend) = params.blend {
-            pipeline = pipeline.color_blend_state(
-                ColorBlendState::new(subpass.num_color_attachments()).blend(blend.to_vulkano()),
-            );
-        }
-        let pipeline = pipeline
-            .render_pass(subpass)
-            .build(vulkan.device.clone())
-            .unwrap();
+                }))
+                .input_assembly_state(
+                    InputAssemblyState::new().topology(PrimitiveTopology::TriangleStrip),
+                )
+                .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+                .fragment_shader(fs.entry_point("main").unwrap(), sc);
+            if let Some(blend) = params.blend {
+                pipeline = pipeline
+                    .color_blend_state(blend.color_blend_state(subpass.num_color_attachments()));
+            }
+            pipeline
+                .render_pass(subpass)
+                .build_with_cache(vulkan.pipeline_cache.clone())
+                .build(vulkan.device.clone())
+                .unwrap()
+        });
 
         let (viewport_size, set, expected_result) = specialized_init(vulkan, &pipeline);
 
@@ -248,12 +711,11 @@ where
             viewport_size,
             render_pass,
             pipeline,
-            set,
-            instance_id: 1,
+            chunks: vec![InputChunk { set, data_size }],
+            layers: 1,
             expected_result,
             parameters: params,
             accumulate: Box::new(accumulate),
-            data_size,
         }
     }
 
@@ -261,7 +723,7 @@ where
     pub fn setup_storage_buffer<SC, Acc>(
         vulkan: &mut VulkanData,
         data_size: Vector2<u32>,
-        fs: &ShaderModule,
+        fs: &Arc<ShaderModule>,
         sc: SC,
         params: ExecuteParameters,
 
@@ -271,33 +733,55 @@ where
         SC: SpecializationConstants,
         Acc: 'static + Fn(Type, Type) -> Type,
     {
-        assert_eq!(data_size.x % params.framebuffer_y, 0);
+        let generated_data = params
+            .data
+            .generate(data_size.x * data_size.y)
+            .collect_vec();
 
-        let total = data_size.x * data_size.y;
-        let generated_data = generate_data(total).collect_vec();
-        let expected = generated_data.iter().copied().reduce(&accumulate).unwrap();
+        Self::setup_storage_buffer_from_data(
+            vulkan,
+            generated_data,
+            data_size,
+            fs,
+            sc,
+            params,
+            accumulate,
+        )
+    }
+
+    #[inline(always)]
+    pub fn setup_storage_buffer_from_data<SC, Acc>(
+        vulkan: &mut VulkanData,
+        mut data: Vec<Type>,
+        data_size: Vector2<u32>,
+        fs: &Arc<ShaderModule>,
+        sc: SC,
+        params: ExecuteParameters,
+
+        accumulate: Acc,
+    ) -> Self
+    where
+        SC: SpecializationConstants,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        let len = data.len();
+        let expected = expected_result(&data, &accumulate, params.final_accumulation);
+        pad_to_vectors(&mut data, params.vectorization_factor);
+
+        // Rounded up, the fragments past `len` are out of bounds
+        let viewport_size = params.viewport_size(data_size);
 
         let mut executor = Self::generic_setup(
             vulkan,
             fs,
             sc,
             params.clone(),
-            total,
+            len as u32,
             accumulate,
             move |vulkan, pipeline| {
                 let mut command_buffer = vulkan.create_command_buffer();
-                let data = vulkan
-                    .create_storage_buffer(&mut command_buffer, generated_data.iter().copied());
-
-                command_buffer
-                    .build()
-                    .unwrap()
-                    .execute(vulkan.queue.clone())
-                    .unwrap()
-                    .then_signal_fence_and_flush()
-                    .unwrap()
-                    .wait(None)
-                    .unwrap();
+                let data = vulkan.create_storage_buffer(&mut command_buffer, data.iter().copied());
+                vulkan.submit_and_wait(command_buffer);
 
                 let set = PersistentDescriptorSet::new(
                     &vulkan.descriptor_set_allocator,
@@ -306,20 +790,15 @@ where
                 )
                 .unwrap();
 
-                (
-                    Vector2::new(data_size.x / params.framebuffer_y, params.framebuffer_y),
-                    set,
-                    expected,
-                )
+                (viewport_size, set, expected)
             },
         );
 
-        assert_eq!(
-            data_size.y % params.vectorization_factor,
-            0,
-            "Dimension Y must be a multiple of the vectorization factor"
+        executor.layers = layer_count(
+            len,
+            viewport_size.x * viewport_size.y * params.output.partials_per_fragment(),
+            params.vectorization_factor,
         );
-        executor.instance_id = data_size.y / params.vectorization_factor;
 
         executor
     }
@@ -328,7 +807,7 @@ where
     pub fn setup_2d_sampler<SC, Acc>(
         vulkan: &mut VulkanData,
         data_size: Vector2<u32>,
-        fs: &ShaderModule,
+        fs: &Arc<ShaderModule>,
         sc: SC,
         params: ExecuteParameters,
 
@@ -338,9 +817,42 @@ where
         SC: SpecializationConstants,
         Acc: 'static + Fn(Type, Type) -> Type,
     {
+        let raw_data = params
+            .data
+            .generate(data_size.x * data_size.y)
+            .collect_vec();
+
+        Self::setup_2d_sampler_from_data(vulkan, raw_data, data_size, fs, sc, params, accumulate)
+    }
+
+    /// `raw_data` has to fill the whole `data_size` image
+    #[inline(always)]
+    pub fn setup_2d_sampler_from_data<SC, Acc>(
+        vulkan: &mut VulkanData,
+        raw_data: Vec<Type>,
+        data_size: Vector2<u32>,
+        fs: &Arc<ShaderModule>,
+        sc: SC,
+        params: ExecuteParameters,
+
+        accumulate: Acc,
+    ) -> Self
+    where
+        SC: SpecializationConstants,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        assert!(
+            vulkan.format_capability(Format::R32_UINT).sampled_image,
+            "R32_UINT images can't be sampled on this device"
+        );
+
         let total = data_size.x * data_size.y;
-        let raw_data = generate_data(total).collect_vec();
-        let expected = raw_data.iter().copied().reduce(&accumulate).unwrap();
+        assert_eq!(
+            raw_data.len(),
+            total as usize,
+            "The data has to fill {data_size:?}"
+        );
+        let expected = expected_result(&raw_data, &accumulate, params.final_accumulation);
 
         let mut executor = Self::generic_setup(
             vulkan,
@@ -359,16 +871,7 @@ where
                     raw_data.iter().copied(),
                     Format::R32_UINT,
                 );
-
-                command_buffer
-                    .build()
-                    .unwrap()
-                    .execute(vulkan.queue.clone())
-                    .unwrap()
-                    .then_signal_fence_and_flush()
-                    .unwrap()
-                    .wait(None)
-                    .unwrap();
+                vulkan.submit_and_wait(command_buffer);
 
                 let descriptor_set_allocator =
                     StandardDescriptorSetAllocator::new(vulkan.device.clone());
@@ -392,28 +895,246 @@ where
                 )
                 .unwrap();
 
-                (
-                    Vector2::new(data_size.x / params.framebuffer_y, params.framebuffer_y),
-                    set,
-                    expected,
-                )
+                (params.viewport_size(data_size), set, expected)
+            },
+        );
+
+        executor.layers = data_size.y;
+
+        executor
+    }
+
+    /// Reads the data from a 1D storage image holding every value
+    #[inline(always)]
+    pub fn setup_storage_image_1d<SC, Acc>(
+        vulkan: &mut VulkanData,
+        data_size: Vector2<u32>,
+        fs: &Arc<ShaderModule>,
+        sc: SC,
+        params: ExecuteParameters,
+
+        accumulate: Acc,
+    ) -> Self
+    where
+        SC: SpecializationConstants,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        let dimensions = ImageDimensions::Dim1d {
+            width: data_size.x * data_size.y,
+            array_layers: 1,
+        };
+
+        Self::setup_generated_input(
+            vulkan,
+            data_size,
+            fs,
+            sc,
+            params,
+            accumulate,
+            move |vulkan, pipeline, data| storage_image_input(vulkan, pipeline, dimensions, data),
+        )
+    }
+
+    /// Reads the data from a 2D storage image with one row per layer, like
+    /// [ExecuteUtil::setup_2d_sampler]
+    #[inline(always)]
+    pub fn setup_storage_image_2d<SC, Acc>(
+        vulkan: &mut VulkanData,
+        data_size: Vector2<u32>,
+        fs: &Arc<ShaderModule>,
+        sc: SC,
+        params: ExecuteParameters,
+
+        accumulate: Acc,
+    ) -> Self
+    where
+        SC: SpecializationConstants,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        let dimensions = ImageDimensions::Dim2d {
+            width: data_size.x,
+            height: data_size.y,
+            array_layers: 1,
+        };
+
+        Self::setup_generated_input(
+            vulkan,
+            data_size,
+            fs,
+            sc,
+            params,
+            accumulate,
+            move |vulkan, pipeline, data| storage_image_input(vulkan, pipeline, dimensions, data),
+        )
+    }
+
+    /// Reads the data from a uniform texel buffer of `R32_UINT` texels
+    #[inline(always)]
+    pub fn setup_uniform_texel_buffer<SC, Acc>(
+        vulkan: &mut VulkanData,
+        data_size: Vector2<u32>,
+        fs: &Arc<ShaderModule>,
+        sc: SC,
+        params: ExecuteParameters,
+
+        accumulate: Acc,
+    ) -> Self
+    where
+        SC: SpecializationConstants,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        Self::setup_generated_input(
+            vulkan,
+            data_size,
+            fs,
+            sc,
+            params,
+            accumulate,
+            |vulkan, pipeline, data| {
+                texel_buffer_input(vulkan, pipeline, BufferUsage::UNIFORM_TEXEL_BUFFER, data)
+            },
+        )
+    }
+
+    /// Reads the data from a storage texel buffer of `R32_UINT` texels
+    #[inline(always)]
+    pub fn setup_storage_texel_buffer<SC, Acc>(
+        vulkan: &mut VulkanData,
+        data_size: Vector2<u32>,
+        fs: &Arc<ShaderModule>,
+        sc: SC,
+        params: ExecuteParameters,
+
+        accumulate: Acc,
+    ) -> Self
+    where
+        SC: SpecializationConstants,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        Self::setup_generated_input(
+            vulkan,
+            data_size,
+            fs,
+            sc,
+            params,
+            accumulate,
+            |vulkan, pipeline, data| {
+                texel_buffer_input(vulkan, pipeline, BufferUsage::STORAGE_TEXEL_BUFFER, data)
+            },
+        )
+    }
+
+    /// Reads the data from a uniform buffer, drawing once per
+    /// [UNIFORM_CHUNK_SIZE] values. Only for [OutputKind::Buffer]
+    #[inline(always)]
+    pub fn setup_uniform_buffer<SC, Acc>(
+        vulkan: &mut VulkanData,
+        data_size: Vector2<u32>,
+        fs: &Arc<ShaderModule>,
+        sc: SC,
+        params: ExecuteParameters,
+
+        accumulate: Acc,
+    ) -> Self
+    where
+        SC: SpecializationConstants,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        assert!(
+            matches!(params.output, OutputKind::Buffer),
+            "Uniform buffer chunks need a buffer output"
+        );
+
+        let mut executor = Self::setup_generated_input(
+            vulkan,
+            data_size,
+            fs,
+            sc,
+            params,
+            accumulate,
+            |vulkan, pipeline, data| uniform_buffer_input(vulkan, pipeline, data),
+        );
+
+        let total = (data_size.x * data_size.y) as usize;
+        executor.layers = layer_count(
+            total.min(UNIFORM_CHUNK_SIZE),
+            executor.viewport_size.x
+                * executor.viewport_size.y
+                * executor.parameters.output.partials_per_fragment(),
+            1,
+        );
+
+        executor
+    }
+
+    /// Generates `data_size.x * data_size.y` values and uploads them with
+    /// `input`, one layer per row of `data_size`
+    #[inline(always)]
+    fn setup_generated_input<SC, Acc, F>(
+        vulkan: &mut VulkanData,
+        data_size: Vector2<u32>,
+        fs: &Arc<ShaderModule>,
+        sc: SC,
+        params: ExecuteParameters,
+
+        accumulate: Acc,
+        input: F,
+    ) -> Self
+    where
+        SC: SpecializationConstants,
+        Acc: 'static + Fn(Type, Type) -> Type,
+        F: FnOnce(&VulkanData, &Arc<GraphicsPipeline>, Vec<Type>) -> Vec<InputChunk>,
+    {
+        let total = data_size.x * data_size.y;
+        let raw_data = params.data.generate(total).collect_vec();
+        let expected = expected_result(&raw_data, &accumulate, params.final_accumulation);
+
+        let viewport_size = params.viewport_size(data_size);
+
+        let mut chunks = vec![];
+        let mut executor = Self::generic_setup(
+            vulkan,
+            fs,
+            sc,
+            params,
+            total,
+            accumulate,
+            |vulkan, pipeline| {
+                chunks = input(vulkan, pipeline, raw_data);
+
+                (viewport_size, chunks[0].set.clone(), expected)
             },
         );
 
-        executor.instance_id = data_size.y;
+        executor.chunks = chunks;
+        executor.layers = data_size.y;
 
         executor
     }
 
     #[inline(always)]
-    fn run_for_attachment(&mut self, vulkan: &mut VulkanData, format: Format) {
+    fn run_for_attachment(
+        &mut self,
+        vulkan: &mut VulkanData,
+        format: Format,
+        attachments: u32,
+    ) -> Type {
+        // Chunks would overwrite each other's fragments
+        let [chunk] = &self.chunks[..] else {
+            panic!("Attachment outputs take a single input chunk");
+        };
         let mut command_buffer = vulkan.create_command_buffer();
 
-        let target = vulkan.create_target_image(self.viewport_size, format);
+        let targets = (0..attachments)
+            .map(|_| vulkan.create_target_image(self.viewport_size, format))
+            .collect_vec();
         let framebuffer = Framebuffer::new(
             self.render_pass.clone(),
             FramebufferCreateInfo {
-                attachments: vec![ImageView::new_default(target.clone()).unwrap()],
+                attachments: targets
+                    .iter()
+                    .map(|target| ImageView::new_default(target.clone()).unwrap() as _)
+                    .collect(),
                 ..Default::default()
             },
         )
@@ -422,7 +1143,7 @@ where
         command_buffer
             .begin_render_pass(
                 RenderPassBeginInfo {
-                    clear_values: vec![Some(self.parameters.clear_value)],
+                    clear_values: vec![Some(self.parameters.clear_value); attachments as usize],
                     ..RenderPassBeginInfo::framebuffer(framebuffer)
                 },
                 SubpassContents::Inline,
@@ -440,15 +1161,15 @@ where
                 PipelineBindPoint::Graphics,
                 self.pipeline.layout().clone(),
                 0,
-                self.set.clone(),
+                chunk.set.clone(),
             )
             .bind_vertex_buffers(0, vulkan.vertex_buffer())
-            .push_constants(self.pipeline.layout().clone(), 0, self.data_size)
+            .push_constants(self.pipeline.layout().clone(), 0, chunk.data_size)
             .draw(
                 if self.parameters.quad_method == QuadMethod::two_triangles {vulkan.vertex_buffer().len() as _} else {3},
-                if self.parameters.use_instances_and_blend {self.instance_id} else {1},
+                if self.parameters.use_instances_and_blend {self.layers} else {1},
                 0,
-                if self.parameters.use_instances_and_blend {0} else {self.instance_id}
+                if self.parameters.use_instances_and_blend {0} else {self.layers}
             )
             .unwrap()
 
@@ -456,78 +1177,53 @@ where
             .end_render_pass()
             .unwrap();
 
-        let read_buffer: Subbuffer<[Type]> =
-            vulkan.download_image(&mut command_buffer, target.clone());
-
-        let future = command_buffer
-            .build()
-            .unwrap()
-            .execute(vulkan.queue.clone())
+        let read_buffers: Vec<Subbuffer<[Type]>> = targets
+            .into_iter()
+            .map(|target| vulkan.download_image(&mut command_buffer, target))
+            .collect();
+
+        vulkan.submit_and_wait(command_buffer);
+
+        // dbg!(&read_buffers[0].read().unwrap() as &[_]);
+
+        let result = read_buffers
+            .iter()
+            .map(|read_buffer| {
+                black_box(accumulate_on_cpu(
+                    read_buffer,
+                    &self.accumulate,
+                    self.parameters.final_accumulation,
+                ))
+            })
+            .reduce(|a, b| (self.accumulate)(a, b))
             .unwrap();
-        let fence = future.then_signal_fence_and_flush().unwrap();
-        fence.wait(None).unwrap();
-
-        // dbg!(&read_buffer.read().unwrap() as &[_]);
-
-        let result = black_box(
-            read_buffer
-                .read()
-                .unwrap()
-                .iter()
-                .copied()
-                .reduce(&self.accumulate)
-                .unwrap(),
-        );
         assert_eq!(result, self.expected_result);
         // dbg!(result, self.expected_result);
+        result
     }
 
+    /// Draws every input chunk into its own storage buffer and returns the
+    /// buffers holding the output
     #[inline(always)]
-    fn run_for_buffer(&mut self, vulkan: &mut VulkanData, separate_read_buffer: bool) {
+    fn draw_to_buffers(
+        &mut self,
+        vulkan: &mut VulkanData,
+        separate_read_buffer: bool,
+    ) -> Vec<Subbuffer<[Type]>> {
         let mut command_buffer = vulkan.create_command_buffer();
 
-        let target: Subbuffer<[Type]> = Buffer::new_slice(
-            &vulkan.memory_allocator,
-            BufferCreateInfo {
-                usage: BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_SRC,
-                ..Default::default()
-            },
-            AllocationCreateInfo {
-                usage: if separate_read_buffer {
-                    MemoryUsage::DeviceOnly
-                } else {
-                    MemoryUsage::Download
-                },
-                ..Default::default()
-            },
-            (self.viewport_size.x * self.viewport_size.y * self.parameters.vectorization_factor)
-                as DeviceSize,
-        )
-        .unwrap();
-        let read_buffer = if separate_read_buffer {
-            Buffer::new_slice(
-                &vulkan.memory_allocator,
-                BufferCreateInfo {
-                    usage: BufferUsage::TRANSFER_DST,
-                    ..Default::default()
-                },
-                AllocationCreateInfo {
-                    usage: MemoryUsage::Download,
-                    ..Default::default()
-                },
-                target.len(),
-            )
-            .unwrap()
-        } else {
-            target.clone()
-        };
-
-        let target_set = PersistentDescriptorSet::new(
-            &vulkan.descriptor_set_allocator,
-            self.pipeline.layout().set_layouts().get(0).unwrap().clone(),
-            [WriteDescriptorSet::buffer(0, target.clone())],
-        )
-        .unwrap();
+        let outputs = self
+            .chunks
+            .iter()
+            .map(|_| {
+                vulkan.create_output_buffer::<Type>(
+                    (self.viewport_size.x
+                        * self.viewport_size.y
+                        * self.parameters.vectorization_factor) as DeviceSize,
+                    separate_read_buffer,
+                )
+            })
+            .collect_vec();
 
         let framebuffer = Framebuffer::new(
             self.render_pass.clone(),
@@ -553,66 +1249,223 @@ where
                 depth_range: 0.0..1.0
             }))
             .bind_pipeline_graphics(self.pipeline.clone())
-            .bind_descriptor_sets(
-                PipelineBindPoint::Graphics,
-                self.pipeline.layout().clone(),
-                0,
-                self.set.clone(),
-            ).bind_descriptor_sets(
-                PipelineBindPoint::Graphics,
-                self.pipeline.layout().clone(),
-                1,
-                target_set,
-            )
-            .bind_vertex_buffers(0, vulkan.vertex_buffer())
-            .push_constants(self.pipeline.layout().clone(), 0, self.data_size)
-            .draw(if self.parameters.quad_method == QuadMethod::two_triangles {vulkan.vertex_buffer().len() as _} else {3}, 1, 0, self.instance_id)
-            .unwrap()
+            .bind_vertex_buffers(0, vulkan.vertex_buffer());
 
-            // End rendering
-            .end_render_pass()
+        for (chunk, (target, _)) in self.chunks.iter().zip(&outputs) {
+            let target_set = PersistentDescriptorSet::new(
+                &vulkan.descriptor_set_allocator,
+                self.pipeline.layout().set_layouts().get(0).unwrap().clone(),
+                [WriteDescriptorSet::buffer(0, target.clone())],
+            )
             .unwrap();
 
-        if separate_read_buffer {
             command_buffer
-                .copy_buffer(CopyBufferInfo::buffers(target, read_buffer.clone()))
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Graphics,
+                    self.pipeline.layout().clone(),
+                    0,
+                    chunk.set.clone(),
+                )
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Graphics,
+                    self.pipeline.layout().clone(),
+                    1,
+                    target_set,
+                )
+                .push_constants(self.pipeline.layout().clone(), 0, chunk.data_size)
+                .draw(
+                    if self.parameters.quad_method == QuadMethod::two_triangles {
+                        vulkan.vertex_buffer().len() as _
+                    } else {
+                        3
+                    },
+                    1,
+                    0,
+                    self.layers,
+                )
                 .unwrap();
         }
 
-        let future = command_buffer
-            .build()
-            .unwrap()
-            .execute(vulkan.queue.clone())
-            .unwrap();
-        let fence = future.then_signal_fence_and_flush().unwrap();
-        fence.wait(None).unwrap();
+        // End rendering
+        command_buffer.end_render_pass().unwrap();
+
+        if separate_read_buffer {
+            for (target, read_buffer) in &outputs {
+                command_buffer
+                    .copy_buffer(CopyBufferInfo::buffers(target.clone(), read_buffer.clone()))
+                    .unwrap();
+            }
+        }
 
-        // dbg!(&read_buffer.read().unwrap() as &[_]);
+        vulkan.submit_and_wait(command_buffer);
 
-        let result = black_box(
-            read_buffer
-                .read()
-                .unwrap()
-                .iter()
-                .copied()
-                .reduce(&self.accumulate)
-                .unwrap(),
-        );
+        outputs
+            .into_iter()
+            .map(|(_, read_buffer)| read_buffer)
+            .collect()
+    }
+
+    #[inline(always)]
+    fn run_for_buffer(&mut self, vulkan: &mut VulkanData, separate_read_buffer: bool) -> Type {
+        let read_buffers = self.draw_to_buffers(vulkan, separate_read_buffer);
+
+        let result = read_buffers
+            .iter()
+            .map(|read_buffer| {
+                black_box(accumulate_on_cpu(
+                    read_buffer,
+                    &self.accumulate,
+                    self.parameters.final_accumulation,
+                ))
+            })
+            .reduce(|a, b| (self.accumulate)(a, b))
+            .unwrap();
         // assert_eq!(result, self.expected_result);
+        result
+    }
+
+    /// The value every fragment wrote, without the final accumulation, one
+    /// chunk after the other. Only for [OutputKind::Buffer]
+    pub fn run_partials(&mut self, vulkan: &mut VulkanData) -> Vec<Type> {
+        assert!(
+            matches!(self.parameters.output, OutputKind::Buffer),
+            "Partial results need a buffer output"
+        );
+        self.draw_to_buffers(vulkan, true)
+            .iter()
+            .flat_map(|read_buffer| read_buffer.read().unwrap().to_vec())
+            .collect()
     }
 
     #[inline(always)]
-    pub fn run(&mut self, vulkan: &mut VulkanData, separate_read_buffer: bool) {
+    pub fn run(&mut self, vulkan: &mut VulkanData, separate_read_buffer: bool) -> Type {
         match self.parameters.output {
-            OutputKind::RenderAttachment(format) => self.run_for_attachment(vulkan, format),
+            OutputKind::RenderAttachment(format) => self.run_for_attachment(vulkan, format, 1),
+            OutputKind::PackedAttachments {
+                format,
+                attachments,
+            } => self.run_for_attachment(vulkan, format, attachments),
+            OutputKind::BlendAttachment => unreachable!("Resolved during setup"),
             OutputKind::Buffer => self.run_for_buffer(vulkan, separate_read_buffer),
         }
     }
 }
 
+impl<Type> Executor<Type> for ExecuteUtil<Type>
+where
+    Type: Copy + NumCast + Pod + BufferContents + PartialEq + Zero + Debug + Sum + CpuReducible,
+{
+    type Parameters = ExecuteParameters;
+    type Program = Arc<ShaderModule>;
+
+    /// Reads the data from a storage buffer, see
+    /// [ExecuteUtil::setup_storage_buffer_from_data]
+    fn setup<Acc>(
+        vulkan: &mut VulkanData,
+        program: &Self::Program,
+        data: Vec<Type>,
+        data_size: Vector2<u32>,
+        parameters: Self::Parameters,
+        accumulate: Acc,
+    ) -> Self
+    where
+        Self: Sized,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        let texture_size = parameters.texture_size(data_size);
+        let sc = PluggableConstants {
+            TEXTURE_SIZE_X: texture_size.x as _,
+            TEXTURE_SIZE_Y: texture_size.y as _,
+        };
+
+        Self::setup_storage_buffer_from_data(
+            vulkan, data, data_size, program, sc, parameters, accumulate,
+        )
+    }
+
+    fn run(&mut self, vulkan: &mut VulkanData, separate_read_buffer: bool) -> Type {
+        ExecuteUtil::run(self, vulkan, separate_read_buffer)
+    }
+
+    fn expected(&self) -> Type {
+        self.expected_result
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "graphics {:?} {:?} {}x{} layers {} vectorization {}{}",
+            self.parameters.output,
+            self.parameters.quad_method,
+            self.viewport_size.x,
+            self.viewport_size.y,
+            self.layers,
+            self.parameters.vectorization_factor,
+            match self.parameters.blend {
+                Some(blend) if self.parameters.use_instances_and_blend => {
+                    format!(" blend {:?}", blend)
+                },
+                _ => String::new(),
+            },
+        )
+    }
+}
+
 mod vs {
     vulkano_shaders::shader! {
         ty: "vertex",
         path: "shaders/basic.vs",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blend_identity_fits_component_width() {
+        assert!(matches!(
+            BlendMethod::And.identity(Format::R8_UINT),
+            Some(ClearValue::Uint([0xFF, ..]))
+        ));
+        assert!(matches!(
+            BlendMethod::And.identity(Format::R16_UINT),
+            Some(ClearValue::Uint([0xFFFF, ..]))
+        ));
+        assert!(matches!(
+            BlendMethod::And.identity(Format::R32_UINT),
+            Some(ClearValue::Uint([u32::MAX, ..]))
+        ));
+        assert!(matches!(
+            BlendMethod::Min.identity(Format::R8_SINT),
+            Some(ClearValue::Int([127, ..]))
+        ));
+        assert!(matches!(
+            BlendMethod::Max.identity(Format::R16_SINT),
+            Some(ClearValue::Int([-32768, ..]))
+        ));
+        assert!(BlendMethod::And.identity(Format::R64_UINT).is_none());
+        assert!(matches!(
+            BlendMethod::Xor.identity(Format::R64_UINT),
+            Some(ClearValue::Uint([0, ..]))
+        ));
+    }
+
+    #[test]
+    fn attachment_format_holds_the_type() {
+        assert_eq!(BlendMethod::And.format_holding::<u8>(), Ok(Format::R8_UINT));
+        assert_eq!(
+            BlendMethod::Min.format_holding::<f32>(),
+            Ok(Format::R32_SFLOAT)
+        );
+        assert!(BlendMethod::And.format_holding::<u64>().is_err());
+        assert_eq!(
+            BlendMethod::Or.format_holding::<u64>(),
+            Ok(Format::R64_UINT)
+        );
+
+        // Blending would convert the integers to floats
+        assert!(BlendMethod::Add.format_holding::<u32>().is_err());
+        assert!(BlendMethod::Max.format_holding::<i32>().is_err());
+        assert!(BlendMethod::Xor.format_holding::<f32>().is_err());
+    }
+}
@@ -1,42 +1,66 @@
-use crate::{execute_util::generate_data, vulkan_util::VulkanData};
+use crate::{
+    cpu::{CpuReducible, ReduceOp},
+    execute_util::DataGenerator,
+    executor::{
+        accumulate_on_cpu, expected_result, layer_count, pad_to_vectors, storage_image_input,
+        texel_buffer_input, uniform_buffer_input, ComputeConstants, Executor, InputChunk,
+        PluggableConstants, UNIFORM_CHUNK_SIZE,
+    },
+    vulkan_util::{PipelineKey, RawComputePipeline, SubgroupSupport, VulkanData},
+};
+use ash::vk;
 use bytemuck::Pod;
 use derivative::Derivative;
 use itertools::Itertools;
 use nalgebra::Vector2;
 use num::{NumCast, Zero};
-use std::{fmt::Debug, hint::black_box, iter::Sum, marker::PhantomData, sync::Arc};
+use std::{
+    fmt::Debug,
+    hint::black_box,
+    iter::{once, Sum},
+    marker::PhantomData,
+    ptr,
+    sync::Arc,
+};
 use vulkano::{
-    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
-    command_buffer::{CopyBufferInfo, PrimaryCommandBufferAbstract},
-    descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet},
-    memory::allocator::{AllocationCreateInfo, MemoryUsage},
+    buffer::{BufferContents, BufferUsage, Subbuffer},
+    command_buffer::CopyBufferInfo,
+    descriptor_set::{DescriptorSet, PersistentDescriptorSet, WriteDescriptorSet},
+    image::ImageDimensions,
     pipeline::{ComputePipeline, Pipeline, PipelineBindPoint},
-    shader::{ShaderModule, SpecializationConstants},
-    sync::GpuFuture,
-    DeviceSize,
+    shader::{ShaderModule, ShaderStages},
+    DeviceSize, VulkanObject,
 };
 
 pub struct ComputeExecuteUtil<Type> {
-    viewport_size: Vector2<u32>,
+    thread_count: u32,
     parameters: ComputeParameters,
 
     pipeline: Arc<ComputePipeline>,
-    set: Arc<PersistentDescriptorSet>,
+    /// `pipeline` built with the [SubgroupSizeRequest], if there is one
+    raw_pipeline: Option<Arc<RawComputePipeline>>,
+    /// Dispatched one after another, each into its own output buffer
+    chunks: Vec<InputChunk>,
 
-    instance_id: u32,
+    /// Number of data layers along z, passed to the shader as push constant
+    layers: u32,
 
     expected_result: Type,
 
     accumulate: Box<dyn Fn(Type, Type) -> Type>,
 
-    data_size: u32,
+    /// What the pipeline actually runs with, written by the subgroup writers
+    /// into binding 1 of set 1 during setup. Only for
+    /// [OutputModification::OnePerSubgroup]
+    subgroup_size: Option<u32>,
+    subgroup_size_buffer: Option<Subbuffer<[u32]>>,
 
     t: PhantomData<Type>,
 }
 
 #[derive(Derivative)]
-#[derivative(Default, Clone)]
-#[derive(Copy)]
+#[derivative(Default, Debug)]
+#[derive(Copy, Clone)]
 pub enum OutputModification {
     #[derivative(Default)]
     OneForOne,
@@ -45,13 +69,16 @@ pub enum OutputModification {
 
     OnePerSubgroup,
 
+    /// For the shared memory fallbacks of the subgroup writers
+    OnePerWorkgroup,
+
     /// Make the output buffer be oversized to compare how much the final
     /// accumulation costs
     FixedSize(DeviceSize),
 }
 
 #[derive(Derivative)]
-#[derivative(Default, Clone)]
+#[derivative(Default, Clone, Debug)]
 pub struct ComputeParameters {
     #[derivative(Default(value = "1"))]
     pub vectorization_factor: u32,
@@ -60,19 +87,69 @@ pub struct ComputeParameters {
 
     pub output: OutputModification,
 
+    /// Skip reading back the results, [ComputeExecuteUtil::run] then returns
+    /// zero
     pub skip_cpu_final_accumulation: bool,
 
     pub override_thread_count: Option<u32>,
+
+    /// Local size of the compute shader, passed as specialization constant.
+    /// It may not exceed [VulkanData::max_workgroup_size]
+    #[derivative(Default(value = "64"))]
+    pub workgroup_size: u32,
+
+    /// Accumulate the partial results with the reducers of [crate::cpu],
+    /// picked by size, instead of folding them with the accumulate closure
+    pub final_accumulation: Option<ReduceOp>,
+
+    pub subgroup_size: SubgroupSizeRequest,
+
+    /// Input of the setup functions that don't take data
+    pub data: DataGenerator,
 }
 
+/// The subgroup size a compute pipeline has to run with.
+///
+/// vulkano can't pass the request on to pipeline creation, so
+/// [ComputeExecuteUtil] builds and dispatches these pipelines with raw Vulkan,
+/// see [RawComputePipeline]
+#[derive(Derivative)]
+#[derivative(Default)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum SubgroupSizeRequest {
+    #[derivative(Default)]
+    Any,
+    /// Needs `subgroup_size_control` for compute shaders
+    Required(u32),
+    /// Every subgroup of a workgroup is fully populated, needs
+    /// `compute_full_subgroups` and a workgroup size that is a multiple of
+    /// the maximum subgroup size
+    Full,
+}
+
+/// Everything besides shader and specialization constants that is baked into a
+/// compute pipeline
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct ComputePipelineState {
+    pub subgroup_size: SubgroupSizeRequest,
+}
+
+/// Output buffer, read buffer and the descriptor set binding the output, for
+/// one input chunk
+type ChunkOutput<Type> = (
+    Subbuffer<[Type]>,
+    Subbuffer<[Type]>,
+    Arc<PersistentDescriptorSet>,
+);
+
 impl<Type> ComputeExecuteUtil<Type>
 where
-    Type: Copy + NumCast + Pod + BufferContents + PartialEq + Zero + Debug + Sum,
+    Type: Copy + NumCast + Pod + BufferContents + PartialEq + Zero + Debug + Sum + CpuReducible,
 {
     #[inline(always)]
     fn generic_setup<SC, Acc, INIT>(
         vulkan: &mut VulkanData,
-        cs: &ShaderModule,
+        cs: &Arc<ShaderModule>,
         sc: SC,
         parameters: ComputeParameters,
         data_size: u32,
@@ -81,44 +158,121 @@ where
         specialized_init: INIT,
     ) -> Self
     where
-        SC: SpecializationConstants,
+        SC: Into<ComputeConstants>,
         Acc: 'static + Fn(Type, Type) -> Type,
         INIT: FnOnce(
             &mut VulkanData,
             &Arc<ComputePipeline>,
-        ) -> (Vector2<u32>, Arc<PersistentDescriptorSet>, Type),
+        ) -> (u32, Arc<PersistentDescriptorSet>, Type),
     {
-        let pipeline = ComputePipeline::new(
-            vulkan.device.clone(),
-            cs.entry_point("main").unwrap(),
-            &sc,
-            None,
-            |_| {},
-        )
-        .unwrap();
+        assert!(
+            parameters.workgroup_size > 0
+                && parameters.workgroup_size <= vulkan.max_workgroup_size(),
+            "Workgroup size {} exceeds the device limit of {}",
+            parameters.workgroup_size,
+            vulkan.max_workgroup_size()
+        );
+
+        let subgroups = vulkan.subgroup_support();
+        if let OutputModification::OnePerSubgroup = parameters.output {
+            assert!(
+                subgroups.supports(ShaderStages::COMPUTE, SubgroupSupport::writer_operations()),
+                "The device lacks the subgroup operations of OnePerSubgroup shaders"
+            );
+        }
+        match parameters.subgroup_size {
+            SubgroupSizeRequest::Any => {},
+            SubgroupSizeRequest::Required(size) => {
+                assert!(
+                    subgroups
+                        .size_control_stages
+                        .contains(ShaderStages::COMPUTE)
+                        && subgroups.min_size <= Some(size)
+                        && Some(size) <= subgroups.max_size,
+                    "The device can't run compute shaders with subgroups of {size}, \
+                     supported are {:?} to {:?}",
+                    subgroups.min_size,
+                    subgroups.max_size
+                );
+                let max_subgroups = subgroups.max_workgroup_subgroups.unwrap();
+                assert!(
+                    parameters.workgroup_size <= size * max_subgroups,
+                    "Workgroups of {} don't fit into {max_subgroups} subgroups of {size}",
+                    parameters.workgroup_size
+                );
+            },
+            SubgroupSizeRequest::Full => {
+                assert!(
+                    subgroups.full_subgroups,
+                    "The device doesn't support compute_full_subgroups"
+                );
+                // Without a required size the driver may pick any size up to
+                // the maximum, which then has to fill the workgroup
+                let size = subgroups.max_size.or(subgroups.size).unwrap();
+                assert_eq!(
+                    parameters.workgroup_size % size,
+                    0,
+                    "Subgroups of up to {size} don't fill workgroups of {}",
+                    parameters.workgroup_size
+                );
+            },
+        }
 
-        let (viewport_size, set, expected_result) = specialized_init(vulkan, &pipeline);
+        let sc = ComputeConstants::new(sc, parameters.workgroup_size);
+        let key = PipelineKey::new(vulkan.shader_id(cs), &sc, ComputePipelineState::default());
+        let pipeline = vulkan.compute_pipeline(key, |vulkan| {
+            ComputePipeline::new(
+                vulkan.device.clone(),
+                cs.entry_point("main").unwrap(),
+                &sc,
+                Some(vulkan.pipeline_cache.clone()),
+                |_| {},
+            )
+            .unwrap()
+        });
+        let raw_pipeline = match parameters.subgroup_size {
+            SubgroupSizeRequest::Any => None,
+            subgroup_size => {
+                let key = PipelineKey::new(
+                    vulkan.shader_id(cs),
+                    &sc,
+                    ComputePipelineState { subgroup_size },
+                );
+                Some(vulkan.raw_compute_pipeline(key, &pipeline, cs, &sc))
+            },
+        };
 
-        Self {
-            viewport_size,
+        let (thread_count, set, expected_result) = specialized_init(vulkan, &pipeline);
+
+        let mut executor = Self {
+            thread_count,
             pipeline,
-            set,
-            instance_id: 1,
+            raw_pipeline,
+            chunks: vec![InputChunk { set, data_size }],
+            layers: 1,
             expected_result,
             parameters,
 
             accumulate: Box::new(accumulate),
 
-            data_size,
+            subgroup_size: None,
+            subgroup_size_buffer: None,
+
             t: Default::default(),
+        };
+
+        if let OutputModification::OnePerSubgroup = executor.parameters.output {
+            executor.measure_subgroup_size(vulkan);
         }
+
+        executor
     }
 
     #[inline(always)]
     pub fn setup_storage_buffer<SC, Acc>(
         vulkan: &mut VulkanData,
         data_size: Vector2<u32>,
-        fs: &ShaderModule,
+        fs: &Arc<ShaderModule>,
         sc: SC,
 
         parameters: ComputeParameters,
@@ -126,34 +280,51 @@ where
         accumulate: Acc,
     ) -> Self
     where
-        SC: SpecializationConstants,
+        SC: Into<ComputeConstants>,
         Acc: 'static + Fn(Type, Type) -> Type,
     {
-        let total = data_size.x * data_size.y;
-        let gen_data = generate_data(total).collect_vec();
-        let expected = gen_data.iter().copied().reduce(&accumulate).unwrap();
+        let gen_data = parameters
+            .data
+            .generate(data_size.x * data_size.y)
+            .collect_vec();
+
+        Self::setup_storage_buffer_from_data(
+            vulkan, gen_data, data_size, fs, sc, parameters, accumulate,
+        )
+    }
+
+    #[inline(always)]
+    pub fn setup_storage_buffer_from_data<SC, Acc>(
+        vulkan: &mut VulkanData,
+        mut data: Vec<Type>,
+        data_size: Vector2<u32>,
+        fs: &Arc<ShaderModule>,
+        sc: SC,
+
+        parameters: ComputeParameters,
+
+        accumulate: Acc,
+    ) -> Self
+    where
+        SC: Into<ComputeConstants>,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        let len = data.len();
+        let expected = expected_result(&data, &accumulate, parameters.final_accumulation);
+        pad_to_vectors(&mut data, parameters.vectorization_factor);
 
         let mut executor = Self::generic_setup(
             vulkan,
             fs,
             sc,
             parameters.clone(),
-            total,
+            len as u32,
             accumulate,
             move |vulkan, pipeline| {
                 let mut command_buffer = vulkan.create_command_buffer();
                 let data: Subbuffer<[Type]> =
-                    vulkan.create_storage_buffer(&mut command_buffer, gen_data.iter().copied());
-
-                command_buffer
-                    .build()
-                    .unwrap()
-                    .execute(vulkan.queue.clone())
-                    .unwrap()
-                    .then_signal_fence_and_flush()
-                    .unwrap()
-                    .wait(None)
-                    .unwrap();
+                    vulkan.create_storage_buffer(&mut command_buffer, data.iter().copied());
+                vulkan.submit_and_wait(command_buffer);
 
                 let set = PersistentDescriptorSet::new(
                     &vulkan.descriptor_set_allocator,
@@ -162,140 +333,568 @@ where
                 )
                 .unwrap();
 
-                (Vector2::new(data_size.x, 1), set, expected)
+                (data_size.x, set, expected)
             },
         );
 
-        assert_eq!(
-            executor.viewport_size.x % 64,
-            0,
-            "Dimension X must be a multiple of 64 because of workgroup sizes"
+        executor.layers = layer_count(len, data_size.x, parameters.vectorization_factor);
+
+        executor
+    }
+
+    /// Reads the data from a 1D storage image holding every value
+    #[inline(always)]
+    pub fn setup_storage_image_1d<SC, Acc>(
+        vulkan: &mut VulkanData,
+        data_size: Vector2<u32>,
+        cs: &Arc<ShaderModule>,
+        sc: SC,
+
+        parameters: ComputeParameters,
+
+        accumulate: Acc,
+    ) -> Self
+    where
+        SC: Into<ComputeConstants>,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        let dimensions = ImageDimensions::Dim1d {
+            width: data_size.x * data_size.y,
+            array_layers: 1,
+        };
+
+        Self::setup_generated_input(
+            vulkan,
+            data_size,
+            cs,
+            sc,
+            parameters,
+            accumulate,
+            move |vulkan, pipeline, data| storage_image_input(vulkan, pipeline, dimensions, data),
+        )
+    }
+
+    /// Reads the data from a 2D storage image with one row per layer
+    #[inline(always)]
+    pub fn setup_storage_image_2d<SC, Acc>(
+        vulkan: &mut VulkanData,
+        data_size: Vector2<u32>,
+        cs: &Arc<ShaderModule>,
+        sc: SC,
+
+        parameters: ComputeParameters,
+
+        accumulate: Acc,
+    ) -> Self
+    where
+        SC: Into<ComputeConstants>,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        let dimensions = ImageDimensions::Dim2d {
+            width: data_size.x,
+            height: data_size.y,
+            array_layers: 1,
+        };
+
+        Self::setup_generated_input(
+            vulkan,
+            data_size,
+            cs,
+            sc,
+            parameters,
+            accumulate,
+            move |vulkan, pipeline, data| storage_image_input(vulkan, pipeline, dimensions, data),
+        )
+    }
+
+    /// Reads the data from a uniform texel buffer of `R32_UINT` texels
+    #[inline(always)]
+    pub fn setup_uniform_texel_buffer<SC, Acc>(
+        vulkan: &mut VulkanData,
+        data_size: Vector2<u32>,
+        cs: &Arc<ShaderModule>,
+        sc: SC,
+
+        parameters: ComputeParameters,
+
+        accumulate: Acc,
+    ) -> Self
+    where
+        SC: Into<ComputeConstants>,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        Self::setup_generated_input(
+            vulkan,
+            data_size,
+            cs,
+            sc,
+            parameters,
+            accumulate,
+            |vulkan, pipeline, data| {
+                texel_buffer_input(vulkan, pipeline, BufferUsage::UNIFORM_TEXEL_BUFFER, data)
+            },
+        )
+    }
+
+    /// Reads the data from a storage texel buffer of `R32_UINT` texels
+    #[inline(always)]
+    pub fn setup_storage_texel_buffer<SC, Acc>(
+        vulkan: &mut VulkanData,
+        data_size: Vector2<u32>,
+        cs: &Arc<ShaderModule>,
+        sc: SC,
+
+        parameters: ComputeParameters,
+
+        accumulate: Acc,
+    ) -> Self
+    where
+        SC: Into<ComputeConstants>,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        Self::setup_generated_input(
+            vulkan,
+            data_size,
+            cs,
+            sc,
+            parameters,
+            accumulate,
+            |vulkan, pipeline, data| {
+                texel_buffer_input(vulkan, pipeline, BufferUsage::STORAGE_TEXEL_BUFFER, data)
+            },
+        )
+    }
+
+    /// Reads the data from a uniform buffer, dispatching once per
+    /// [UNIFORM_CHUNK_SIZE] values
+    #[inline(always)]
+    pub fn setup_uniform_buffer<SC, Acc>(
+        vulkan: &mut VulkanData,
+        data_size: Vector2<u32>,
+        cs: &Arc<ShaderModule>,
+        sc: SC,
+
+        parameters: ComputeParameters,
+
+        accumulate: Acc,
+    ) -> Self
+    where
+        SC: Into<ComputeConstants>,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        let mut executor = Self::setup_generated_input(
+            vulkan,
+            data_size,
+            cs,
+            sc,
+            parameters,
+            accumulate,
+            |vulkan, pipeline, data| uniform_buffer_input(vulkan, pipeline, data),
         );
 
-        assert_eq!(
-            data_size.y % parameters.vectorization_factor,
-            0,
-            "Dimension Y must be a multiple of the vectorization factor"
+        let total = (data_size.x * data_size.y) as usize;
+        executor.layers = layer_count(total.min(UNIFORM_CHUNK_SIZE), data_size.x, 1);
+
+        executor
+    }
+
+    /// Generates `data_size.x * data_size.y` values and uploads them with
+    /// `input`, one layer per row of `data_size`
+    #[inline(always)]
+    fn setup_generated_input<SC, Acc, F>(
+        vulkan: &mut VulkanData,
+        data_size: Vector2<u32>,
+        cs: &Arc<ShaderModule>,
+        sc: SC,
+
+        parameters: ComputeParameters,
+
+        accumulate: Acc,
+        input: F,
+    ) -> Self
+    where
+        SC: Into<ComputeConstants>,
+        Acc: 'static + Fn(Type, Type) -> Type,
+        F: FnOnce(&VulkanData, &Arc<ComputePipeline>, Vec<Type>) -> Vec<InputChunk>,
+    {
+        let total = data_size.x * data_size.y;
+        let raw_data = parameters.data.generate(total).collect_vec();
+        let expected = expected_result(&raw_data, &accumulate, parameters.final_accumulation);
+
+        let mut chunks = vec![];
+        let mut executor = Self::generic_setup(
+            vulkan,
+            cs,
+            sc,
+            parameters,
+            total,
+            accumulate,
+            |vulkan, pipeline| {
+                chunks = input(vulkan, pipeline, raw_data);
+
+                (data_size.x, chunks[0].set.clone(), expected)
+            },
         );
-        executor.instance_id = data_size.y / parameters.vectorization_factor;
+
+        executor.chunks = chunks;
+        executor.layers = data_size.y;
 
         executor
     }
 
+    /// Invocations per dispatch, whole workgroups with the surplus invocations
+    /// out of bounds
+    fn dispatch_thread_count(&self) -> u32 {
+        self.parameters
+            .override_thread_count
+            .unwrap_or(self.thread_count)
+            .next_multiple_of(self.parameters.workgroup_size)
+    }
+
+    /// Dispatches the shader once per input chunk and returns the buffers
+    /// holding the output
     #[inline(always)]
-    pub fn run(&mut self, vulkan: &mut VulkanData, separate_read_buffer: bool) {
+    fn dispatch(
+        &mut self,
+        vulkan: &mut VulkanData,
+        separate_read_buffer: bool,
+    ) -> Vec<Subbuffer<[Type]>> {
+        let thread_count = self.dispatch_thread_count();
+        let workgroup_size = self.parameters.workgroup_size;
+
+        let output_len = match self.parameters.output {
+            OutputModification::OneForOne => (thread_count) as DeviceSize,
+            OutputModification::SingleValue => 1,
+            OutputModification::OnePerSubgroup => {
+                ((thread_count) as DeviceSize).div_ceil(self.subgroup_size.unwrap() as DeviceSize)
+            },
+            OutputModification::OnePerWorkgroup => (thread_count / workgroup_size) as DeviceSize,
+            OutputModification::FixedSize(size) => size.max((thread_count) as DeviceSize),
+        } * (self.parameters.vectorization_factor as DeviceSize);
+
+        self.dispatch_into(vulkan, separate_read_buffer, thread_count, output_len)
+    }
+
+    /// Runs the pipeline once into an output large enough for any subgroup
+    /// size and reads back the size the subgroup writers report
+    fn measure_subgroup_size(&mut self, vulkan: &mut VulkanData) {
+        let (buffer, _) = vulkan.create_output_buffer::<u32>(1, false);
+        self.subgroup_size_buffer = Some(buffer.clone());
+
+        let thread_count = self.dispatch_thread_count();
+        let output_len =
+            thread_count as DeviceSize * self.parameters.vectorization_factor as DeviceSize;
+        self.dispatch_into(vulkan, false, thread_count, output_len);
+
+        let size = buffer.read().unwrap()[0];
+        match self.parameters.subgroup_size {
+            SubgroupSizeRequest::Any | SubgroupSizeRequest::Full => {},
+            SubgroupSizeRequest::Required(required) => assert_eq!(
+                size, required,
+                "The pipeline runs with subgroups of {size} instead of the required {required}"
+            ),
+        }
+
+        self.subgroup_size = Some(size);
+    }
+
+    fn dispatch_into(
+        &self,
+        vulkan: &mut VulkanData,
+        separate_read_buffer: bool,
+        thread_count: u32,
+        output_len: DeviceSize,
+    ) -> Vec<Subbuffer<[Type]>> {
+        let outputs = self
+            .chunks
+            .iter()
+            .map(|_| {
+                let (target, read_buffer) =
+                    vulkan.create_output_buffer::<Type>(output_len, separate_read_buffer);
+
+                let target_set = PersistentDescriptorSet::new(
+                    &vulkan.descriptor_set_allocator,
+                    self.pipeline.layout().set_layouts().get(1).unwrap().clone(),
+                    once(WriteDescriptorSet::buffer(0, target.clone())).chain(
+                        self.subgroup_size_buffer
+                            .clone()
+                            .map(|buffer| WriteDescriptorSet::buffer(1, buffer)),
+                    ),
+                )
+                .unwrap();
+
+                (target, read_buffer, target_set)
+            })
+            .collect_vec();
+
+        match &self.raw_pipeline {
+            Some(pipeline) => self.record_raw(
+                vulkan,
+                pipeline,
+                &outputs,
+                separate_read_buffer,
+                thread_count,
+            ),
+            None => self.record(vulkan, &outputs, separate_read_buffer, thread_count),
+        }
+
+        // println!("\n\n\n{:x?}\n", &read_buffer.read().unwrap() as &[_]);
+
+        outputs
+            .into_iter()
+            .map(|(_, read_buffer, _)| read_buffer)
+            .collect()
+    }
+
+    fn record(
+        &self,
+        vulkan: &VulkanData,
+        outputs: &[ChunkOutput<Type>],
+        separate_read_buffer: bool,
+        thread_count: u32,
+    ) {
         let mut command_buffer = vulkan.create_command_buffer();
+        let workgroup_size = self.parameters.workgroup_size;
 
-        let thread_count = self
-            .parameters
-            .override_thread_count
-            .unwrap_or(self.viewport_size.x);
-
-        let target: Subbuffer<[Type]> =
-            Buffer::new_slice(
-                &vulkan.memory_allocator,
-                BufferCreateInfo {
-                    usage: BufferUsage::STORAGE_BUFFER
-                        | BufferUsage::TRANSFER_SRC
-                        | BufferUsage::TRANSFER_DST,
-                    ..Default::default()
-                },
-                AllocationCreateInfo {
-                    usage: if separate_read_buffer {
-                        MemoryUsage::DeviceOnly
-                    } else {
-                        MemoryUsage::Download
-                    },
-                    ..Default::default()
-                },
-                match self.parameters.output {
-                    OutputModification::OneForOne => (thread_count) as DeviceSize,
-                    OutputModification::SingleValue => 1,
-                    OutputModification::OnePerSubgroup => ((thread_count) as DeviceSize)
-                        .div_ceil(vulkan.physical_device.properties().subgroup_size.unwrap()
-                            as DeviceSize),
-                    OutputModification::FixedSize(size) => size.max((thread_count) as DeviceSize),
-                } * (self.parameters.vectorization_factor as DeviceSize),
-            )
-            .unwrap();
-        let read_buffer = if separate_read_buffer {
-            Buffer::new_slice(
-                &vulkan.memory_allocator,
-                BufferCreateInfo {
-                    usage: BufferUsage::TRANSFER_DST,
-                    ..Default::default()
-                },
-                AllocationCreateInfo {
-                    usage: MemoryUsage::Download,
-                    ..Default::default()
-                },
-                target.len(),
-            )
-            .unwrap()
-        } else {
-            target.clone()
-        };
+        command_buffer.bind_pipeline_compute(self.pipeline.clone());
+
+        for (chunk, (target, read_buffer, target_set)) in self.chunks.iter().zip(outputs) {
+            if self.parameters.clear_buffer {
+                command_buffer
+                    .fill_buffer(target.clone().into_bytes().cast_aligned(), 0)
+                    .unwrap();
+            }
 
-        if self.parameters.clear_buffer {
             command_buffer
-                .fill_buffer(target.clone().into_bytes().cast_aligned(), 0)
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Compute,
+                    self.pipeline.layout().clone(),
+                    0,
+                    chunk.set.clone(),
+                )
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Compute,
+                    self.pipeline.layout().clone(),
+                    1,
+                    target_set.clone(),
+                )
+                .push_constants(self.pipeline.layout().clone(), 0, chunk.data_size)
+                .push_constants(self.pipeline.layout().clone(), 4, self.layers)
+                .dispatch([thread_count / workgroup_size, 1, 1])
                 .unwrap();
+
+            if separate_read_buffer {
+                command_buffer
+                    .copy_buffer(CopyBufferInfo::buffers(target.clone(), read_buffer.clone()))
+                    .unwrap();
+            }
         }
 
-        let target_set = PersistentDescriptorSet::new(
-            &vulkan.descriptor_set_allocator,
-            self.pipeline.layout().set_layouts().get(0).unwrap().clone(),
-            [WriteDescriptorSet::buffer(0, target.clone())],
-        )
-        .unwrap();
-
-        assert_eq!(thread_count % 64, 0);
-        command_buffer
-            .bind_pipeline_compute(self.pipeline.clone())
-            .bind_descriptor_sets(
-                PipelineBindPoint::Compute,
-                self.pipeline.layout().clone(),
-                0,
-                self.set.clone(),
-            )
-            .bind_descriptor_sets(
-                PipelineBindPoint::Compute,
-                self.pipeline.layout().clone(),
+        vulkan.submit_and_wait(command_buffer);
+    }
+
+    /// Like [ComputeExecuteUtil::record] for a [RawComputePipeline], with the
+    /// barriers vulkano would insert
+    fn record_raw(
+        &self,
+        vulkan: &VulkanData,
+        pipeline: &RawComputePipeline,
+        outputs: &[ChunkOutput<Type>],
+        separate_read_buffer: bool,
+        thread_count: u32,
+    ) {
+        let fns = vulkan.device.fns();
+        let layout = pipeline.layout().handle();
+        let workgroup_size = self.parameters.workgroup_size;
+
+        let barrier = |command_buffer, src_stage, src_access_mask, dst_stage, dst_access_mask| unsafe {
+            let memory_barrier = vk::MemoryBarrier {
+                src_access_mask,
+                dst_access_mask,
+                ..Default::default()
+            };
+            (fns.v1_0.cmd_pipeline_barrier)(
+                command_buffer,
+                src_stage,
+                dst_stage,
+                vk::DependencyFlags::empty(),
                 1,
-                target_set,
-            )
-            .push_constants(self.pipeline.layout().clone(), 0, self.data_size)
-            .push_constants(self.pipeline.layout().clone(), 4, self.instance_id)
-            .dispatch([thread_count / 64, 1, 1])
-            .unwrap();
+                &memory_barrier,
+                0,
+                ptr::null(),
+                0,
+                ptr::null(),
+            );
+        };
 
-        if separate_read_buffer {
-            command_buffer
-                .copy_buffer(CopyBufferInfo::buffers(target, read_buffer.clone()))
-                .unwrap();
+        // The buffers and descriptor sets live in `outputs` and `self` until
+        // the submission finished
+        unsafe {
+            vulkan.submit_raw_and_wait(|command_buffer| {
+                (fns.v1_0.cmd_bind_pipeline)(
+                    command_buffer,
+                    vk::PipelineBindPoint::COMPUTE,
+                    pipeline.handle(),
+                );
+
+                for (chunk, (target, read_buffer, target_set)) in self.chunks.iter().zip(outputs) {
+                    if self.parameters.clear_buffer {
+                        (fns.v1_0.cmd_fill_buffer)(
+                            command_buffer,
+                            target.buffer().handle(),
+                            target.offset(),
+                            target.size(),
+                            0,
+                        );
+                        barrier(
+                            command_buffer,
+                            vk::PipelineStageFlags::TRANSFER,
+                            vk::AccessFlags::TRANSFER_WRITE,
+                            vk::PipelineStageFlags::COMPUTE_SHADER,
+                            vk::AccessFlags::SHADER_WRITE,
+                        );
+                    }
+
+                    let sets = [chunk.set.inner().handle(), target_set.inner().handle()];
+                    (fns.v1_0.cmd_bind_descriptor_sets)(
+                        command_buffer,
+                        vk::PipelineBindPoint::COMPUTE,
+                        layout,
+                        0,
+                        sets.len() as u32,
+                        sets.as_ptr(),
+                        0,
+                        ptr::null(),
+                    );
+                    let push_constants = [chunk.data_size, self.layers];
+                    (fns.v1_0.cmd_push_constants)(
+                        command_buffer,
+                        layout,
+                        vk::ShaderStageFlags::COMPUTE,
+                        0,
+                        std::mem::size_of_val(&push_constants) as u32,
+                        push_constants.as_ptr() as *const _,
+                    );
+                    (fns.v1_0.cmd_dispatch)(command_buffer, thread_count / workgroup_size, 1, 1);
+
+                    // Every dispatch writes the subgroup size
+                    barrier(
+                        command_buffer,
+                        vk::PipelineStageFlags::COMPUTE_SHADER,
+                        vk::AccessFlags::SHADER_WRITE,
+                        vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::TRANSFER,
+                        vk::AccessFlags::SHADER_WRITE | vk::AccessFlags::TRANSFER_READ,
+                    );
+
+                    if separate_read_buffer {
+                        let region = vk::BufferCopy {
+                            src_offset: target.offset(),
+                            dst_offset: read_buffer.offset(),
+                            size: target.size(),
+                        };
+                        (fns.v1_0.cmd_copy_buffer)(
+                            command_buffer,
+                            target.buffer().handle(),
+                            read_buffer.buffer().handle(),
+                            1,
+                            &region,
+                        );
+                    }
+                }
+
+                barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::TRANSFER,
+                    vk::AccessFlags::SHADER_WRITE | vk::AccessFlags::TRANSFER_WRITE,
+                    vk::PipelineStageFlags::HOST,
+                    vk::AccessFlags::HOST_READ,
+                );
+            });
         }
+    }
 
-        let future = command_buffer
-            .build()
-            .unwrap()
-            .execute(vulkan.queue.clone())
-            .unwrap();
-        let fence = future.then_signal_fence_and_flush().unwrap();
-        fence.wait(None).unwrap();
+    /// The values the invocations wrote, without the final accumulation, one
+    /// chunk after the other
+    pub fn run_partials(&mut self, vulkan: &mut VulkanData) -> Vec<Type> {
+        self.dispatch(vulkan, true)
+            .iter()
+            .flat_map(|read_buffer| read_buffer.read().unwrap().to_vec())
+            .collect()
+    }
 
-        // println!("\n\n\n{:x?}\n", &read_buffer.read().unwrap() as &[_]);
+    #[inline(always)]
+    pub fn run(&mut self, vulkan: &mut VulkanData, separate_read_buffer: bool) -> Type {
+        let read_buffers = self.dispatch(vulkan, separate_read_buffer);
 
         if !self.parameters.skip_cpu_final_accumulation {
-            let result = black_box(
-                read_buffer
-                    .read()
-                    .unwrap()
-                    .iter()
-                    .copied()
-                    .reduce(&self.accumulate)
-                    .unwrap(),
-            );
+            let result = read_buffers
+                .iter()
+                .map(|read_buffer| {
+                    black_box(accumulate_on_cpu(
+                        read_buffer,
+                        &self.accumulate,
+                        self.parameters.final_accumulation,
+                    ))
+                })
+                .reduce(|a, b| (self.accumulate)(a, b))
+                .unwrap();
             // assert_eq!(result, self.expected_result);
+            result
+        } else {
+            Type::zero()
         }
     }
 }
+
+impl<Type> Executor<Type> for ComputeExecuteUtil<Type>
+where
+    Type: Copy + NumCast + Pod + BufferContents + PartialEq + Zero + Debug + Sum + CpuReducible,
+{
+    type Parameters = ComputeParameters;
+    type Program = Arc<ShaderModule>;
+
+    /// Reads the data from a storage buffer, see
+    /// [ComputeExecuteUtil::setup_storage_buffer_from_data]
+    fn setup<Acc>(
+        vulkan: &mut VulkanData,
+        program: &Self::Program,
+        data: Vec<Type>,
+        data_size: Vector2<u32>,
+        parameters: Self::Parameters,
+        accumulate: Acc,
+    ) -> Self
+    where
+        Self: Sized,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        let sc = PluggableConstants {
+            TEXTURE_SIZE_X: data_size.x as _,
+            TEXTURE_SIZE_Y: 1,
+        };
+
+        Self::setup_storage_buffer_from_data(
+            vulkan, data, data_size, program, sc, parameters, accumulate,
+        )
+    }
+
+    fn run(&mut self, vulkan: &mut VulkanData, separate_read_buffer: bool) -> Type {
+        ComputeExecuteUtil::run(self, vulkan, separate_read_buffer)
+    }
+
+    fn expected(&self) -> Type {
+        self.expected_result
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "compute {:?} threads {} workgroup {} layers {} vectorization {}",
+            self.parameters.output,
+            self.parameters
+                .override_thread_count
+                .unwrap_or(self.thread_count),
+            self.parameters.workgroup_size,
+            self.layers,
+            self.parameters.vectorization_factor,
+        )
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{executor::Executor, vulkan_util::VulkanData};
+use nalgebra::Vector2;
+use num::Zero;
+use ocl::{r#async::BufferSink, Buffer, Kernel, MemFlags, OclPrm, ProQue, WriteGuard};
+use ocl_futures::future::Future;
+use std::{fmt::Debug, hint::black_box};
+
+/// Runs an OpenCL kernel named `sum` that takes `size_x`, `size_y`, the input
+/// and the output buffer, like the kernels in `shaders/opencl`
+pub struct OpenClExecuteUtil<Type>
+where
+    Type: OclPrm,
+{
+    data_size: Vector2<u32>,
+
+    kernel: Kernel,
+    output_buffer: Buffer<Type>,
+    result: Vec<Type>,
+
+    expected_result: Type,
+
+    accumulate: Box<dyn Fn(Type, Type) -> Type>,
+}
+
+impl<Type> Executor<Type> for OpenClExecuteUtil<Type>
+where
+    Type: Copy + PartialEq + Debug + OclPrm + Zero,
+{
+    type Parameters = ();
+    type Program = str;
+
+    fn setup<Acc>(
+        _vulkan: &mut VulkanData,
+        program: &Self::Program,
+        data: Vec<Type>,
+        data_size: Vector2<u32>,
+        _parameters: Self::Parameters,
+        accumulate: Acc,
+    ) -> Self
+    where
+        Self: Sized,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        let total = data_size.x * data_size.y;
+        assert_eq!(data.len(), total as usize);
+
+        let cl_program = ProQue::builder()
+            .src(program)
+            .dims(data_size.x)
+            .build()
+            .unwrap();
+
+        let source_buffer = Buffer::<Type>::builder()
+            .queue(cl_program.queue().clone())
+            .flags(
+                MemFlags::empty()
+                    .read_only()
+                    .host_write_only()
+                    .alloc_host_ptr(),
+            )
+            .len(total)
+            .build()
+            .unwrap();
+
+        {
+            let source_sink = unsafe {
+                BufferSink::from_buffer(
+                    source_buffer.clone(),
+                    Some(cl_program.queue().clone()),
+                    0,
+                    total as _,
+                )
+            }
+            .unwrap();
+            let writer = source_sink.write();
+            let mut writer = writer.wait().unwrap();
+            writer.copy_from_slice(&data);
+
+            let source_sink: BufferSink<Type> = WriteGuard::release(writer).into();
+            source_sink.flush().enq().unwrap().wait().unwrap();
+        }
+
+        let output_buffer = cl_program
+            .buffer_builder::<Type>()
+            .flags(MemFlags::empty().host_read_only().write_only())
+            .build()
+            .unwrap();
+
+        let kernel = cl_program
+            .kernel_builder("sum")
+            .arg(data_size.x as u64)
+            .arg(data_size.y as u64)
+            .arg(&source_buffer)
+            .arg(&output_buffer)
+            .build()
+            .unwrap();
+
+        let expected_result = data.iter().copied().fold(Type::zero(), &accumulate);
+
+        Self {
+            data_size,
+            kernel,
+            output_buffer,
+            result: vec![Type::zero(); data_size.x as _],
+            expected_result,
+            accumulate: Box::new(accumulate),
+        }
+    }
+
+    fn run(&mut self, _vulkan: &mut VulkanData, _separate_read_buffer: bool) -> Type {
+        unsafe { self.kernel.enq() }.unwrap();
+        self.output_buffer.read(&mut self.result).enq().unwrap();
+
+        let result = black_box(self.result.iter().copied().reduce(&self.accumulate)).unwrap();
+        // assert_eq!(result, self.expected_result);
+        result
+    }
+
+    fn expected(&self) -> Type {
+        self.expected_result
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "opencl threads {} layers {}",
+            self.data_size.x, self.data_size.y
+        )
+    }
+}
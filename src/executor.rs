@@ -0,0 +1,485 @@
+use crate::{
+    cpu::{CpuReducible, CpuStrategy, ReduceOp},
+    vulkan_util::VulkanData,
+};
+use nalgebra::Vector2;
+use num::Zero;
+use std::sync::Arc;
+use vulkano::{
+    buffer::{BufferContents, BufferUsage, Subbuffer},
+    descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet},
+    format::Format,
+    image::{view::ImageView, ImageDimensions},
+    pipeline::Pipeline,
+    shader::{SpecializationConstants, SpecializationMapEntry},
+    DeviceSize,
+};
+
+/// Common interface of the graphics, compute and OpenCL reduction harnesses,
+/// so benchmarks can be written once and run against every backend
+pub trait Executor<Type> {
+    /// What the backend executes, e.g. a shader module or kernel source
+    type Program: ?Sized;
+    /// Backend specific knobs, e.g. [crate::execute_util::ExecuteParameters]
+    type Parameters;
+
+    /// Uploads `data` and prepares everything needed to reduce it.
+    ///
+    /// `data_size.x` is the number of parallel invocations and `data_size.y`
+    /// the number of values each of them accumulates. The Vulkan executors
+    /// accept data of any length and skip the rest of the grid.
+    fn setup<Acc>(
+        vulkan: &mut VulkanData,
+        program: &Self::Program,
+        data: Vec<Type>,
+        data_size: Vector2<u32>,
+        parameters: Self::Parameters,
+        accumulate: Acc,
+    ) -> Self
+    where
+        Self: Sized,
+        Acc: 'static + Fn(Type, Type) -> Type;
+
+    /// [Executor::setup] with the layout of [grid_layout]
+    fn setup_any_length<Acc>(
+        vulkan: &mut VulkanData,
+        program: &Self::Program,
+        data: Vec<Type>,
+        parameters: Self::Parameters,
+        accumulate: Acc,
+    ) -> Self
+    where
+        Self: Sized,
+        Acc: 'static + Fn(Type, Type) -> Type,
+    {
+        let data_size = grid_layout(data.len(), vulkan.gpu_thread_count());
+        Self::setup(vulkan, program, data, data_size, parameters, accumulate)
+    }
+
+    /// Runs the reduction once, including the final accumulation on the CPU
+    fn run(&mut self, vulkan: &mut VulkanData, separate_read_buffer: bool) -> Type;
+
+    /// The result of reducing the input data on the CPU
+    fn expected(&self) -> Type;
+
+    /// A short human readable summary of the backend and its configuration
+    fn describe(&self) -> String;
+}
+
+/// Grid for `len` values with at most `max_threads` parallel invocations, the
+/// shaders skip the values of the last row beyond `len`
+pub fn grid_layout(len: usize, max_threads: u32) -> Vector2<u32> {
+    let len = len.max(1) as u32;
+    let x = len.min(max_threads);
+
+    Vector2::new(x, len.div_ceil(x))
+}
+
+/// How many layers `invocations` have to loop over to cover `len` values
+/// loaded `vectorization_factor` at a time
+pub fn layer_count(len: usize, invocations: u32, vectorization_factor: u32) -> u32 {
+    (len as u32)
+        .div_ceil(vectorization_factor)
+        .div_ceil(invocations)
+        .max(1)
+}
+
+/// Uploads `data` into an `R32_UINT` storage image and binds it as binding 0
+/// of the first set of `pipeline`, see `shaders/pluggable/raw_get_data`
+pub(crate) fn storage_image_input<Type, P>(
+    vulkan: &VulkanData,
+    pipeline: &Arc<P>,
+    dimensions: ImageDimensions,
+    data: Vec<Type>,
+) -> Vec<InputChunk>
+where
+    Type: BufferContents,
+    P: Pipeline,
+{
+    assert!(
+        vulkan.format_capability(Format::R32_UINT).storage_image,
+        "R32_UINT storage images aren't supported on this device"
+    );
+
+    let data_size = data.len() as u32;
+    let mut command_buffer = vulkan.create_command_buffer();
+    let image =
+        vulkan.create_data_storage_image(&mut command_buffer, dimensions, data, Format::R32_UINT);
+    vulkan.submit_and_wait(command_buffer);
+
+    let set = PersistentDescriptorSet::new(
+        &vulkan.descriptor_set_allocator,
+        pipeline.layout().set_layouts().get(0).unwrap().clone(),
+        [WriteDescriptorSet::image_view(
+            0,
+            ImageView::new_default(image).unwrap(),
+        )],
+    )
+    .unwrap();
+
+    vec![InputChunk { set, data_size }]
+}
+
+/// Uploads `data` into a texel buffer of `R32_UINT` texels and binds it as
+/// binding 0 of the first set of `pipeline`
+pub(crate) fn texel_buffer_input<Type, P>(
+    vulkan: &VulkanData,
+    pipeline: &Arc<P>,
+    usage: BufferUsage,
+    data: Vec<Type>,
+) -> Vec<InputChunk>
+where
+    Type: BufferContents,
+    P: Pipeline,
+{
+    let data_size = data.len() as u32;
+    let max_texels = vulkan
+        .physical_device
+        .properties()
+        .max_texel_buffer_elements;
+    assert!(
+        data_size <= max_texels,
+        "{data_size} texels exceed the device limit of {max_texels}"
+    );
+    let capability = vulkan.format_capability(Format::R32_UINT);
+    assert!(
+        if usage.intersects(BufferUsage::STORAGE_TEXEL_BUFFER) {
+            capability.storage_texel_buffer
+        } else {
+            capability.uniform_texel_buffer
+        },
+        "R32_UINT {usage:?} isn't supported on this device"
+    );
+
+    let mut command_buffer = vulkan.create_command_buffer();
+    let view = vulkan.create_texel_buffer(&mut command_buffer, data, usage, Format::R32_UINT);
+    vulkan.submit_and_wait(command_buffer);
+
+    let set = PersistentDescriptorSet::new(
+        &vulkan.descriptor_set_allocator,
+        pipeline.layout().set_layouts().get(0).unwrap().clone(),
+        [WriteDescriptorSet::buffer_view(0, view)],
+    )
+    .unwrap();
+
+    vec![InputChunk { set, data_size }]
+}
+
+/// Values in one chunk of the uniform buffer input, the `uvec4` array of
+/// `shaders/pluggable/raw_get_data/uniform_buffer.glsl`
+pub const UNIFORM_CHUNK_SIZE: usize = 4096 * 4;
+
+/// Descriptor set 0 of one pass over the input and the number of values it
+/// holds. Only the uniform buffer input is split into several.
+#[derive(Clone)]
+pub(crate) struct InputChunk {
+    pub set: Arc<PersistentDescriptorSet>,
+    pub data_size: u32,
+}
+
+/// Uploads `data` into one uniform buffer, bound in chunks of
+/// [UNIFORM_CHUNK_SIZE] values that the executors run one pass each over
+pub(crate) fn uniform_buffer_input<Type, P>(
+    vulkan: &VulkanData,
+    pipeline: &Arc<P>,
+    mut data: Vec<Type>,
+) -> Vec<InputChunk>
+where
+    Type: BufferContents + Zero + Clone,
+    P: Pipeline,
+{
+    assert_eq!(
+        std::mem::size_of::<Type>(),
+        4,
+        "The uniform buffer input holds 32 bit values"
+    );
+    let max_range = vulkan.physical_device.properties().max_uniform_buffer_range as usize;
+    assert!(
+        UNIFORM_CHUNK_SIZE * 4 <= max_range,
+        "Uniform buffer chunks exceed the device limit of {max_range} bytes"
+    );
+
+    // The last chunk is padded, every binding covers the whole array. Like in
+    // [pad_to_vectors] the padding is zero and only ever read masked.
+    let len = data.len();
+    data.resize(
+        len.next_multiple_of(UNIFORM_CHUNK_SIZE)
+            .max(UNIFORM_CHUNK_SIZE),
+        Type::zero(),
+    );
+
+    let mut command_buffer = vulkan.create_command_buffer();
+    let buffer = vulkan.create_uniform_buffer(&mut command_buffer, data);
+    vulkan.submit_and_wait(command_buffer);
+
+    let chunk = UNIFORM_CHUNK_SIZE as DeviceSize;
+    (0..buffer.len() / chunk)
+        .map(|i| InputChunk {
+            set: PersistentDescriptorSet::new(
+                &vulkan.descriptor_set_allocator,
+                pipeline.layout().set_layouts().get(0).unwrap().clone(),
+                [WriteDescriptorSet::buffer(
+                    0,
+                    buffer.clone().slice(i * chunk..(i + 1) * chunk),
+                )],
+            )
+            .unwrap(),
+            data_size: (len as DeviceSize).saturating_sub(i * chunk).min(chunk) as u32,
+        })
+        .collect()
+}
+
+/// Pads `data` to whole vectors, at least one so empty data still gets a
+/// buffer. The padding is zero whatever the operation, the shaders must never
+/// read it unmasked: `mask_partial` replaces it with the identity.
+pub(crate) fn pad_to_vectors<Type: Zero + Clone>(data: &mut Vec<Type>, vectorization_factor: u32) {
+    let len = data
+        .len()
+        .next_multiple_of(vectorization_factor as usize)
+        .max(vectorization_factor as usize);
+    data.resize(len, Type::zero());
+}
+
+/// Reduces `data` on the CPU for checking the GPU result. Empty data reduces
+/// to the identity of `op`, or to zero if the operation isn't known.
+pub(crate) fn expected_result<Type: CpuReducible>(
+    data: &[Type],
+    accumulate: &dyn Fn(Type, Type) -> Type,
+    op: Option<ReduceOp>,
+) -> Type {
+    data.iter()
+        .copied()
+        .reduce(accumulate)
+        .unwrap_or_else(|| op.map_or_else(Type::zero, ReduceOp::identity))
+}
+
+/// Specialization constants shared by every shader built from
+/// `shaders/pluggable/constants.glsl`
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+#[allow(non_snake_case)]
+pub struct PluggableConstants {
+    pub TEXTURE_SIZE_X: i32,
+    pub TEXTURE_SIZE_Y: i32,
+}
+
+unsafe impl SpecializationConstants for PluggableConstants {
+    fn descriptors() -> &'static [SpecializationMapEntry] {
+        static DESCRIPTORS: [SpecializationMapEntry; 2] = [
+            SpecializationMapEntry {
+                constant_id: 0,
+                offset: 0,
+                size: 4,
+            },
+            SpecializationMapEntry {
+                constant_id: 1,
+                offset: 4,
+                size: 4,
+            },
+        ];
+
+        &DESCRIPTORS
+    }
+}
+
+/// [PluggableConstants] plus the view of
+/// `shaders/pluggable/raw_get_data/storage_buffer_axis.glsl`, a row-major
+/// tensor seen as `[outer, AXIS_LENGTH, AXIS_INNER]`
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+#[allow(non_snake_case)]
+pub struct AxisConstants {
+    pub TEXTURE_SIZE_X: i32,
+    pub TEXTURE_SIZE_Y: i32,
+    pub AXIS_LENGTH: i32,
+    pub AXIS_INNER: i32,
+}
+
+unsafe impl SpecializationConstants for AxisConstants {
+    fn descriptors() -> &'static [SpecializationMapEntry] {
+        static DESCRIPTORS: [SpecializationMapEntry; 4] = [
+            SpecializationMapEntry {
+                constant_id: 0,
+                offset: 0,
+                size: 4,
+            },
+            SpecializationMapEntry {
+                constant_id: 1,
+                offset: 4,
+                size: 4,
+            },
+            SpecializationMapEntry {
+                constant_id: 3,
+                offset: 8,
+                size: 4,
+            },
+            SpecializationMapEntry {
+                constant_id: 4,
+                offset: 12,
+                size: 4,
+            },
+        ];
+
+        &DESCRIPTORS
+    }
+}
+
+/// Every constant of the pluggable shaders plus the workgroup size of compute
+/// shaders, see `shaders/pluggable/location.glsl`. Shaders ignore the ones
+/// they do not declare
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+#[allow(non_snake_case)]
+pub struct ComputeConstants {
+    pub TEXTURE_SIZE_X: i32,
+    pub TEXTURE_SIZE_Y: i32,
+    pub WORKGROUP_SIZE: u32,
+    pub AXIS_LENGTH: i32,
+    pub AXIS_INNER: i32,
+}
+
+impl ComputeConstants {
+    pub fn new<SC: Into<Self>>(sc: SC, workgroup_size: u32) -> Self {
+        Self {
+            WORKGROUP_SIZE: workgroup_size,
+            ..sc.into()
+        }
+    }
+}
+
+impl From<PluggableConstants> for ComputeConstants {
+    fn from(sc: PluggableConstants) -> Self {
+        Self {
+            TEXTURE_SIZE_X: sc.TEXTURE_SIZE_X,
+            TEXTURE_SIZE_Y: sc.TEXTURE_SIZE_Y,
+            WORKGROUP_SIZE: 64,
+            AXIS_LENGTH: 1,
+            AXIS_INNER: 1,
+        }
+    }
+}
+
+impl From<AxisConstants> for ComputeConstants {
+    fn from(sc: AxisConstants) -> Self {
+        Self {
+            TEXTURE_SIZE_X: sc.TEXTURE_SIZE_X,
+            TEXTURE_SIZE_Y: sc.TEXTURE_SIZE_Y,
+            WORKGROUP_SIZE: 64,
+            AXIS_LENGTH: sc.AXIS_LENGTH,
+            AXIS_INNER: sc.AXIS_INNER,
+        }
+    }
+}
+
+unsafe impl SpecializationConstants for ComputeConstants {
+    fn descriptors() -> &'static [SpecializationMapEntry] {
+        static DESCRIPTORS: [SpecializationMapEntry; 5] = [
+            SpecializationMapEntry {
+                constant_id: 0,
+                offset: 0,
+                size: 4,
+            },
+            SpecializationMapEntry {
+                constant_id: 1,
+                offset: 4,
+                size: 4,
+            },
+            SpecializationMapEntry {
+                constant_id: 2,
+                offset: 8,
+                size: 4,
+            },
+            SpecializationMapEntry {
+                constant_id: 3,
+                offset: 12,
+                size: 4,
+            },
+            SpecializationMapEntry {
+                constant_id: 4,
+                offset: 16,
+                size: 4,
+            },
+        ];
+
+        &DESCRIPTORS
+    }
+}
+
+/// Reads back the partial results of a shader and accumulates them, with the
+/// reducers of [crate::cpu] if the operation is known
+pub(crate) fn accumulate_on_cpu<Type>(
+    read_buffer: &Subbuffer<[Type]>,
+    accumulate: &dyn Fn(Type, Type) -> Type,
+    op: Option<ReduceOp>,
+) -> Type
+where
+    Type: Copy + BufferContents + CpuReducible,
+{
+    let data = read_buffer.read().unwrap();
+
+    match op {
+        Some(op) => CpuStrategy::for_len(data.len()).reduce(&data, op),
+        None => data.iter().copied().reduce(accumulate).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIME_LENGTHS: &[usize] = &[1, 2, 3, 61, 127, 4099, 65537, 1_000_003];
+
+    #[test]
+    fn grid_covers_prime_lengths() {
+        for len in PRIME_LENGTHS.iter().copied() {
+            for max_threads in [1, 64, 1000, 65536] {
+                let size = grid_layout(len, max_threads);
+
+                assert!(size.x <= max_threads);
+                assert!((size.x * size.y) as usize >= len, "{len} in {size:?}");
+                // Only the last row may be partial
+                assert!(
+                    ((size.x * (size.y - 1)) as usize) < len,
+                    "{len} in {size:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn layers_cover_prime_lengths() {
+        for len in PRIME_LENGTHS.iter().copied() {
+            for (invocations, vectorization_factor) in [(1, 1), (64, 1), (64, 4), (1000, 4)] {
+                let layers = layer_count(len, invocations, vectorization_factor);
+                let per_layer = (invocations * vectorization_factor) as usize;
+
+                assert!(layers as usize * per_layer >= len);
+                assert!(((layers - 1) as usize * per_layer) < len);
+            }
+        }
+    }
+
+    #[test]
+    fn pads_to_whole_vectors() {
+        for len in PRIME_LENGTHS.iter().copied() {
+            let mut data = vec![1u32; len];
+            pad_to_vectors(&mut data, 4);
+
+            assert_eq!(data.len() % 4, 0);
+            assert!(data.len() - len < 4);
+            assert_eq!(data.iter().sum::<u32>() as usize, len);
+        }
+    }
+
+    #[test]
+    fn empty_data_gets_one_vector_and_the_identity() {
+        let mut data = Vec::<u32>::new();
+        pad_to_vectors(&mut data, 4);
+        assert_eq!(data, [0; 4]);
+
+        let add = |a: u32, b: u32| a.wrapping_add(b);
+        assert_eq!(expected_result(&[], &add, Some(ReduceOp::Min)), u32::MAX);
+        assert_eq!(expected_result(&[], &add, None), 0);
+        assert_eq!(expected_result(&[3, 4], &add, None), 7);
+    }
+}
@@ -1,8 +1,21 @@
 #![feature(int_roundings)]
+#![feature(portable_simd)]
 
+pub mod auto_reducer;
+pub mod axis_reduce;
+pub mod bench_matrix;
 pub mod capture;
+pub mod cpu;
+pub mod data_source;
+pub mod dispatch;
 pub mod execute_util;
 pub mod execute_util_compute;
+#[cfg(feature = "opencl")]
+pub mod execute_util_opencl;
+pub mod executor;
+pub mod results;
+pub mod shader_registry;
+pub mod tuner;
 pub mod vulkan_util;
 
 use std::ffi::c_int;
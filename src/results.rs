@@ -0,0 +1,522 @@
+use crate::{
+    execute_util::{ExecuteParameters, OutputKind},
+    execute_util_compute::ComputeParameters,
+    vulkan_util::VulkanData,
+};
+use criterion::Throughput;
+use itertools::Itertools;
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+use std::{
+    any::type_name,
+    fs::File,
+    hint::black_box,
+    io::{BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+/// Criterion throughput of reducing `elements` values of `Type`
+pub fn throughput<Type>(elements: u32) -> Throughput {
+    Throughput::Bytes(elements as u64 * std::mem::size_of::<Type>() as u64)
+}
+
+/// Identifies the device a [ResultSet] was measured on
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub device_type: String,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub driver_version: u32,
+    pub driver_info: Option<String>,
+    pub api_version: String,
+    pub subgroup_size: Option<u32>,
+    pub subgroup_stages: Option<String>,
+    pub subgroup_operations: Option<String>,
+    /// Subgroup shaders that were replaced or skipped because the device lacks
+    /// the operations they need, see
+    /// [crate::bench_matrix::BenchMatrix::subgroup_shader]
+    #[serde(default)]
+    pub subgroup_fallbacks: Vec<String>,
+    pub gpu_thread_count: u32,
+}
+
+impl DeviceInfo {
+    pub fn new(vulkan: &VulkanData) -> Self {
+        let properties = vulkan.physical_device.properties();
+        let subgroups = vulkan.subgroup_support();
+
+        Self {
+            name: properties.device_name.clone(),
+            device_type: format!("{:?}", properties.device_type),
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            driver_version: properties.driver_version,
+            driver_info: properties.driver_info.clone(),
+            api_version: properties.api_version.to_string(),
+            subgroup_size: properties.subgroup_size,
+            subgroup_stages: Some(format!("{:?}", subgroups.stages)),
+            subgroup_operations: Some(format!("{:?}", subgroups.operations)),
+            subgroup_fallbacks: vec![],
+            gpu_thread_count: vulkan.gpu_thread_count(),
+        }
+    }
+}
+
+/// Everything that distinguishes one benchmark from another, flattened so it
+/// maps onto CSV columns. Fields that do not apply to a backend are `None`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct RunParameters {
+    pub backend: String,
+    pub shader: String,
+    pub data_size_x: u32,
+    pub data_size_y: u32,
+    pub element_size: u32,
+    /// [type_name] of the elements, tells e.g. u32 and f32 sums apart. Not
+    /// present in results exported before it was recorded
+    #[serde(default)]
+    pub element_type: Option<String>,
+    pub vectorization_factor: u32,
+    pub separate_read_buffer: bool,
+
+    pub output: Option<String>,
+    pub quad_method: Option<String>,
+    pub framebuffer_y: Option<u32>,
+    pub blend: Option<String>,
+
+    pub clear_buffer: Option<bool>,
+    pub override_thread_count: Option<u32>,
+    pub workgroup_size: Option<u32>,
+
+    /// The [crate::execute_util::DataGenerator] of the input
+    #[serde(default)]
+    pub data: Option<String>,
+}
+
+impl RunParameters {
+    pub fn graphics<Type>(
+        shader: &str,
+        data_size: Vector2<u32>,
+        parameters: &ExecuteParameters,
+        separate_read_buffer: bool,
+    ) -> Self {
+        Self {
+            backend: "graphics".to_string(),
+            shader: shader.to_string(),
+            data_size_x: data_size.x,
+            data_size_y: data_size.y,
+            element_size: std::mem::size_of::<Type>() as _,
+            element_type: Some(type_name::<Type>().to_string()),
+            vectorization_factor: parameters.vectorization_factor,
+            separate_read_buffer,
+            output: Some(match parameters.output {
+                OutputKind::RenderAttachment(format) => format!("{format:?}"),
+                OutputKind::BlendAttachment => "BlendAttachment".to_string(),
+                OutputKind::PackedAttachments {
+                    format,
+                    attachments,
+                } => format!("{attachments}x{format:?}"),
+                OutputKind::Buffer => "Buffer".to_string(),
+            }),
+            quad_method: Some(format!("{:?}", parameters.quad_method)),
+            framebuffer_y: Some(parameters.framebuffer_y),
+            blend: parameters
+                .blend
+                .filter(|_| parameters.use_instances_and_blend)
+                .map(|blend| format!("{blend:?}")),
+            data: Some(parameters.data.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn compute<Type>(
+        shader: &str,
+        data_size: Vector2<u32>,
+        parameters: &ComputeParameters,
+        separate_read_buffer: bool,
+    ) -> Self {
+        Self {
+            backend: "compute".to_string(),
+            shader: shader.to_string(),
+            data_size_x: data_size.x,
+            data_size_y: data_size.y,
+            element_size: std::mem::size_of::<Type>() as _,
+            element_type: Some(type_name::<Type>().to_string()),
+            vectorization_factor: parameters.vectorization_factor,
+            separate_read_buffer,
+            output: Some(format!("{:?}", parameters.output)),
+            clear_buffer: Some(parameters.clear_buffer),
+            override_thread_count: parameters.override_thread_count,
+            workgroup_size: Some(parameters.workgroup_size),
+            data: Some(parameters.data.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn cpu<Type>(strategy: &str, elements: u32) -> Self {
+        Self {
+            backend: "cpu".to_string(),
+            shader: strategy.to_string(),
+            data_size_x: 1,
+            data_size_y: elements,
+            element_size: std::mem::size_of::<Type>() as _,
+            element_type: Some(type_name::<Type>().to_string()),
+            vectorization_factor: 1,
+            ..Default::default()
+        }
+    }
+
+    pub fn elements(&self) -> u64 {
+        self.data_size_x as u64 * self.data_size_y as u64
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.elements() * self.element_size as u64
+    }
+}
+
+/// Summary of a set of samples in nanoseconds
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Timing {
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    pub std_dev: f64,
+}
+
+impl Timing {
+    pub fn new(samples: &[Duration]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let nanos = samples
+            .iter()
+            .map(|d| d.as_nanos() as f64)
+            .sorted_by(f64::total_cmp)
+            .collect_vec();
+        let count = nanos.len() as f64;
+
+        let mean = nanos.iter().sum::<f64>() / count;
+        // Sample variance, the comparisons treat the runs as a sample
+        let variance =
+            nanos.iter().map(|n| (n - mean).powi(2)).sum::<f64>() / (count - 1.0).max(1.0);
+
+        Some(Self {
+            mean,
+            median: nanos[nanos.len() / 2],
+            min: nanos[0],
+            max: nanos[nanos.len() - 1],
+            std_dev: variance.sqrt(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchResult {
+    pub group: String,
+    pub id: String,
+    pub parameters: RunParameters,
+    pub samples: usize,
+
+    pub wall_ns: Timing,
+    /// Only present if the device supports timestamps, see
+    /// [VulkanData::with_gpu_timing]
+    pub gpu_ns: Option<Timing>,
+
+    /// Input bytes per median wall time
+    pub gb_per_s: f64,
+    /// Input elements per median wall time
+    pub elements_per_s: f64,
+    /// [BenchResult::gb_per_s] relative to [ResultSet::peak_gb_per_s]
+    pub percent_of_peak: Option<f64>,
+    /// Only present for benchmarks with a reference, see
+    /// [Recorder::with_reference]
+    #[serde(default)]
+    pub accuracy: Option<Accuracy>,
+}
+
+/// How close the results of a benchmark are to a higher precision reference
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Accuracy {
+    pub reference: f64,
+    /// Of the first sample
+    pub result: f64,
+    /// `|result - reference| / |reference|`, the absolute error for a zero
+    /// reference
+    pub relative_error: f64,
+    /// Whether every sample returned bit for bit the same result
+    pub reproducible: bool,
+}
+
+/// Runs of a benchmark recorded after criterion measured it, so that neither
+/// its warm up nor the recording overhead end up in the other's numbers
+pub const RECORDED_RUNS: usize = 20;
+
+/// Collects the samples of a single benchmark
+#[derive(Default)]
+pub struct Recorder {
+    wall: Vec<Duration>,
+    gpu: Vec<Duration>,
+
+    reference: Option<f64>,
+    results: Vec<f64>,
+}
+
+impl Recorder {
+    /// Also compares the values passed to [Recorder::result] against
+    /// `reference`
+    pub fn with_reference(reference: f64) -> Self {
+        Self {
+            reference: Some(reference),
+            ..Default::default()
+        }
+    }
+
+    /// Keeps the result of a sample, converted losslessly for f32 and integers
+    /// up to 2^53
+    pub fn result(&mut self, result: f64) {
+        self.results.push(result);
+    }
+
+    pub fn accuracy(&self) -> Option<Accuracy> {
+        let reference = self.reference?;
+        let result = *self.results.first()?;
+
+        let error = (result - reference).abs();
+        Some(Accuracy {
+            reference,
+            result,
+            relative_error: if reference == 0.0 {
+                error
+            } else {
+                error / reference.abs()
+            },
+            reproducible: self
+                .results
+                .iter()
+                .all(|other| other.to_bits() == result.to_bits()),
+        })
+    }
+
+    /// Runs `f` and records its wall time and the GPU time of the last command
+    /// buffer it submitted
+    #[inline(always)]
+    pub fn measure<R, F>(&mut self, vulkan: &mut VulkanData, f: F) -> R
+    where
+        F: FnOnce(&mut VulkanData) -> R,
+    {
+        let start = std::time::Instant::now();
+        let result = f(vulkan);
+        self.wall.push(start.elapsed());
+
+        if let Some(gpu) = vulkan.last_gpu_time() {
+            self.gpu.push(gpu);
+        }
+
+        result
+    }
+
+    /// Runs `f` and records its wall time, for benchmarks that do not touch
+    /// the GPU
+    #[inline(always)]
+    pub fn measure_cpu<R, F>(&mut self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        let start = std::time::Instant::now();
+        let result = f();
+        self.wall.push(start.elapsed());
+
+        result
+    }
+
+    /// Records [RECORDED_RUNS] runs of `f` with [Recorder::measure], with
+    /// GPU timing enabled only for these runs
+    pub fn measure_runs<R, F>(&mut self, vulkan: &mut VulkanData, mut f: F) -> Vec<R>
+    where
+        F: FnMut(&mut VulkanData) -> R,
+    {
+        vulkan.with_gpu_timing(|vulkan| {
+            (0..RECORDED_RUNS)
+                .map(|_| self.measure(vulkan, &mut f))
+                .collect()
+        })
+    }
+
+    /// Records [RECORDED_RUNS] runs of `f` with [Recorder::measure_cpu]
+    pub fn measure_cpu_runs<R, F>(&mut self, mut f: F)
+    where
+        F: FnMut() -> R,
+    {
+        for _ in 0..RECORDED_RUNS {
+            black_box(self.measure_cpu(&mut f));
+        }
+    }
+
+    pub fn wall_time(&self) -> Option<Timing> {
+        Timing::new(&self.wall)
+    }
+}
+
+/// Benchmark results of one device, exported as JSON and CSV
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ResultSet {
+    pub device: DeviceInfo,
+    /// Copy bandwidth measured by [ResultSet::calibrate]
+    pub peak_gb_per_s: Option<f64>,
+    pub results: Vec<BenchResult>,
+}
+
+impl ResultSet {
+    /// Recorded runs include GPU times if `vulkan` supports timestamps, see
+    /// [Recorder::measure_runs]
+    pub fn new(vulkan: &mut VulkanData) -> Self {
+        if !vulkan.supports_gpu_timing() {
+            println!("Device does not support timestamps, only recording wall times");
+        }
+
+        Self {
+            device: DeviceInfo::new(vulkan),
+            peak_gb_per_s: None,
+            results: vec![],
+        }
+    }
+
+    /// Measures the peak copy bandwidth of the device, results recorded
+    /// afterwards are also reported as percentage of it
+    pub fn calibrate(&mut self, vulkan: &mut VulkanData) {
+        let peak = vulkan.measure_copy_bandwidth(256 << 20, 20);
+        println!("Peak copy bandwidth: {peak:.2} GB/s");
+
+        self.peak_gb_per_s = Some(peak);
+    }
+
+    pub fn record(&mut self, group: &str, id: &str, parameters: RunParameters, recorder: Recorder) {
+        let Some(wall_ns) = Timing::new(&recorder.wall) else {
+            return;
+        };
+
+        let gb_per_s = parameters.bytes() as f64 / wall_ns.median;
+        let elements_per_s = parameters.elements() as f64 / wall_ns.median * 1e9;
+
+        self.results.push(BenchResult {
+            group: group.to_string(),
+            id: id.to_string(),
+            parameters,
+            samples: recorder.wall.len(),
+            wall_ns,
+            gpu_ns: Timing::new(&recorder.gpu),
+            gb_per_s,
+            elements_per_s,
+            percent_of_peak: self.peak_gb_per_s.map(|peak| gb_per_s / peak * 100.0),
+            accuracy: recorder.accuracy(),
+        });
+    }
+
+    pub fn read_json<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut file, self)?;
+        file.flush()
+    }
+
+    /// One row per result, device info is repeated in every row so files of
+    /// different devices can simply be concatenated
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        fn opt<T: ToString>(value: &Option<T>) -> String {
+            value.as_ref().map(T::to_string).unwrap_or_default()
+        }
+        fn escape(value: &str) -> String {
+            if value.contains([',', '"', '\n']) {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            } else {
+                value.to_string()
+            }
+        }
+
+        let mut file = BufWriter::new(File::create(path)?);
+
+        writeln!(
+            file,
+            "device,device_type,vendor_id,device_id,driver_version,subgroup_size,\
+             group,id,backend,shader,data_size_x,data_size_y,element_size,element_type,\
+             vectorization_factor,separate_read_buffer,output,quad_method,framebuffer_y,blend,\
+             clear_buffer,override_thread_count,workgroup_size,data,samples,\
+             wall_mean_ns,wall_median_ns,wall_min_ns,wall_max_ns,wall_std_dev_ns,\
+             gpu_mean_ns,gpu_median_ns,gpu_min_ns,gpu_max_ns,gpu_std_dev_ns,\
+             gb_per_s,elements_per_s,percent_of_peak,relative_error,reproducible"
+        )?;
+
+        let device = &self.device;
+        for result in self.results.iter() {
+            let p = &result.parameters;
+            let gpu = |f: fn(&Timing) -> f64| opt(&result.gpu_ns.as_ref().map(f));
+
+            let row = [
+                device.name.clone(),
+                device.device_type.clone(),
+                device.vendor_id.to_string(),
+                device.device_id.to_string(),
+                device.driver_version.to_string(),
+                opt(&device.subgroup_size),
+                result.group.clone(),
+                result.id.clone(),
+                p.backend.clone(),
+                p.shader.clone(),
+                p.data_size_x.to_string(),
+                p.data_size_y.to_string(),
+                p.element_size.to_string(),
+                opt(&p.element_type),
+                p.vectorization_factor.to_string(),
+                p.separate_read_buffer.to_string(),
+                opt(&p.output),
+                opt(&p.quad_method),
+                opt(&p.framebuffer_y),
+                opt(&p.blend),
+                opt(&p.clear_buffer),
+                opt(&p.override_thread_count),
+                opt(&p.workgroup_size),
+                opt(&p.data),
+                result.samples.to_string(),
+                result.wall_ns.mean.to_string(),
+                result.wall_ns.median.to_string(),
+                result.wall_ns.min.to_string(),
+                result.wall_ns.max.to_string(),
+                result.wall_ns.std_dev.to_string(),
+                gpu(|t| t.mean),
+                gpu(|t| t.median),
+                gpu(|t| t.min),
+                gpu(|t| t.max),
+                gpu(|t| t.std_dev),
+                result.gb_per_s.to_string(),
+                result.elements_per_s.to_string(),
+                opt(&result.percent_of_peak),
+                opt(&result.accuracy.as_ref().map(|a| a.relative_error)),
+                opt(&result.accuracy.as_ref().map(|a| a.reproducible)),
+            ];
+
+            writeln!(file, "{}", row.iter().map(|v| escape(v)).join(","))?;
+        }
+
+        file.flush()
+    }
+
+    /// Writes `{name}.json` and `{name}.csv` into `directory`
+    pub fn save<P: AsRef<Path>>(&self, directory: P, name: &str) -> std::io::Result<()> {
+        let directory = directory.as_ref();
+        std::fs::create_dir_all(directory)?;
+
+        self.write_json(directory.join(format!("{name}.json")))?;
+        self.write_csv(directory.join(format!("{name}.csv")))?;
+
+        println!("Results written to {:?}", directory.join(name));
+        Ok(())
+    }
+}
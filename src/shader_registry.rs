@@ -0,0 +1,439 @@
+use crate::{
+    bench_matrix::ShaderKind,
+    cpu::ReduceOp,
+    execute_util::{ExecuteParameters, OutputKind},
+    execute_util_compute::{ComputeParameters, OutputModification},
+    vulkan_util::{SubgroupSupport, VulkanData},
+};
+use std::{any::type_name, sync::Arc};
+use vulkano::{
+    device::Device,
+    format::Format,
+    shader::{ShaderCreationError, ShaderModule, ShaderStages},
+};
+
+pub type ShaderLoader = fn(Arc<Device>) -> Result<Arc<ShaderModule>, ShaderCreationError>;
+
+/// A reduction shader reading a storage buffer and the parameters it was
+/// compiled for
+#[derive(Clone)]
+pub struct RegisteredShader {
+    /// The name of the shader in the benches
+    pub name: &'static str,
+    pub op: ReduceOp,
+    /// [type_name] of the elements
+    pub element_type: &'static str,
+    pub kind: ShaderKind,
+    /// Needs [SubgroupSupport::writer_operations] in compute shaders
+    pub subgroups: bool,
+    pub load: ShaderLoader,
+}
+
+impl RegisteredShader {
+    fn graphics<Type>(
+        name: &'static str,
+        op: ReduceOp,
+        parameters: ExecuteParameters,
+        load: ShaderLoader,
+    ) -> Self {
+        Self::new::<Type>(name, op, ShaderKind::Graphics(parameters), load)
+    }
+
+    fn compute<Type>(
+        name: &'static str,
+        op: ReduceOp,
+        parameters: ComputeParameters,
+        load: ShaderLoader,
+    ) -> Self {
+        Self::new::<Type>(name, op, ShaderKind::Compute(parameters), load)
+    }
+
+    fn new<Type>(name: &'static str, op: ReduceOp, kind: ShaderKind, load: ShaderLoader) -> Self {
+        Self {
+            name,
+            op,
+            element_type: type_name::<Type>(),
+            kind,
+            subgroups: false,
+            load,
+        }
+    }
+
+    fn subgroups(self) -> Self {
+        Self {
+            subgroups: true,
+            ..self
+        }
+    }
+
+    pub fn supported(&self, vulkan: &VulkanData) -> bool {
+        !self.subgroups
+            || vulkan
+                .subgroup_support()
+                .supports(ShaderStages::COMPUTE, SubgroupSupport::writer_operations())
+    }
+
+    /// Whether the shader reduces elements of `element_type` with `op`
+    pub fn reduces(&self, op: ReduceOp, element_type: &str) -> bool {
+        self.op == op && self.element_type == element_type
+    }
+}
+
+/// The shaders of `benches/buffer.rs`, `benches/buffer_f32.rs` and
+/// `benches/sum_f32.rs` reading a storage buffer, shared with the
+/// [crate::auto_reducer] and `gpu_compute-bench`
+pub fn shaders() -> Vec<RegisteredShader> {
+    use ReduceOp::{Min, Sum};
+
+    let graphics = |output, vectorization_factor| ExecuteParameters {
+        output,
+        vectorization_factor,
+        ..Default::default()
+    };
+    let compute = |output, vectorization_factor| ComputeParameters {
+        output,
+        vectorization_factor,
+        ..Default::default()
+    };
+    let atomic = ComputeParameters {
+        clear_buffer: true,
+        ..compute(OutputModification::SingleValue, 1)
+    };
+
+    vec![
+        RegisteredShader::graphics::<u32>(
+            "buffer_to_rendertarget",
+            Sum,
+            graphics(OutputKind::Attachment, 1),
+            sum_attach_none_sbuffer_loop::load,
+        ),
+        RegisteredShader::graphics::<u32>(
+            "buffer_to_buffer",
+            Sum,
+            graphics(OutputKind::Buffer, 1),
+            sum_buffer_none_sbuffer_loop::load,
+        ),
+        RegisteredShader::graphics::<u32>(
+            "vector_buffer_to_buffer",
+            Sum,
+            graphics(OutputKind::Buffer, 4),
+            sum_vector_buffer_none_sbuffer_loop::load,
+        ),
+        RegisteredShader::compute::<u32>(
+            "compute_buffer_to_buffer",
+            Sum,
+            compute(OutputModification::OneForOne, 1),
+            sum_compute_none_sbuffer_loop::load,
+        ),
+        RegisteredShader::compute::<u32>(
+            "vector_compute_buffer_to_buffer",
+            Sum,
+            compute(OutputModification::OneForOne, 4),
+            sum_vector_compute_none_sbuffer_loop::load,
+        ),
+        RegisteredShader::compute::<u32>(
+            "compute_buffer_to_buffer_subgroup",
+            Sum,
+            compute(OutputModification::OnePerSubgroup, 1),
+            sum_compute_none_groupbuffer_loop::load,
+        )
+        .subgroups(),
+        RegisteredShader::compute::<u32>(
+            "compute_buffer_to_buffer_shared",
+            Sum,
+            compute(OutputModification::OnePerWorkgroup, 1),
+            sum_compute_none_sharedbuffer_loop::load,
+        ),
+        RegisteredShader::compute::<u32>(
+            "compute_buffer_to_buffer_atomic_add",
+            Sum,
+            atomic,
+            sum_compute_none_atomic_add_buffer_loop::load,
+        ),
+        RegisteredShader::compute::<u32>(
+            "compute_buffer_to_buffer_tree_interleaved",
+            Sum,
+            compute(OutputModification::OnePerWorkgroup, 1),
+            sum_compute_none_sharedtree_interleaved_loop::load,
+        ),
+        RegisteredShader::compute::<u32>(
+            "compute_buffer_to_buffer_tree_sequential",
+            Sum,
+            compute(OutputModification::OnePerWorkgroup, 1),
+            sum_compute_none_sharedtree_sequential_loop::load,
+        ),
+        RegisteredShader::graphics::<f32>(
+            "graphics_buffer_to_buffer",
+            Min,
+            graphics(OutputKind::Buffer, 1),
+            min_none_sbuffer_loop::load,
+        ),
+        RegisteredShader::graphics::<f32>(
+            "graphics_buffer_to_rendertarget",
+            Min,
+            graphics(OutputKind::RenderAttachment(Format::R32_SFLOAT), 1),
+            min_attach_none_sbuffer_loop::load,
+        ),
+        RegisteredShader::compute::<f32>(
+            "compute_buffer_to_buffer",
+            Min,
+            compute(OutputModification::OneForOne, 1),
+            min_compute_none_sbuffer_loop::load,
+        ),
+        RegisteredShader::compute::<f32>(
+            "vector_compute_buffer_to_buffer",
+            Min,
+            compute(OutputModification::OneForOne, 4),
+            min_vector_compute_none_sbuffer_loop::load,
+        ),
+        RegisteredShader::compute::<f32>(
+            "compute_buffer_to_buffer_tree_interleaved",
+            Min,
+            compute(OutputModification::OnePerWorkgroup, 1),
+            min_compute_none_sharedtree_interleaved_loop::load,
+        ),
+        RegisteredShader::compute::<f32>(
+            "compute_buffer_to_buffer_tree_sequential",
+            Min,
+            compute(OutputModification::OnePerWorkgroup, 1),
+            min_compute_none_sharedtree_sequential_loop::load,
+        ),
+        RegisteredShader::compute::<f32>(
+            "compute_buffer_to_buffer",
+            Sum,
+            compute(OutputModification::OneForOne, 1),
+            sum_f32_compute_none_sbuffer_loop::load,
+        ),
+        RegisteredShader::compute::<f32>(
+            "compute_buffer_to_buffer_kahan",
+            Sum,
+            compute(OutputModification::OneForOne, 1),
+            sum_f32_compute_none_sbuffer_kahan::load,
+        ),
+        RegisteredShader::compute::<f32>(
+            "compute_buffer_to_buffer_pairwise",
+            Sum,
+            compute(OutputModification::OneForOne, 1),
+            sum_f32_compute_none_sbuffer_pairwise::load,
+        ),
+    ]
+}
+
+/// The registered shader `name` reducing `Type` with `op`
+pub fn find<Type>(op: ReduceOp, name: &str) -> Option<RegisteredShader> {
+    shaders()
+        .into_iter()
+        .find(|shader| shader.name == name && shader.reduces(op, type_name::<Type>()))
+}
+
+/// The loader of the registered shader `name` reducing `Type` with `op`
+pub fn loader<Type>(op: ReduceOp, name: &str) -> ShaderLoader {
+    find::<Type>(op, name)
+        .unwrap_or_else(|| {
+            panic!(
+                "No shader {name:?} for {} {}",
+                op.name(),
+                type_name::<Type>()
+            )
+        })
+        .load
+}
+
+mod sum_attach_none_sbuffer_loop {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "shaders/instances/gpu_sum/attach_none_sbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+    }
+}
+
+mod sum_buffer_none_sbuffer_loop {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "shaders/instances/gpu_sum/buffer_none_sbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+    }
+}
+
+mod sum_vector_buffer_none_sbuffer_loop {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "shaders/instances/gpu_sum/vectorized/buffer_none_sbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+    }
+}
+
+mod sum_compute_none_sbuffer_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_sum/buffer_none_sbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1")],
+    }
+}
+
+mod sum_vector_compute_none_sbuffer_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_sum/vectorized/buffer_none_sbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1")],
+    }
+}
+
+mod sum_compute_none_groupbuffer_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_sum/buffer_none_groupbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1")],
+        spirv_version: "1.3",
+    }
+}
+
+mod sum_compute_none_sharedbuffer_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_sum/buffer_none_sharedbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1")],
+    }
+}
+
+mod sum_compute_none_atomic_add_buffer_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_sum/buffer_none_atomic_add_buffer_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1")],
+    }
+}
+
+mod sum_compute_none_sharedtree_interleaved_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_sum/buffer_none_sharedtree_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1"), ("TREE_INTERLEAVED", "1")],
+    }
+}
+
+mod sum_compute_none_sharedtree_sequential_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_sum/buffer_none_sharedtree_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1")],
+    }
+}
+
+mod min_none_sbuffer_loop {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "shaders/instances/gpu_min/buffer_none_sbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("DATA_TYPE", "float")],
+    }
+}
+
+mod min_attach_none_sbuffer_loop {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "shaders/instances/gpu_min/attach_none_sbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("DATA_TYPE", "float")],
+    }
+}
+
+mod min_compute_none_sbuffer_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_min/buffer_none_sbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1"), ("DATA_TYPE", "float")],
+    }
+}
+
+mod min_vector_compute_none_sbuffer_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_min/vectorized/buffer_none_sbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1"), ("DATA_TYPE", "vec4")],
+    }
+}
+
+mod min_compute_none_sharedtree_interleaved_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_min/buffer_none_sharedtree_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1"), ("DATA_TYPE", "float"), ("TREE_INTERLEAVED", "1")],
+    }
+}
+
+mod min_compute_none_sharedtree_sequential_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_min/buffer_none_sharedtree_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1"), ("DATA_TYPE", "float")],
+    }
+}
+
+mod sum_f32_compute_none_sbuffer_loop {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_sum/buffer_none_sbuffer_loop.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1"), ("DATA_TYPE", "float")],
+    }
+}
+
+mod sum_f32_compute_none_sbuffer_kahan {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_sum/buffer_none_sbuffer_kahan.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1"), ("DATA_TYPE", "float")],
+    }
+}
+
+mod sum_f32_compute_none_sbuffer_pairwise {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/instances/gpu_sum/buffer_none_sbuffer_pairwise.glsl",
+        include: ["shaders/pluggable"],
+        define: [("COMPUTE_SHADER", "1"), ("DATA_TYPE", "float")],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn names_are_unique_per_operation_and_type() {
+        let shaders = shaders();
+        let unique = shaders
+            .iter()
+            .map(|shader| (shader.name, shader.op, shader.element_type))
+            .unique()
+            .count();
+
+        assert_eq!(unique, shaders.len());
+    }
+
+    #[test]
+    fn find_matches_the_element_type() {
+        assert!(find::<u32>(ReduceOp::Sum, "compute_buffer_to_buffer_kahan").is_none());
+        assert!(find::<f32>(ReduceOp::Sum, "compute_buffer_to_buffer_kahan").is_some());
+        assert_eq!(
+            find::<f32>(ReduceOp::Min, "compute_buffer_to_buffer")
+                .unwrap()
+                .element_type,
+            "f32"
+        );
+    }
+}
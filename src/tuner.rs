@@ -0,0 +1,245 @@
+use crate::{
+    auto_reducer::{AutoReducible, GpuExecutor, Strategy},
+    cpu::ReduceOp,
+    execute_util::generate_data,
+    executor::grid_layout,
+    results::Recorder,
+    vulkan_util::VulkanData,
+};
+use derivative::Derivative;
+use itertools::Itertools;
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Launch configuration of a GPU reduction
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct TuningConfig {
+    /// Local size of the compute shader, ignored by graphics pipelines
+    pub workgroup_size: u32,
+    /// Parallel invocations, i.e. `data_size.x`
+    pub thread_count: u32,
+    /// Rows of the framebuffer, 1 for compute
+    pub framebuffer_y: u32,
+    pub vectorization_factor: u32,
+}
+
+impl TuningConfig {
+    /// What the executors did before there was a tuner
+    pub fn fallback(vulkan: &VulkanData) -> Self {
+        Self {
+            workgroup_size: 64,
+            thread_count: vulkan.gpu_thread_count(),
+            framebuffer_y: 1,
+            vectorization_factor: 1,
+        }
+    }
+
+    /// Grid for `len` elements with at most [TuningConfig::thread_count]
+    /// invocations
+    pub fn layout(&self, len: usize) -> Vector2<u32> {
+        grid_layout(len, self.thread_count)
+    }
+}
+
+/// The best configuration found for reducing `elements` values
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TuningEntry {
+    pub operation: String,
+    pub element_type: String,
+    /// `graphics` or `compute`
+    pub backend: String,
+    pub elements: u32,
+    pub config: TuningConfig,
+    pub median_ns: f64,
+}
+
+/// The values [AutoTuner::tune] tries, every combination the device and
+/// backend support is measured
+#[derive(Derivative)]
+#[derivative(Default, Clone, Debug)]
+pub struct TuningSpace {
+    #[derivative(Default(value = "vec![32, 64, 128, 256, 512, 1024]"))]
+    pub workgroup_sizes: Vec<u32>,
+    /// The group sizes `benches/sizes.rs` sweeps
+    #[derivative(Default(value = "vec![64, 256, 512, 2048, 4096, 8192, 16384, 32768, 65536]"))]
+    pub thread_counts: Vec<u32>,
+    #[derivative(Default(value = "vec![1, 2, 4, 8, 16]"))]
+    pub framebuffer_ys: Vec<u32>,
+    /// Factors other than 1 need vectorized shaders, see
+    /// [crate::auto_reducer::GpuShaders]
+    #[derivative(Default(value = "vec![1, 4]"))]
+    pub vectorization_factors: Vec<u32>,
+    #[derivative(Default(value = "5"))]
+    pub iterations: usize,
+}
+
+impl TuningSpace {
+    /// Every configuration worth trying for `elements` values
+    pub fn configs(
+        &self,
+        vulkan: &VulkanData,
+        strategy: Strategy,
+        elements: u32,
+    ) -> Vec<TuningConfig> {
+        let (workgroup_sizes, framebuffer_ys) = match strategy {
+            Strategy::Graphics => (vec![64], self.framebuffer_ys.clone()),
+            _ => (
+                self.workgroup_sizes
+                    .iter()
+                    .copied()
+                    .filter(|size| *size <= vulkan.max_workgroup_size())
+                    .collect_vec(),
+                vec![1],
+            ),
+        };
+
+        // More threads than elements only adds idle invocations
+        let max_threads = elements.max(1).next_power_of_two();
+        let thread_counts = self
+            .thread_counts
+            .iter()
+            .copied()
+            .filter(|count| *count <= max_threads)
+            .chain(self.thread_counts.iter().copied().min())
+            .unique()
+            .collect_vec();
+
+        itertools::iproduct!(
+            workgroup_sizes,
+            thread_counts,
+            framebuffer_ys,
+            self.vectorization_factors.iter().copied()
+        )
+        .map(
+            |(workgroup_size, thread_count, framebuffer_y, vectorization_factor)| TuningConfig {
+                workgroup_size,
+                thread_count,
+                framebuffer_y,
+                vectorization_factor,
+            },
+        )
+        .filter(|config| {
+            config.thread_count % config.workgroup_size == 0
+                && config.thread_count % config.framebuffer_y == 0
+        })
+        .collect_vec()
+    }
+}
+
+/// Searches launch configurations of the GPU reductions and remembers the
+/// best ones per device in `tuning/` of [VulkanData::cache_dir], named like the
+/// pipeline cache
+pub struct AutoTuner {
+    path: PathBuf,
+    entries: Vec<TuningEntry>,
+    pub space: TuningSpace,
+}
+
+impl AutoTuner {
+    /// Loads the configurations tuned on this device in earlier runs
+    pub fn load(vulkan: &VulkanData) -> Self {
+        let path = VulkanData::cache_dir()
+            .join("tuning")
+            .join(format!("{}.json", vulkan.device_key()));
+
+        let entries = match std::fs::read(&path) {
+            Ok(data) => {
+                println!("Loading tuning results from {:?}", path);
+                serde_json::from_slice(&data).unwrap_or_else(|e| {
+                    println!("Ignoring tuning results {:?}: {e}", path);
+                    vec![]
+                })
+            },
+            Err(_) => vec![],
+        };
+
+        Self {
+            path,
+            entries,
+            space: Default::default(),
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        std::fs::create_dir_all(self.path.parent().unwrap())?;
+        std::fs::write(&self.path, serde_json::to_string_pretty(&self.entries)?)
+    }
+
+    pub fn entries(&self) -> &[TuningEntry] {
+        &self.entries
+    }
+
+    /// The tuned configuration for the measured size closest to `elements`
+    pub fn lookup<T: AutoReducible>(
+        &self,
+        op: ReduceOp,
+        strategy: Strategy,
+        elements: usize,
+    ) -> Option<&TuningEntry> {
+        let distance = |entry: &&TuningEntry| {
+            ((entry.elements.max(1) as f64).log2() - (elements.max(1) as f64).log2()).abs()
+        };
+
+        self.entries
+            .iter()
+            .filter(|entry| {
+                entry.operation == op.name()
+                    && entry.element_type == std::any::type_name::<T>()
+                    && entry.backend == strategy.backend()
+            })
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+    }
+
+    /// Measures every configuration of [AutoTuner::space] and keeps the
+    /// fastest, replacing an earlier result for the same size
+    pub fn tune<T: AutoReducible>(
+        &mut self,
+        vulkan: &mut VulkanData,
+        op: ReduceOp,
+        strategy: Strategy,
+        elements: u32,
+    ) -> TuningEntry {
+        let data = generate_data::<T>(elements).collect_vec();
+
+        let best = self
+            .space
+            .configs(vulkan, strategy, elements)
+            .into_iter()
+            .filter(|config| {
+                T::shaders(op)
+                    .and_then(|shaders| shaders.loader(strategy, config.vectorization_factor))
+                    .is_some()
+            })
+            .map(|config| {
+                let mut execute = GpuExecutor::setup(vulkan, op, strategy, &config, &data);
+
+                let mut recorder = Recorder::default();
+                for _ in 0..self.space.iterations {
+                    recorder.measure_cpu(|| execute.run(vulkan, true));
+                }
+                (config, recorder.wall_time().unwrap().median)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("No configuration to tune");
+
+        let entry = TuningEntry {
+            operation: op.name().to_string(),
+            element_type: std::any::type_name::<T>().to_string(),
+            backend: strategy.backend().to_string(),
+            elements,
+            config: best.0,
+            median_ns: best.1,
+        };
+
+        self.entries.retain(|e| {
+            !(e.operation == entry.operation
+                && e.element_type == entry.element_type
+                && e.backend == entry.backend
+                && e.elements == entry.elements)
+        });
+        self.entries.push(entry.clone());
+
+        entry
+    }
+}
@@ -1,34 +1,64 @@
+use crate::{
+    execute_util::GraphicsPipelineState,
+    execute_util_compute::{ComputePipelineState, SubgroupSizeRequest},
+};
+use ash::vk;
 use bytemuck::{Pod, Zeroable};
 use itertools::Itertools;
 use nalgebra::Vector2;
+use serde::{Serialize, Serializer};
 use smallvec::smallvec;
 use std::{
+    cell::Cell,
     collections::{hash_map::Entry, HashMap},
+    ffi::CString,
+    fmt::Debug,
+    path::PathBuf,
+    ptr,
     sync::Arc,
+    time::Duration,
 };
 use vulkano::{
-    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    buffer::{
+        view::{BufferView, BufferViewCreateInfo},
+        Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer,
+    },
     command_buffer::{
-        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, BufferImageCopy,
-        CommandBufferUsage, CopyBufferInfo, CopyBufferToImageInfo, CopyImageToBufferInfo,
-        PrimaryAutoCommandBuffer,
+        allocator::{
+            CommandBufferAllocator, CommandBufferBuilderAlloc, StandardCommandBufferAllocator,
+        },
+        sys::{CommandBufferBeginInfo, UnsafeCommandBufferBuilder},
+        AutoCommandBufferBuilder, BufferImageCopy, CommandBufferLevel, CommandBufferUsage,
+        CopyBufferInfo, CopyBufferToImageInfo, CopyImageToBufferInfo, PrimaryAutoCommandBuffer,
+        PrimaryCommandBufferAbstract,
     },
     descriptor_set::allocator::StandardDescriptorSetAllocator,
     device::{
-        physical::{PhysicalDevice, PhysicalDeviceType},
+        physical::{PhysicalDevice, PhysicalDeviceType, SubgroupFeatures},
         Device, DeviceCreateInfo, DeviceExtensions, DeviceOwned, Features, Queue, QueueCreateInfo,
         QueueFlags,
     },
-    format::Format,
+    format::{Format, FormatFeatures},
     image::{
-        AttachmentImage, ImageAccess, ImageCreateFlags, ImageDimensions, ImageSubresourceLayers,
-        ImageUsage, ImmutableImage, MipmapsCount, StorageImage,
+        AttachmentImage, ImageAccess, ImageCreateFlags, ImageDimensions, ImageLayout,
+        ImageSubresourceLayers, ImageUsage, ImmutableImage, MipmapsCount, SampleCount,
+        StorageImage,
     },
     instance::{Instance, InstanceCreateInfo},
     memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator},
-    pipeline::graphics::vertex_input::Vertex,
-    render_pass::RenderPass,
-    single_pass_renderpass, Version, VulkanLibrary,
+    pipeline::{
+        cache::PipelineCache, graphics::vertex_input::Vertex, ComputePipeline, GraphicsPipeline,
+        Pipeline, PipelineLayout,
+    },
+    query::{QueryPool, QueryPoolCreateInfo, QueryResultFlags, QueryType},
+    render_pass::{
+        AttachmentDescription, AttachmentReference, LoadOp, RenderPass, RenderPassCreateInfo,
+        StoreOp, SubpassDescription,
+    },
+    shader::{ShaderModule, ShaderStages, SpecializationConstants},
+    single_pass_renderpass,
+    sync::{fence::Fence, GpuFuture, PipelineStage},
+    DeviceSize, Version, VulkanLibrary, VulkanObject,
 };
 
 pub struct VulkanData {
@@ -44,7 +74,22 @@ pub struct VulkanData {
     render_pass_cache: HashMap<RenderPassKey, Arc<RenderPass>>,
     vertex_buffer: Subbuffer<[MVertex]>,
 
+    /// Driver side cache, persisted to [VulkanData::pipeline_cache_path]
+    pub pipeline_cache: Arc<PipelineCache>,
+    shader_cache: HashMap<usize, Arc<ShaderModule>>,
+    /// The loaders of the modules in `shader_cache`
+    shader_ids: HashMap<Arc<ShaderModule>, usize>,
+    graphics_pipeline_cache: HashMap<PipelineKey<GraphicsPipelineState>, Arc<GraphicsPipeline>>,
+    compute_pipeline_cache: HashMap<PipelineKey<ComputePipelineState>, Arc<ComputePipeline>>,
+    raw_compute_pipeline_cache: HashMap<PipelineKey<ComputePipelineState>, Arc<RawComputePipeline>>,
+
+    /// Timestamps written around every command buffer, see
+    /// [VulkanData::with_gpu_timing]
+    gpu_timer: Option<Arc<QueryPool>>,
+    last_gpu_time: Cell<Option<Duration>>,
+
     pub supports_fill_rectangle: bool,
+    pub supports_logic_op: bool,
 
     max_size: u32,
 }
@@ -52,6 +97,122 @@ pub struct VulkanData {
 #[derive(Copy, Clone, Eq, PartialEq, Hash)]
 pub struct RenderPassKey {
     pub format: Option<Format>,
+    /// Number of color attachments of `format`, ignored without a format
+    pub attachments: u32,
+}
+
+/// What a format can be used for on the device, images with optimal tiling
+#[derive(Copy, Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct FormatCapability {
+    #[serde(serialize_with = "serialize_format")]
+    pub format: Format,
+    pub color_attachment: bool,
+    pub blend: bool,
+    pub sampled_image: bool,
+    pub storage_image: bool,
+    pub storage_image_atomic: bool,
+    pub uniform_texel_buffer: bool,
+    pub storage_texel_buffer: bool,
+    pub storage_texel_buffer_atomic: bool,
+}
+
+fn serialize_format<S: Serializer>(format: &Format, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_args!("{format:?}"))
+}
+
+/// Which subgroup operations the device supports in which stages
+#[derive(Copy, Clone, Debug)]
+pub struct SubgroupSupport {
+    pub size: Option<u32>,
+    pub stages: ShaderStages,
+    pub operations: SubgroupFeatures,
+
+    /// Range of sizes with `subgroup_size_control`, pipelines may run with
+    /// any of them
+    pub min_size: Option<u32>,
+    pub max_size: Option<u32>,
+    /// Stages that can require a subgroup size, empty without
+    /// `subgroup_size_control`
+    pub size_control_stages: ShaderStages,
+    /// How many subgroups of a required size a workgroup may have
+    pub max_workgroup_subgroups: Option<u32>,
+    pub full_subgroups: bool,
+}
+
+impl SubgroupSupport {
+    /// What the subgroup writers in `shaders/pluggable/writer` use
+    pub fn writer_operations() -> SubgroupFeatures {
+        SubgroupFeatures::BASIC | SubgroupFeatures::ARITHMETIC | SubgroupFeatures::SHUFFLE
+    }
+
+    pub fn supports(&self, stages: ShaderStages, operations: SubgroupFeatures) -> bool {
+        self.size.is_some() && self.stages.contains(stages) && self.operations.contains(operations)
+    }
+}
+
+/// Identifies a built pipeline by its shader, see [VulkanData::shader_id], the
+/// values of its specialization constants and whatever other state the
+/// executor bakes into it
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct PipelineKey<State> {
+    pub shader: usize,
+    pub specialization: Vec<u8>,
+    pub state: State,
+}
+
+impl<State> PipelineKey<State> {
+    pub fn new<SC>(shader: usize, sc: &SC, state: State) -> Self
+    where
+        SC: SpecializationConstants,
+    {
+        // Only the bytes of the constants vulkano passes on, the padding between
+        // them is uninitialized
+        let base = sc as *const SC as *const u8;
+        let specialization = SC::descriptors()
+            .iter()
+            .flat_map(|entry| {
+                let value = unsafe {
+                    std::slice::from_raw_parts(base.add(entry.offset as usize), entry.size)
+                };
+                entry
+                    .constant_id
+                    .to_ne_bytes()
+                    .into_iter()
+                    .chain(value.iter().copied())
+            })
+            .collect();
+
+        Self {
+            shader,
+            specialization,
+            state,
+        }
+    }
+}
+
+/// A compute pipeline vulkano can't create, built with raw Vulkan from the
+/// shader, constants and layout of a vulkano pipeline. Bound with
+/// [VulkanData::submit_raw_and_wait]
+pub struct RawComputePipeline {
+    handle: vk::Pipeline,
+    base: Arc<ComputePipeline>,
+}
+
+impl RawComputePipeline {
+    pub fn handle(&self) -> vk::Pipeline {
+        self.handle
+    }
+
+    pub fn layout(&self) -> &Arc<PipelineLayout> {
+        self.base.layout()
+    }
+}
+
+impl Drop for RawComputePipeline {
+    fn drop(&mut self) {
+        let device = self.base.device();
+        unsafe { (device.fns().v1_0.destroy_pipeline)(device.handle(), self.handle, ptr::null()) };
+    }
 }
 
 #[derive(Pod, Zeroable, Copy, Clone, Vertex)]
@@ -62,10 +223,15 @@ pub struct MVertex {
 }
 
 impl VulkanData {
+    /// Picks the device by type, discrete GPUs first
     pub fn init() -> Self {
+        Self::init_device(None)
+    }
+
+    fn create_instance() -> Arc<Instance> {
         let library = VulkanLibrary::new().unwrap();
 
-        let instance = Instance::new(
+        Instance::new(
             library.clone(),
             InstanceCreateInfo {
                 max_api_version: Some(Version::V1_2),
@@ -73,11 +239,29 @@ impl VulkanData {
                 ..Default::default()
             },
         )
-        .unwrap();
+        .unwrap()
+    }
+
+    /// Every device in enumeration order, the indices [VulkanData::init_device]
+    /// takes
+    pub fn physical_devices() -> Vec<Arc<PhysicalDevice>> {
+        Self::create_instance()
+            .enumerate_physical_devices()
+            .unwrap()
+            .collect()
+    }
+
+    /// Uses the device at `index` of [VulkanData::physical_devices], or picks
+    /// one like [VulkanData::init]
+    pub fn init_device(index: Option<usize>) -> Self {
+        let instance = Self::create_instance();
 
         let (physical_device, queue_family_index, queue_compute) = instance
             .enumerate_physical_devices()
             .unwrap()
+            .enumerate()
+            .filter(|(i, _)| index.map_or(true, |index| index == *i))
+            .map(|(_, p)| p)
             .filter_map(|p| {
                 p.queue_family_properties()
                     .iter()
@@ -106,7 +290,7 @@ impl VulkanData {
                 PhysicalDeviceType::Other => 4,
                 _ => 5,
             })
-            .unwrap();
+            .expect("No device with graphics and compute queues");
 
         dbg!(physical_device.properties().device_type);
         dbg!(physical_device.supported_extensions().nv_fill_rectangle);
@@ -134,11 +318,21 @@ impl VulkanData {
             DeviceCreateInfo {
                 enabled_extensions: DeviceExtensions {
                     nv_fill_rectangle: physical_device.supported_extensions().nv_fill_rectangle,
+                    ext_subgroup_size_control: physical_device
+                        .supported_extensions()
+                        .ext_subgroup_size_control,
                     khr_swapchain: true,
                     ..Default::default()
                 },
                 enabled_features: Features {
                     fill_mode_non_solid: true,
+                    logic_op: physical_device.supported_features().logic_op,
+                    subgroup_size_control: physical_device
+                        .supported_features()
+                        .subgroup_size_control,
+                    compute_full_subgroups: physical_device
+                        .supported_features()
+                        .compute_full_subgroups,
                     ..Default::default()
                 },
                 queue_create_infos: queues,
@@ -194,6 +388,19 @@ impl VulkanData {
         );
         println!("Maximum overall size: {}", max_size);
 
+        let pipeline_cache_path = Self::pipeline_cache_path_for(&physical_device);
+        let pipeline_cache = match std::fs::read(&pipeline_cache_path) {
+            Ok(data) => {
+                println!("Loading pipeline cache from {:?}", pipeline_cache_path);
+                // The driver validates the header and ignores data that does not match
+                unsafe { PipelineCache::with_data(device.clone(), &data) }.unwrap_or_else(|e| {
+                    println!("Ignoring pipeline cache {:?}: {e}", pipeline_cache_path);
+                    PipelineCache::empty(device.clone()).unwrap()
+                })
+            },
+            Err(_) => PipelineCache::empty(device.clone()).unwrap(),
+        };
+
         Self {
             instance,
             physical_device: physical_device.clone(),
@@ -205,11 +412,52 @@ impl VulkanData {
             descriptor_set_allocator,
             render_pass_cache: Default::default(),
             vertex_buffer,
+            pipeline_cache,
+            shader_cache: Default::default(),
+            shader_ids: Default::default(),
+            graphics_pipeline_cache: Default::default(),
+            compute_pipeline_cache: Default::default(),
+            raw_compute_pipeline_cache: Default::default(),
+            gpu_timer: None,
+            last_gpu_time: Cell::new(None),
             supports_fill_rectangle: physical_device.supported_extensions().nv_fill_rectangle,
+            supports_logic_op: physical_device.supported_features().logic_op,
             max_size,
         }
     }
 
+    pub fn format_capabilities(&self, formats: &[Format]) -> Vec<FormatCapability> {
+        formats
+            .iter()
+            .map(|format| self.format_capability(*format))
+            .collect()
+    }
+
+    /// Formats the device doesn't know, e.g. from a missing extension, support
+    /// nothing
+    pub fn format_capability(&self, format: Format) -> FormatCapability {
+        let properties = self.physical_device.format_properties(format).ok();
+        let image = properties
+            .as_ref()
+            .map_or(FormatFeatures::empty(), |p| p.optimal_tiling_features);
+        let buffer = properties
+            .as_ref()
+            .map_or(FormatFeatures::empty(), |p| p.buffer_features);
+
+        FormatCapability {
+            format,
+            color_attachment: image.intersects(FormatFeatures::COLOR_ATTACHMENT),
+            blend: image.intersects(FormatFeatures::COLOR_ATTACHMENT_BLEND),
+            sampled_image: image.intersects(FormatFeatures::SAMPLED_IMAGE),
+            storage_image: image.intersects(FormatFeatures::STORAGE_IMAGE),
+            storage_image_atomic: image.intersects(FormatFeatures::STORAGE_IMAGE_ATOMIC),
+            uniform_texel_buffer: buffer.intersects(FormatFeatures::UNIFORM_TEXEL_BUFFER),
+            storage_texel_buffer: buffer.intersects(FormatFeatures::STORAGE_TEXEL_BUFFER),
+            storage_texel_buffer_atomic: buffer
+                .intersects(FormatFeatures::STORAGE_TEXEL_BUFFER_ATOMIC),
+        }
+    }
+
     pub fn profiling_sizes(&self) -> Vec<u32> {
         [1u32]
             .into_iter()
@@ -229,13 +477,308 @@ impl VulkanData {
         self.max_size
     }
 
+    /// Largest local size along x a compute shader can have on this device
+    pub fn max_workgroup_size(&self) -> u32 {
+        let properties = self.physical_device.properties();
+
+        properties.max_compute_work_group_size[0].min(properties.max_compute_work_group_invocations)
+    }
+
+    pub fn subgroup_support(&self) -> SubgroupSupport {
+        let properties = self.physical_device.properties();
+
+        let features = self.device.enabled_features();
+
+        SubgroupSupport {
+            size: properties.subgroup_size,
+            stages: properties
+                .subgroup_supported_stages
+                .unwrap_or(ShaderStages::empty()),
+            operations: properties
+                .subgroup_supported_operations
+                .unwrap_or(SubgroupFeatures::empty()),
+            min_size: properties.min_subgroup_size,
+            max_size: properties.max_subgroup_size,
+            size_control_stages: properties
+                .required_subgroup_size_stages
+                .filter(|_| features.subgroup_size_control)
+                .unwrap_or(ShaderStages::empty()),
+            max_workgroup_subgroups: properties.max_compute_workgroup_subgroups,
+            full_subgroups: features.compute_full_subgroups,
+        }
+    }
+
+    /// Whether the queue can write timestamps, see
+    /// [VulkanData::with_gpu_timing]
+    pub fn supports_gpu_timing(&self) -> bool {
+        let queue_family = &self.physical_device.queue_family_properties()
+            [self.queue.queue_family_index() as usize];
+        queue_family.timestamp_valid_bits.is_some()
+    }
+
+    fn enable_gpu_timing(&mut self) {
+        if self.gpu_timer.is_some() || !self.supports_gpu_timing() {
+            return;
+        }
+
+        self.gpu_timer = Some(
+            QueryPool::new(
+                self.device.clone(),
+                QueryPoolCreateInfo {
+                    query_count: 2,
+                    ..QueryPoolCreateInfo::query_type(QueryType::Timestamp)
+                },
+            )
+            .unwrap(),
+        );
+    }
+
+    /// Makes every command buffer created by
+    /// [VulkanData::create_command_buffer] and submitted with
+    /// [VulkanData::submit_and_wait] in `f` measure its execution time on the
+    /// GPU, if the queue supports timestamps. Keeps the timestamp overhead out
+    /// of everything else, e.g. criterion's measurements
+    pub fn with_gpu_timing<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let enabled = self.gpu_timer.is_some();
+        self.enable_gpu_timing();
+
+        let result = f(self);
+
+        if !enabled {
+            self.gpu_timer = None;
+            self.last_gpu_time.set(None);
+        }
+        result
+    }
+
+    /// GPU execution time of the last command buffer submitted with
+    /// [VulkanData::submit_and_wait], if timing is enabled
+    pub fn last_gpu_time(&self) -> Option<Duration> {
+        self.last_gpu_time.get()
+    }
+
     pub fn create_command_buffer(&self) -> AutoCommandBufferBuilder<PrimaryAutoCommandBuffer> {
-        AutoCommandBufferBuilder::primary(
+        let mut command_buffer = AutoCommandBufferBuilder::primary(
             &self.command_buffer_allocator,
             self.queue.queue_family_index(),
             CommandBufferUsage::OneTimeSubmit,
         )
-        .unwrap()
+        .unwrap();
+
+        if let Some(timer) = &self.gpu_timer {
+            unsafe {
+                command_buffer
+                    .reset_query_pool(timer.clone(), 0..2)
+                    .unwrap()
+                    .write_timestamp(timer.clone(), 0, PipelineStage::TopOfPipe)
+                    .unwrap();
+            }
+        }
+
+        command_buffer
+    }
+
+    pub fn submit_and_wait(
+        &self,
+        mut command_buffer: AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) {
+        if let Some(timer) = &self.gpu_timer {
+            unsafe {
+                command_buffer
+                    .write_timestamp(timer.clone(), 1, PipelineStage::BottomOfPipe)
+                    .unwrap();
+            }
+        }
+
+        command_buffer
+            .build()
+            .unwrap()
+            .execute(self.queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        self.read_gpu_time();
+    }
+
+    /// Records raw Vulkan commands into a primary command buffer and waits for
+    /// them like [VulkanData::submit_and_wait], for pipelines vulkano can't
+    /// bind
+    ///
+    /// # Safety
+    ///
+    /// `record` has to synchronize the resources it uses with barriers and keep
+    /// them alive until this returns
+    pub unsafe fn submit_raw_and_wait(&self, record: impl FnOnce(vk::CommandBuffer)) {
+        let fns = self.device.fns();
+
+        let alloc = self
+            .command_buffer_allocator
+            .allocate(
+                self.queue.queue_family_index(),
+                CommandBufferLevel::Primary,
+                1,
+            )
+            .unwrap()
+            .next()
+            .unwrap();
+        let builder = UnsafeCommandBufferBuilder::new(
+            alloc.inner(),
+            CommandBufferBeginInfo {
+                usage: CommandBufferUsage::OneTimeSubmit,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        if let Some(timer) = &self.gpu_timer {
+            (fns.v1_0.cmd_reset_query_pool)(builder.handle(), timer.handle(), 0, 2);
+            (fns.v1_0.cmd_write_timestamp)(
+                builder.handle(),
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                timer.handle(),
+                0,
+            );
+        }
+        record(builder.handle());
+        if let Some(timer) = &self.gpu_timer {
+            (fns.v1_0.cmd_write_timestamp)(
+                builder.handle(),
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                timer.handle(),
+                1,
+            );
+        }
+        let command_buffer = builder.build().unwrap();
+
+        let fence = Fence::new(self.device.clone(), Default::default()).unwrap();
+        let command_buffers = [command_buffer.handle()];
+        let submit_info = vk::SubmitInfo::builder().command_buffers(&command_buffers);
+        self.queue
+            .with(|_queue| {
+                (fns.v1_0.queue_submit)(self.queue.handle(), 1, &*submit_info, fence.handle())
+            })
+            .result()
+            .unwrap();
+        fence.wait(None).unwrap();
+
+        self.read_gpu_time();
+    }
+
+    fn read_gpu_time(&self) {
+        if let Some(timer) = &self.gpu_timer {
+            let mut timestamps = [0u64; 2];
+            timer
+                .queries_range(0..2)
+                .unwrap()
+                .get_results(&mut timestamps, QueryResultFlags::WAIT)
+                .unwrap();
+
+            let ticks = timestamps[1].wrapping_sub(timestamps[0]);
+            let period = self.physical_device.properties().timestamp_period as f64;
+            self.last_gpu_time
+                .set(Some(Duration::from_nanos((ticks as f64 * period) as u64)));
+        }
+    }
+
+    /// Measures the peak memory bandwidth in GB/s by copying `bytes` between
+    /// two device local buffers `iterations` times.
+    ///
+    /// Both the read and the write of the copy count towards the bandwidth. GPU
+    /// timestamps are used if the queue supports them, otherwise the wall time
+    /// of the submission.
+    pub fn measure_copy_bandwidth(&mut self, bytes: DeviceSize, iterations: u32) -> f64 {
+        self.with_gpu_timing(|vulkan| vulkan.measure_copy_bandwidth_timed(bytes, iterations))
+    }
+
+    fn measure_copy_bandwidth_timed(&self, bytes: DeviceSize, iterations: u32) -> f64 {
+        let create_buffer = |usage| {
+            Buffer::new_slice::<u32>(
+                &self.memory_allocator,
+                BufferCreateInfo {
+                    usage,
+                    ..Default::default()
+                },
+                AllocationCreateInfo {
+                    usage: MemoryUsage::DeviceOnly,
+                    ..Default::default()
+                },
+                bytes / 4,
+            )
+            .unwrap()
+        };
+        let source = create_buffer(BufferUsage::TRANSFER_SRC);
+        let destination = create_buffer(BufferUsage::TRANSFER_DST);
+
+        let mut best = f64::MAX;
+        for _ in 0..iterations {
+            let mut command_buffer = self.create_command_buffer();
+            command_buffer
+                .copy_buffer(CopyBufferInfo::buffers(source.clone(), destination.clone()))
+                .unwrap();
+
+            let start = std::time::Instant::now();
+            self.submit_and_wait(command_buffer);
+            let elapsed = self.last_gpu_time().unwrap_or_else(|| start.elapsed());
+
+            best = best.min(elapsed.as_nanos() as f64);
+        }
+
+        (2 * source.size()) as f64 / best
+    }
+
+    /// Creates the buffer shaders write their partial results into and the
+    /// buffer they are read back from, which is the same buffer unless
+    /// `separate_read_buffer` is set
+    pub fn create_output_buffer<T>(
+        &self,
+        len: DeviceSize,
+        separate_read_buffer: bool,
+    ) -> (Subbuffer<[T]>, Subbuffer<[T]>)
+    where
+        T: BufferContents,
+    {
+        let target: Subbuffer<[T]> = Buffer::new_slice(
+            &self.memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER
+                    | BufferUsage::TRANSFER_SRC
+                    | BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: if separate_read_buffer {
+                    MemoryUsage::DeviceOnly
+                } else {
+                    MemoryUsage::Download
+                },
+                ..Default::default()
+            },
+            len,
+        )
+        .unwrap();
+
+        let read_buffer = if separate_read_buffer {
+            Buffer::new_slice(
+                &self.memory_allocator,
+                BufferCreateInfo {
+                    usage: BufferUsage::TRANSFER_DST,
+                    ..Default::default()
+                },
+                AllocationCreateInfo {
+                    usage: MemoryUsage::Download,
+                    ..Default::default()
+                },
+                target.len(),
+            )
+            .unwrap()
+        } else {
+            target.clone()
+        };
+
+        (target, read_buffer)
     }
 
     pub fn create_target_image(&self, size: Vector2<u32>, format: Format) -> Arc<AttachmentImage> {
@@ -254,18 +797,34 @@ impl VulkanData {
             Entry::Vacant(e) => e
                 .insert(
                     if let Some(format) = key.format {
-                        single_pass_renderpass!(self.device.clone(),
-                            attachments: {
-                                color: {
-                                    load: Clear,
-                                    store: Store,
-                                    format: format,
-                                    samples: 1,
-                                },
-                            },
-                            pass: {
-                                color: [color],
-                                depth_stencil: {},
+                        let attachment = AttachmentDescription {
+                            format: Some(format),
+                            samples: SampleCount::Sample1,
+                            load_op: LoadOp::Clear,
+                            store_op: StoreOp::Store,
+                            initial_layout: ImageLayout::ColorAttachmentOptimal,
+                            final_layout: ImageLayout::ColorAttachmentOptimal,
+                            ..Default::default()
+                        };
+                        let color_attachments = (0..key.attachments)
+                            .map(|attachment| {
+                                Some(AttachmentReference {
+                                    attachment,
+                                    layout: ImageLayout::ColorAttachmentOptimal,
+                                    ..Default::default()
+                                })
+                            })
+                            .collect();
+
+                        RenderPass::new(
+                            self.device.clone(),
+                            RenderPassCreateInfo {
+                                attachments: vec![attachment; key.attachments as usize],
+                                subpasses: vec![SubpassDescription {
+                                    color_attachments,
+                                    ..Default::default()
+                                }],
+                                ..Default::default()
                             },
                         )
                     } else {
@@ -283,10 +842,196 @@ impl VulkanData {
         }
     }
 
+    /// Identifies device and driver in the names of files that only apply to
+    /// them
+    fn device_key_for(physical_device: &PhysicalDevice) -> String {
+        let properties = physical_device.properties();
+
+        format!(
+            "{}_{:x}",
+            properties
+                .device_uuid
+                .iter()
+                .flatten()
+                .map(|b| format!("{:02x}", b))
+                .join(""),
+            properties.driver_version,
+        )
+    }
+
+    pub fn device_key(&self) -> String {
+        Self::device_key_for(&self.physical_device)
+    }
+
+    /// Directory of the files kept between runs: `$GPU_COMPUTE_CACHE_DIR`, or
+    /// the `target/` directory of this crate whatever the working directory
+    pub fn cache_dir() -> PathBuf {
+        std::env::var_os("GPU_COMPUTE_CACHE_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target"))
+    }
+
+    fn pipeline_cache_path_for(physical_device: &PhysicalDevice) -> PathBuf {
+        Self::cache_dir()
+            .join("pipeline_cache")
+            .join(format!("{}.bin", Self::device_key_for(physical_device)))
+    }
+
+    pub fn pipeline_cache_path(&self) -> PathBuf {
+        Self::pipeline_cache_path_for(&self.physical_device)
+    }
+
+    pub fn save_pipeline_cache(&self) -> std::io::Result<()> {
+        let path = self.pipeline_cache_path();
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, self.pipeline_cache.get_data().unwrap())
+    }
+
+    /// Loads a shader module only once per shader, so that pipelines built from
+    /// it can be found in the pipeline caches again
+    pub fn load_shader<E>(
+        &mut self,
+        load: fn(Arc<Device>) -> Result<Arc<ShaderModule>, E>,
+    ) -> Arc<ShaderModule>
+    where
+        E: Debug,
+    {
+        match self.shader_cache.entry(load as usize) {
+            Entry::Occupied(e) => e.get().clone(),
+            Entry::Vacant(e) => {
+                let shader = e.insert(load(self.device.clone()).unwrap()).clone();
+                self.shader_ids.insert(shader.clone(), load as usize);
+                shader
+            },
+        }
+    }
+
+    /// Identifies a module from [VulkanData::load_shader] by its loader, which
+    /// stays the same for as long as the program runs
+    pub fn shader_id(&self, shader: &Arc<ShaderModule>) -> usize {
+        *self
+            .shader_ids
+            .get(shader)
+            .expect("Pipelines are only cached for shaders from VulkanData::load_shader")
+    }
+
+    pub fn graphics_pipeline<F>(
+        &mut self,
+        key: PipelineKey<GraphicsPipelineState>,
+        build: F,
+    ) -> Arc<GraphicsPipeline>
+    where
+        F: FnOnce(&mut Self) -> Arc<GraphicsPipeline>,
+    {
+        if let Some(pipeline) = self.graphics_pipeline_cache.get(&key) {
+            return pipeline.clone();
+        }
+
+        let pipeline = build(self);
+        self.graphics_pipeline_cache.insert(key, pipeline.clone());
+        pipeline
+    }
+
+    pub fn compute_pipeline<F>(
+        &mut self,
+        key: PipelineKey<ComputePipelineState>,
+        build: F,
+    ) -> Arc<ComputePipeline>
+    where
+        F: FnOnce(&mut Self) -> Arc<ComputePipeline>,
+    {
+        if let Some(pipeline) = self.compute_pipeline_cache.get(&key) {
+            return pipeline.clone();
+        }
+
+        let pipeline = build(self);
+        self.compute_pipeline_cache.insert(key, pipeline.clone());
+        pipeline
+    }
+
+    /// Builds `base` again with the subgroup size `key` requests, which vulkano
+    /// can't pass on to pipeline creation. `shader` and `sc` have to be what
+    /// `base` was built from
+    pub fn raw_compute_pipeline<SC>(
+        &mut self,
+        key: PipelineKey<ComputePipelineState>,
+        base: &Arc<ComputePipeline>,
+        shader: &Arc<ShaderModule>,
+        sc: &SC,
+    ) -> Arc<RawComputePipeline>
+    where
+        SC: SpecializationConstants,
+    {
+        if let Some(pipeline) = self.raw_compute_pipeline_cache.get(&key) {
+            return pipeline.clone();
+        }
+
+        let map_entries = SC::descriptors()
+            .iter()
+            .map(|entry| vk::SpecializationMapEntry {
+                constant_id: entry.constant_id,
+                offset: entry.offset,
+                size: entry.size,
+            })
+            .collect_vec();
+        let specialization = vk::SpecializationInfo {
+            map_entry_count: map_entries.len() as u32,
+            p_map_entries: map_entries.as_ptr(),
+            data_size: std::mem::size_of_val(sc),
+            p_data: sc as *const SC as *const _,
+        };
+
+        let name = CString::new("main").unwrap();
+        let mut required_size = vk::PipelineShaderStageRequiredSubgroupSizeCreateInfo::default();
+        let mut stage = vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(shader.handle())
+            .name(&name)
+            .specialization_info(&specialization);
+        match key.state.subgroup_size {
+            SubgroupSizeRequest::Any => {},
+            SubgroupSizeRequest::Required(size) => {
+                required_size.required_subgroup_size = size;
+                stage = stage.push_next(&mut required_size);
+            },
+            SubgroupSizeRequest::Full => {
+                stage = stage.flags(vk::PipelineShaderStageCreateFlags::REQUIRE_FULL_SUBGROUPS);
+            },
+        }
+        let create_info = vk::ComputePipelineCreateInfo::builder()
+            .stage(*stage)
+            .layout(base.layout().handle());
+
+        let fns = self.device.fns();
+        let mut handle = vk::Pipeline::null();
+        unsafe {
+            (fns.v1_0.create_compute_pipelines)(
+                self.device.handle(),
+                self.pipeline_cache.handle(),
+                1,
+                &*create_info,
+                ptr::null(),
+                &mut handle,
+            )
+        }
+        .result()
+        .unwrap();
+
+        let pipeline = Arc::new(RawComputePipeline {
+            handle,
+            base: base.clone(),
+        });
+        self.raw_compute_pipeline_cache
+            .insert(key, pipeline.clone());
+        pipeline
+    }
+
     pub fn vertex_buffer(&self) -> Subbuffer<[MVertex]> {
         self.vertex_buffer.clone()
     }
 
+    /// Copies `image` into a new host buffer, each texel holding one or more
+    /// `Px` values in component order
     pub fn download_image<Px, I>(
         &self,
         command_buffer: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
@@ -303,7 +1048,8 @@ impl VulkanData {
             .copied()
             .map(|i| i as usize)
             .sum::<usize>();
-        assert_eq!(pixel_bits, std::mem::size_of::<Px>() * 8);
+        assert_eq!(pixel_bits % (std::mem::size_of::<Px>() * 8), 0);
+        let values_per_pixel = (pixel_bits / (std::mem::size_of::<Px>() * 8)) as u64;
 
         let read_buffer = Buffer::new_slice::<Px>(
             &self.memory_allocator,
@@ -330,7 +1076,8 @@ impl VulkanData {
                     height,
                     depth,
                 } => width * height * depth,
-            } as u64,
+            } as u64
+                * values_per_pixel,
         )
         .unwrap();
 
@@ -420,6 +1167,61 @@ impl VulkanData {
         command_buffer: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
         iter: I,
     ) -> Subbuffer<[T]>
+    where
+        T: BufferContents,
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.create_device_buffer(command_buffer, iter, BufferUsage::STORAGE_BUFFER)
+    }
+
+    pub fn create_uniform_buffer<T, I>(
+        &self,
+        command_buffer: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        iter: I,
+    ) -> Subbuffer<[T]>
+    where
+        T: BufferContents,
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.create_device_buffer(command_buffer, iter, BufferUsage::UNIFORM_BUFFER)
+    }
+
+    /// A view of the data as texels of `format`, `usage` is either
+    /// [BufferUsage::UNIFORM_TEXEL_BUFFER] or
+    /// [BufferUsage::STORAGE_TEXEL_BUFFER]
+    pub fn create_texel_buffer<T, I>(
+        &self,
+        command_buffer: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        iter: I,
+        usage: BufferUsage,
+        format: Format,
+    ) -> Arc<BufferView>
+    where
+        T: BufferContents,
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let buffer = self.create_device_buffer(command_buffer, iter, usage);
+
+        BufferView::new(
+            buffer,
+            BufferViewCreateInfo {
+                format: Some(format),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    /// Uploads `iter` into device local memory through a staging buffer
+    fn create_device_buffer<T, I>(
+        &self,
+        command_buffer: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        iter: I,
+        usage: BufferUsage,
+    ) -> Subbuffer<[T]>
     where
         T: BufferContents,
         I: IntoIterator<Item = T>,
@@ -442,7 +1244,7 @@ impl VulkanData {
         let buffer = Buffer::new_slice(
             &self.memory_allocator,
             BufferCreateInfo {
-                usage: BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_DST,
+                usage: usage | BufferUsage::TRANSFER_DST,
                 ..Default::default()
             },
             AllocationCreateInfo::default(),
@@ -463,6 +1265,57 @@ impl VulkanData {
         iter: I,
         format: Format,
     ) -> Arc<StorageImage>
+    where
+        Px: BufferContents,
+        I: IntoIterator<Item = Px>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let iter = iter.into_iter();
+        let size = iter.len();
+
+        self.create_data_storage_image(
+            command_buffer,
+            ImageDimensions::Dim1d {
+                width: size as _,
+                array_layers: 1,
+            },
+            iter,
+            format,
+        )
+    }
+
+    pub fn create_2d_data_storage_image<Px, I>(
+        &self,
+        command_buffer: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        size: Vector2<u32>,
+        iter: I,
+        format: Format,
+    ) -> Arc<StorageImage>
+    where
+        Px: BufferContents,
+        I: IntoIterator<Item = Px>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.create_data_storage_image(
+            command_buffer,
+            ImageDimensions::Dim2d {
+                width: size.x,
+                height: size.y,
+                array_layers: 1,
+            },
+            iter,
+            format,
+        )
+    }
+
+    /// Uploads `iter` into a storage image, in the order of its texels
+    pub fn create_data_storage_image<Px, I>(
+        &self,
+        command_buffer: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        dimensions: ImageDimensions,
+        iter: I,
+        format: Format,
+    ) -> Arc<StorageImage>
     where
         Px: BufferContents,
         I: IntoIterator<Item = Px>,
@@ -476,9 +1329,6 @@ impl VulkanData {
             .sum::<usize>();
         assert_eq!(pixel_bits, std::mem::size_of::<Px>() * 8);
 
-        let iter = iter.into_iter();
-        let size = iter.len();
-
         let source = Buffer::from_iter(
             &self.memory_allocator,
             BufferCreateInfo {
@@ -492,13 +1342,11 @@ impl VulkanData {
             iter,
         )
         .unwrap();
+        assert_eq!(source.len(), dimensions.num_texels() as DeviceSize);
 
         let image = StorageImage::with_usage(
             &self.memory_allocator,
-            ImageDimensions::Dim1d {
-                width: size as _,
-                array_layers: 1,
-            },
+            dimensions,
             format,
             ImageUsage::TRANSFER_DST | ImageUsage::STORAGE,
             ImageCreateFlags::empty(),
@@ -528,3 +1376,63 @@ impl VulkanData {
         image
     }
 }
+
+impl Drop for VulkanData {
+    fn drop(&mut self) {
+        if let Err(e) = self.save_pipeline_cache() {
+            println!("Failed to save pipeline cache: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vulkano::shader::SpecializationMapEntry;
+
+    /// Has padding between its constants
+    #[derive(Copy, Clone)]
+    #[repr(C)]
+    struct Padded {
+        small: i32,
+        large: f64,
+    }
+
+    unsafe impl SpecializationConstants for Padded {
+        fn descriptors() -> &'static [SpecializationMapEntry] {
+            static DESCRIPTORS: [SpecializationMapEntry; 2] = [
+                SpecializationMapEntry {
+                    constant_id: 0,
+                    offset: 0,
+                    size: 4,
+                },
+                SpecializationMapEntry {
+                    constant_id: 1,
+                    offset: 8,
+                    size: 8,
+                },
+            ];
+            &DESCRIPTORS
+        }
+    }
+
+    #[test]
+    fn pipeline_keys_ignore_padding() {
+        let constants = Padded {
+            small: 3,
+            large: 0.5,
+        };
+        let mut filled = unsafe { std::mem::transmute::<[u8; 16], Padded>([0xff; 16]) };
+        filled.small = 3;
+        filled.large = 0.5;
+
+        assert!(PipelineKey::new(1, &constants, ()) == PipelineKey::new(1, &filled, ()));
+        assert!(PipelineKey::new(1, &constants, ()) != PipelineKey::new(2, &constants, ()));
+
+        let changed = Padded {
+            large: 1.5,
+            ..constants
+        };
+        assert!(PipelineKey::new(1, &constants, ()) != PipelineKey::new(1, &changed, ()));
+    }
+}